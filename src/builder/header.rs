@@ -0,0 +1,90 @@
+use std::ascii::AsciiExt;
+
+use checker;
+use parser::ast::*;
+use types::*;
+
+/// Generates a C header declaring every exported function of the program.
+/// `name` is used for the include guard.
+pub fn header(items: &Program, program: &checker::Program, name: &str) -> String {
+	let guard: String = name.chars().map(|c| {
+		if c.is_alphanumeric() { c.to_ascii_uppercase() } else { '_' }
+	}).collect();
+
+	let mut out = String::new();
+	out.push_str("/* Generated by thang. Do not edit. */\n");
+	out.push_str(&format!("#ifndef {}_H\n#define {}_H\n\n", guard, guard));
+	out.push_str("#include <stdbool.h>\n#include <stdint.h>\n\n");
+	out.push_str("#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+
+	for item in items {
+		if let Item::Function(ref def) = *item {
+			let function = program.scope.get_fn(&def.name.val).unwrap();
+			if !function.export { continue; }
+
+			let params: Vec<String> = function.params.iter().map(|&(ref name, var)| {
+				format!("{} {}", c_type(var.ty).unwrap(), name)
+			}).collect();
+			let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+			let ret = function.ret_ty.map_or("void", |ty| c_type(ty).unwrap());
+			let name = super::symbol(&def.name.val, function);
+			out.push_str(&format!("{} {}({});\n", ret, name, params));
+		}
+	}
+
+	out.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif\n");
+	out
+}
+
+/// The C type of a type, if it has one. The checker makes sure that exported functions
+/// only use types which do.
+pub fn c_type(ty: Type) -> Option<&'static str> {
+	Some(match ty {
+		Type::Bool                                      => "bool",
+		Type::Char                                      => "uint32_t",
+		Type::Num(NumType::Signed(    SignedType::I8 )) => "int8_t",
+		Type::Num(NumType::Signed(    SignedType::I16)) => "int16_t",
+		Type::Num(NumType::Signed(    SignedType::I32)) => "int32_t",
+		Type::Num(NumType::Signed(    SignedType::I64)) => "int64_t",
+		Type::Num(NumType::Signed(   SignedType::I128)) => "__int128",
+		Type::Num(NumType::Signed(  SignedType::ISize)) => "intptr_t",
+		Type::Num(NumType::Unsigned(UnsignedType::U8 )) => "uint8_t",
+		Type::Num(NumType::Unsigned(UnsignedType::U16)) => "uint16_t",
+		Type::Num(NumType::Unsigned(UnsignedType::U32)) => "uint32_t",
+		Type::Num(NumType::Unsigned(UnsignedType::U64)) => "uint64_t",
+		Type::Num(NumType::Unsigned(UnsignedType::U128))=> "unsigned __int128",
+		Type::Num(NumType::Unsigned(UnsignedType::USize))=> "uintptr_t",
+		Type::Num(NumType::Float(      FloatType::F16)) => "_Float16",
+		Type::Num(NumType::Float(      FloatType::F32)) => "float",
+		Type::Num(NumType::Float(      FloatType::F64)) => "double",
+		Type::Num(NumType::Float(     FloatType::F128)) => "__float128",
+		_ => return None,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use checker;
+	use parser;
+
+	#[test]
+	fn header_test() {
+		let (mut items, e) = parser::parse(r#"
+			@export fn add(a: I32, b: U8) -> F64 {
+				return 1.0
+			}
+			@export fn reset() {}
+			fn hidden() {}
+		"#);
+		assert_eq!(e, Vec::new());
+		let mut program = checker::Program::new();
+		checker::check_program(&mut items, &mut program).unwrap();
+
+		let out = header(&items, &program, "lib-math");
+		assert!(out.contains("#ifndef LIB_MATH_H"));
+		assert!(out.contains("double add(int32_t a, uint8_t b);\n"));
+		assert!(out.contains("void reset(void);\n"));
+		assert!(!out.contains("hidden"));
+	}
+}
@@ -1,14 +1,24 @@
-use std::{mem, ptr};
-use std::ffi::CString;
+use std::ptr;
+use std::ffi::{CStr, CString};
 
 use llvm_sys::*;
 use llvm_sys::core::*;
 use llvm_sys::prelude::*;
 use llvm_sys::analysis::*;
+use llvm_sys::target::*;
+use llvm_sys::target_machine::*;
 
-fn to_cstr(bstr: &[u8]) -> *const i8 {
-	let cstr = CString::new(bstr).unwrap();
-	unsafe { &mem::transmute::<&[u8], &[i8]>(cstr.as_bytes_with_nul())[0] }
+// The returned string must outlive the pointer handed to LLVM,
+// so call sites use `to_cstr(name).as_ptr()` within a single expression.
+fn to_cstr(bstr: &[u8]) -> CString {
+	CString::new(bstr).unwrap()
+}
+
+/// Copies a string LLVM allocated, and frees it.
+unsafe fn take_message(message: *mut i8) -> Vec<u8> {
+	let bytes = CStr::from_ptr(message).to_bytes().to_vec();
+	LLVMDisposeMessage(message);
+	bytes
 }
 
 macro_rules! type_constructor {
@@ -34,13 +44,24 @@ impl Type {
 	type_constructor!(i16 , LLVMTypeKind::LLVMIntegerTypeKind, LLVMInt16Type);
 	type_constructor!(i32 , LLVMTypeKind::LLVMIntegerTypeKind, LLVMInt32Type);
 	type_constructor!(i64 , LLVMTypeKind::LLVMIntegerTypeKind, LLVMInt64Type);
+	type_constructor!(f16 , LLVMTypeKind::LLVMHalfTypeKind   , LLVMHalfType);
 	type_constructor!(f32 , LLVMTypeKind::LLVMFloatTypeKind  , LLVMFloatType);
 	type_constructor!(f64 , LLVMTypeKind::LLVMDoubleTypeKind , LLVMDoubleType);
+	type_constructor!(f128, LLVMTypeKind::LLVMFP128TypeKind  , LLVMFP128Type);
+	type_constructor!(x86_fp80, LLVMTypeKind::LLVMX86_FP80TypeKind, LLVMX86FP80Type);
 	type_constructor!(void, LLVMTypeKind::LLVMVoidTypeKind   , LLVMVoidType);
 
+	pub fn int(width: u32) -> Type {
+		unsafe { Type { kind: LLVMTypeKind::LLVMIntegerTypeKind, r: LLVMIntType(width) } }
+	}
+
 	pub fn is_int(&self) -> bool {
 		self.kind == LLVMTypeKind::LLVMIntegerTypeKind
 	}
+	pub fn int_width(&self) -> u32 {
+		assert!(self.is_int());
+		unsafe { LLVMGetIntTypeWidth(self.r) }
+	}
 	pub fn is_real(&self) -> bool {
 		match self.kind {
 			LLVMTypeKind::LLVMHalfTypeKind      => true,
@@ -53,12 +74,44 @@ impl Type {
 		}
 	}
 
+	pub fn vector(elem: Type, lanes: u32) -> Type {
+		unsafe { Type { kind: LLVMTypeKind::LLVMVectorTypeKind, r: LLVMVectorType(elem.r, lanes) } }
+	}
+	pub fn is_vector(&self) -> bool {
+		self.kind == LLVMTypeKind::LLVMVectorTypeKind
+	}
+	pub fn vector_size(&self) -> u32 {
+		assert!(self.is_vector());
+		unsafe { LLVMGetVectorSize(self.r) }
+	}
+
+	pub fn pointer(ty: Type) -> Type {
+		unsafe { Type { kind: LLVMTypeKind::LLVMPointerTypeKind, r: LLVMPointerType(ty.r, 0) } }
+	}
+
+	pub fn structure(elems: &mut [Type]) -> Type {
+		let mut elems_vec: Vec<LLVMTypeRef> = elems.iter().map(|elem| elem.r).collect();
+		unsafe {
+			let elems_ptr = if elems_vec.is_empty() { ptr::null_mut() } else { &mut elems_vec[0] };
+			Type {
+				kind: LLVMTypeKind::LLVMStructTypeKind,
+				r: LLVMStructType(elems_ptr, elems_vec.len() as u32, 0),
+			}
+		}
+	}
+
 	pub fn function(ret: Type, params: &mut [Type]) -> Type {
+		Type::function_type(ret, params, false)
+	}
+	pub fn var_arg_function(ret: Type, params: &mut [Type]) -> Type {
+		Type::function_type(ret, params, true)
+	}
+	fn function_type(ret: Type, params: &mut [Type], var_arg: bool) -> Type {
 		if params.is_empty() {
 			unsafe {
 				return Type {
 					kind: LLVMTypeKind::LLVMFunctionTypeKind,
-					r: LLVMFunctionType(ret.r, ptr::null_mut(), 0, 0),
+					r: LLVMFunctionType(ret.r, ptr::null_mut(), 0, var_arg as LLVMBool),
 				};
 			}
 		}
@@ -69,7 +122,8 @@ impl Type {
 		unsafe {
 			Type {
 				kind: LLVMTypeKind::LLVMFunctionTypeKind,
-				r: LLVMFunctionType(ret.r, &mut params_vec[..][0], params_vec.len() as u32, 0),
+				r: LLVMFunctionType(ret.r, &mut params_vec[..][0], params_vec.len() as u32,
+				                    var_arg as LLVMBool),
 			}
 		}
 	}
@@ -88,20 +142,46 @@ impl Value {
 		unsafe { Value { ty: Type::new(LLVMTypeOf(val)), r: val } }
 	}
 
-	pub fn const_int(ty: Type, val: u64) -> Value {
+	/// An integer constant, truncated to the width of the type.
+	pub fn const_int(ty: Type, val: u128) -> Value {
 		assert!(ty.is_int());
-		unsafe { Value { ty: ty, r: LLVMConstInt(ty.r, val, 0) } }
+		let words = [val as u64, (val >> 64) as u64];
+		unsafe { Value { ty: ty, r: LLVMConstIntOfArbitraryPrecision(ty.r, 2, words.as_ptr()) } }
 	}
 	pub fn const_real(ty: Type, val: f64) -> Value {
 		assert!(ty.is_real());
 		unsafe { Value { ty: ty, r: LLVMConstReal(ty.r, val) } }
 	}
 
+	/// A vector constant with the constant in each of the lanes.
+	pub fn const_splat(val: Value, lanes: u32) -> Value {
+		let mut vals = vec![val.r; lanes as usize];
+		unsafe { Value::new(LLVMConstVector(&mut vals[0], lanes)) }
+	}
+
+	pub fn const_null(ty: Type) -> Value {
+		unsafe { Value { ty: ty, r: LLVMConstNull(ty.r) } }
+	}
+	pub fn undef(ty: Type) -> Value {
+		unsafe { Value { ty: ty, r: LLVMGetUndef(ty.r) } }
+	}
+	/// The size of the type in bytes, as an i64 constant.
+	pub fn size_of(ty: Type) -> Value {
+		unsafe { Value::new(LLVMSizeOf(ty.r)) }
+	}
+
 	pub fn get_type(&self) -> Type {
 		self.ty
 	}
-	/*pub fn set_name(&mut self, name: &[u8]) {
-		unsafe { LLVMSetValueName(self.r, to_cstr(name)); }
+	pub fn set_name(&mut self, name: &[u8]) {
+		unsafe { LLVMSetValueName(self.r, to_cstr(name).as_ptr()); }
+	}
+	pub fn set_linkage(&mut self, linkage: LLVMLinkage) {
+		unsafe { LLVMSetLinkage(self.r, linkage); }
+	}
+	/// Sets the initial value of a global, which must be a constant.
+	pub fn set_initializer(&mut self, val: Value) {
+		unsafe { LLVMSetInitializer(self.r, val.r); }
 	}
 
 	pub fn get_param(&mut self, index: u32) -> Value {
@@ -110,12 +190,12 @@ impl Value {
 			assert!(index < LLVMCountParams(self.r));
 			Value::new(LLVMGetParam(self.r, index))
 		}
-	}*/
+	}
 
 	pub fn append_basic_block(&mut self, name: &[u8]) -> BasicBlock {
 		unsafe {
 			assert!(self.ty.is_function());
-			BasicBlock { r: LLVMAppendBasicBlock(self.r, to_cstr(name)) }
+			BasicBlock { r: LLVMAppendBasicBlock(self.r, to_cstr(name).as_ptr()) }
 		}
 	}
 }
@@ -136,7 +216,7 @@ pub struct Module { r: LLVMModuleRef }
 impl Module {
 	/// Creates a new LLVM Module with the given name and context.
 	pub fn new(name: &[u8]) -> Module {
-		unsafe { Module { r: LLVMModuleCreateWithName(to_cstr(name)) } }
+		unsafe { Module { r: LLVMModuleCreateWithName(to_cstr(name).as_ptr()) } }
 	}
 	pub fn verify(&mut self) -> bool {
 		unsafe {
@@ -147,17 +227,27 @@ impl Module {
 	}
 	pub fn print(&mut self, filename: &[u8]) -> bool {
 		unsafe {
-			LLVMPrintModuleToFile(self.r, to_cstr(filename), ptr::null_mut()) == 0
+			LLVMPrintModuleToFile(self.r, to_cstr(filename).as_ptr(), ptr::null_mut()) == 0
 		}
 	}
-	pub fn add_function(&mut self, name: &[u8], func: Type) -> Value {
+	pub fn add_function(&mut self, name: &[u8], func: Type, call_conv: LLVMCallConv) -> Value {
 		assert_eq!(func.kind, LLVMTypeKind::LLVMFunctionTypeKind);
 		unsafe {
-			let val = LLVMAddFunction(self.r, to_cstr(name), func.r);
-			LLVMSetFunctionCallConv(val, LLVMCallConv::LLVMFastCallConv as u32);
+			let val = LLVMAddFunction(self.r, to_cstr(name).as_ptr(), func.r);
+			LLVMSetFunctionCallConv(val, call_conv as u32);
 			Value { ty: func, r: val }
 		}
 	}
+	pub fn set_data_layout(&mut self, layout: &[u8]) {
+		unsafe { LLVMSetDataLayout(self.r, to_cstr(layout).as_ptr()); }
+	}
+	pub fn set_target(&mut self, triple: &[u8]) {
+		unsafe { LLVMSetTarget(self.r, to_cstr(triple).as_ptr()); }
+	}
+	/// Adds a global variable. The value is a pointer to it.
+	pub fn add_global(&mut self, ty: Type, name: &[u8]) -> Value {
+		unsafe { Value::new(LLVMAddGlobal(self.r, ty.r, to_cstr(name).as_ptr())) }
+	}
 }
 impl Drop for Module {
 	fn drop(&mut self) {
@@ -165,13 +255,20 @@ impl Drop for Module {
 	}
 }
 
+#[derive(Copy, Clone, Debug)]
 pub struct BasicBlock { r: LLVMBasicBlockRef }
 impl BasicBlock {
 	pub fn insert(&mut self, name: &[u8]) -> BasicBlock {
-		unsafe { BasicBlock { r: LLVMInsertBasicBlock(self.r, to_cstr(name)) } }
+		unsafe { BasicBlock { r: LLVMInsertBasicBlock(self.r, to_cstr(name).as_ptr()) } }
 	}
 }
 
+pub const CALL_C:    LLVMCallConv = LLVMCallConv::LLVMCCallConv;
+pub const CALL_FAST: LLVMCallConv = LLVMCallConv::LLVMFastCallConv;
+
+pub const LINK_EXTERNAL: LLVMLinkage = LLVMLinkage::LLVMExternalLinkage;
+pub const LINK_INTERNAL: LLVMLinkage = LLVMLinkage::LLVMInternalLinkage;
+
 pub const INT_EQ:    LLVMIntPredicate =  LLVMIntPredicate::LLVMIntEQ;
 pub const INT_NE:    LLVMIntPredicate =  LLVMIntPredicate::LLVMIntNE;
 pub const INT_UGT:   LLVMIntPredicate =  LLVMIntPredicate::LLVMIntUGT;
@@ -192,17 +289,99 @@ pub const REAL_ONE: LLVMRealPredicate = LLVMRealPredicate::LLVMRealONE;
 macro_rules! builder_binop {
 	($func: ident, $llvm: ident) => {
 		pub fn $func(&mut self, lhs: Value, rhs: Value, name: &[u8]) -> Value {
-			unsafe { Value::new($llvm(self.r, lhs.r, rhs.r, to_cstr(name))) }
+			unsafe { Value::new($llvm(self.r, lhs.r, rhs.r, to_cstr(name).as_ptr())) }
 		}
 	}
 }
 macro_rules! builder_unop {
 	($func: ident, $llvm: ident) => {
 		pub fn $func(&mut self, val: Value, name: &[u8]) -> Value {
-			unsafe { Value::new($llvm(self.r, val.r, to_cstr(name))) }
+			unsafe { Value::new($llvm(self.r, val.r, to_cstr(name).as_ptr())) }
+		}
+	}
+}
+macro_rules! builder_cast {
+	($func: ident, $llvm: ident) => {
+		pub fn $func(&mut self, val: Value, ty: Type, name: &[u8]) -> Value {
+			unsafe { Value::new($llvm(self.r, val.r, ty.r, to_cstr(name).as_ptr())) }
+		}
+	}
+}
+/// The machine code is generated for, which is the default target of LLVM.
+pub struct TargetMachine { r: LLVMTargetMachineRef }
+impl TargetMachine {
+	pub fn host() -> TargetMachine {
+		unsafe {
+			initialize_native_target();
+			let triple = take_message(LLVMGetDefaultTargetTriple());
+			let mut target = ptr::null_mut();
+			let mut error = ptr::null_mut();
+			if LLVMGetTargetFromTriple(to_cstr(&triple).as_ptr(), &mut target, &mut error) != 0 {
+				panic!("No LLVM target for '{}': {}", String::from_utf8_lossy(&triple),
+				       String::from_utf8_lossy(&take_message(error)));
+			}
+			let r = LLVMCreateTargetMachine(target, to_cstr(&triple).as_ptr(), to_cstr(b"").as_ptr(),
+			                                to_cstr(b"").as_ptr(), LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault,
+			                                LLVMRelocMode::LLVMRelocDefault, LLVMCodeModel::LLVMCodeModelDefault);
+			TargetMachine { r: r }
 		}
 	}
+	pub fn triple(&self) -> Vec<u8> {
+		unsafe { take_message(LLVMGetTargetMachineTriple(self.r)) }
+	}
+	pub fn data(&self) -> TargetData {
+		unsafe { TargetData { r: LLVMCreateTargetDataLayout(self.r) } }
+	}
+}
+impl Drop for TargetMachine {
+	fn drop(&mut self) {
+		unsafe { LLVMDisposeTargetMachine(self.r) }
+	}
 }
+
+/// Registers the target of the host with LLVM, which the C interface does with macros.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+unsafe fn initialize_native_target() {
+	LLVMInitializeX86TargetInfo();
+	LLVMInitializeX86Target();
+	LLVMInitializeX86TargetMC();
+}
+#[cfg(target_arch = "aarch64")]
+unsafe fn initialize_native_target() {
+	LLVMInitializeAArch64TargetInfo();
+	LLVMInitializeAArch64Target();
+	LLVMInitializeAArch64TargetMC();
+}
+#[cfg(target_arch = "arm")]
+unsafe fn initialize_native_target() {
+	LLVMInitializeARMTargetInfo();
+	LLVMInitializeARMTarget();
+	LLVMInitializeARMTargetMC();
+}
+
+/// The sizes and alignments of types for a data layout.
+pub struct TargetData { r: LLVMTargetDataRef }
+impl TargetData {
+	/// The layout as LLVM writes it in modules.
+	pub fn layout(&self) -> Vec<u8> {
+		unsafe { take_message(LLVMCopyStringRepOfTargetData(self.r)) }
+	}
+	pub fn pointer_width(&self) -> u32 {
+		unsafe { LLVMPointerSize(self.r) * 8 }
+	}
+	pub fn size_of(&self, ty: Type) -> u64 {
+		unsafe { LLVMABISizeOfType(self.r, ty.r) }
+	}
+	pub fn align_of(&self, ty: Type) -> u32 {
+		unsafe { LLVMABIAlignmentOfType(self.r, ty.r) }
+	}
+}
+impl Drop for TargetData {
+	fn drop(&mut self) {
+		unsafe { LLVMDisposeTargetData(self.r) }
+	}
+}
+
 pub struct Builder { r: LLVMBuilderRef }
 impl Builder {
 	pub fn new(block: BasicBlock) -> Builder {
@@ -212,6 +391,57 @@ impl Builder {
 			builder
 		}
 	}
+	/// A builder which is not positioned in any block. Instructions on constants are folded,
+	/// so it can build constant expressions.
+	pub fn detached() -> Builder {
+		unsafe { Builder { r: LLVMCreateBuilder() } }
+	}
+
+	pub fn position_at_end(&mut self, block: BasicBlock) {
+		unsafe { LLVMPositionBuilderAtEnd(self.r, block.r) }
+	}
+	/// Whether the block being built already ends in a terminator such as `ret`.
+	pub fn is_terminated(&self) -> bool {
+		unsafe { !LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.r)).is_null() }
+	}
+
+	pub fn br(&mut self, dest: BasicBlock) -> Value {
+		unsafe { Value::new(LLVMBuildBr(self.r, dest.r)) }
+	}
+	pub fn cond_br(&mut self, cond: Value, then: BasicBlock, els: BasicBlock) -> Value {
+		unsafe { Value::new(LLVMBuildCondBr(self.r, cond.r, then.r, els.r)) }
+	}
+	pub fn unreachable(&mut self) -> Value {
+		unsafe { Value::new(LLVMBuildUnreachable(self.r)) }
+	}
+
+	/// Calls a function with its own calling convention.
+	pub fn call(&mut self, func: Value, args: &mut [Value], name: &[u8]) -> Value {
+		let call_conv = unsafe { LLVMGetFunctionCallConv(func.r) };
+		self.build_call(func, args, call_conv, name)
+	}
+	/// Calls a function pointer, which has no calling convention of its own.
+	pub fn call_with(&mut self, func: Value, args: &mut [Value], call_conv: LLVMCallConv,
+	                 name: &[u8]) -> Value {
+		self.build_call(func, args, call_conv as u32, name)
+	}
+	fn build_call(&mut self, func: Value, args: &mut [Value], call_conv: u32,
+	              name: &[u8]) -> Value {
+		let mut args_vec: Vec<LLVMValueRef> = args.iter().map(|arg| arg.r).collect();
+		unsafe {
+			let args_ptr = if args_vec.is_empty() { ptr::null_mut() } else { &mut args_vec[0] };
+			let val = LLVMBuildCall(self.r, func.r, args_ptr, args_vec.len() as u32,
+			                        to_cstr(name).as_ptr());
+			LLVMSetInstructionCallConv(val, call_conv);
+			Value::new(val)
+		}
+	}
+	pub fn global_string_ptr(&mut self, string: &[u8], name: &[u8]) -> Value {
+		unsafe {
+			Value::new(LLVMBuildGlobalStringPtr(self.r, to_cstr(string).as_ptr(),
+			                                    to_cstr(name).as_ptr()))
+		}
+	}
 
 	pub fn ret_void(&mut self) -> Value {
 		unsafe { Value::new(LLVMBuildRetVoid(self.r)) }
@@ -220,12 +450,15 @@ impl Builder {
 		unsafe { Value::new(LLVMBuildRet(self.r, val.r)) }
 	}
 
+	builder_binop!(    add, LLVMBuildAdd);
 	builder_binop!(nsw_add, LLVMBuildNSWAdd);
 	builder_binop!(nuw_add, LLVMBuildNUWAdd);
 	builder_binop!(  f_add, LLVMBuildFAdd);
+	builder_binop!(    sub, LLVMBuildSub);
 	builder_binop!(nsw_sub, LLVMBuildNSWSub);
 	builder_binop!(nuw_sub, LLVMBuildNUWSub);
 	builder_binop!(  f_sub, LLVMBuildFSub);
+	builder_binop!(    mul, LLVMBuildMul);
 	builder_binop!(nsw_mul, LLVMBuildNSWMul);
 	builder_binop!(nuw_mul, LLVMBuildNUWMul);
 	builder_binop!(  f_mul, LLVMBuildFMul);
@@ -239,23 +472,62 @@ impl Builder {
 	builder_binop!(     or, LLVMBuildOr);
 
 	builder_unop!(    not, LLVMBuildNot);
+	builder_unop!(    neg, LLVMBuildNeg);
 	builder_unop!(nsw_neg, LLVMBuildNSWNeg);
 	builder_unop!(nuw_neg, LLVMBuildNUWNeg);
 	builder_unop!(  f_neg, LLVMBuildFNeg);
 
+	builder_cast!(s_ext_or_bit_cast, LLVMBuildSExtOrBitCast);
+	builder_cast!(z_ext_or_bit_cast, LLVMBuildZExtOrBitCast);
+	builder_cast!(           fp_ext, LLVMBuildFPExt);
+	builder_cast!(         bit_cast, LLVMBuildBitCast);
+	builder_cast!(            trunc, LLVMBuildTrunc);
+	builder_cast!(            s_ext, LLVMBuildSExt);
+	builder_cast!(            z_ext, LLVMBuildZExt);
+	builder_cast!(         fp_trunc, LLVMBuildFPTrunc);
+	builder_cast!(         si_to_fp, LLVMBuildSIToFP);
+	builder_cast!(         ui_to_fp, LLVMBuildUIToFP);
+
+	pub fn select(&mut self, cond: Value, then: Value, els: Value, name: &[u8]) -> Value {
+		unsafe { Value::new(LLVMBuildSelect(self.r, cond.r, then.r, els.r, to_cstr(name).as_ptr())) }
+	}
+
+	pub fn extract_value(&mut self, agg: Value, index: u32, name: &[u8]) -> Value {
+		unsafe { Value::new(LLVMBuildExtractValue(self.r, agg.r, index, to_cstr(name).as_ptr())) }
+	}
+	pub fn insert_value(&mut self, agg: Value, val: Value, index: u32, name: &[u8]) -> Value {
+		unsafe {
+			Value::new(LLVMBuildInsertValue(self.r, agg.r, val.r, index, to_cstr(name).as_ptr()))
+		}
+	}
+	pub fn extract_element(&mut self, vector: Value, index: Value, name: &[u8]) -> Value {
+		unsafe {
+			Value::new(LLVMBuildExtractElement(self.r, vector.r, index.r, to_cstr(name).as_ptr()))
+		}
+	}
+	pub fn insert_element(&mut self, vector: Value, val: Value, index: Value, name: &[u8]) -> Value {
+		unsafe {
+			Value::new(LLVMBuildInsertElement(self.r, vector.r, val.r, index.r, to_cstr(name).as_ptr()))
+		}
+	}
+	pub fn struct_gep(&mut self, ptr: Value, index: u32, name: &[u8]) -> Value {
+		assert!(ptr.ty.kind == LLVMTypeKind::LLVMPointerTypeKind);
+		unsafe { Value::new(LLVMBuildStructGEP(self.r, ptr.r, index, to_cstr(name).as_ptr())) }
+	}
+
 	pub fn i_cmp(&mut self, op: LLVMIntPredicate,   l: Value, r: Value, name: &[u8]) -> Value {
-		unsafe { Value::new(LLVMBuildICmp(self.r, op, l.r, r.r, to_cstr(name))) }
+		unsafe { Value::new(LLVMBuildICmp(self.r, op, l.r, r.r, to_cstr(name).as_ptr())) }
 	}
 	pub fn f_cmp(&mut self, op: LLVMRealPredicate, l: Value, r: Value, name: &[u8]) -> Value {
-		unsafe { Value::new(LLVMBuildFCmp(self.r, op, l.r, r.r, to_cstr(name))) }
+		unsafe { Value::new(LLVMBuildFCmp(self.r, op, l.r, r.r, to_cstr(name).as_ptr())) }
 	}
 
 	pub fn alloca(&mut self, ty: Type, name: &[u8]) -> Value {
-		unsafe { Value::new(LLVMBuildAlloca(self.r, ty.r, to_cstr(name))) }
+		unsafe { Value::new(LLVMBuildAlloca(self.r, ty.r, to_cstr(name).as_ptr())) }
 	}
 	pub fn load(&mut self, ptr: Value, name: &[u8]) -> Value {
 		assert!(ptr.ty.kind == LLVMTypeKind::LLVMPointerTypeKind);
-		unsafe { Value::new(LLVMBuildLoad(self.r, ptr.r, to_cstr(name))) }
+		unsafe { Value::new(LLVMBuildLoad(self.r, ptr.r, to_cstr(name).as_ptr())) }
 	}
 	pub fn store(&mut self, val: Value, ptr: Value) -> Value {
 		assert!(ptr.ty.kind == LLVMTypeKind::LLVMPointerTypeKind);
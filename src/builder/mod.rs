@@ -0,0 +1,1006 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use checker::{self, Scope};
+use parser::FullToken;
+use parser::ast::*;
+use types::*;
+
+pub mod llvm;
+pub mod header;
+
+/// Picks the signed, unsigned or float version of an instruction for the given type.
+/// Booleans count as unsigned, and vectors go by the type of their lanes.
+macro_rules! by_type {
+	($ty: expr, $signed: expr, $unsigned: expr, $float: expr) => {
+		match $ty.elem() {
+			Type::Num(NumType::Signed(_)) => $signed,
+			Type::Num(NumType::Float(_))  => $float,
+			_                             => $unsigned,
+		}
+	}
+}
+
+// The machine the program is compiled for, along with its data layout, which the sizes
+// and alignments of types and the width of pointers come from.
+thread_local!(static TARGET: (llvm::TargetMachine, llvm::TargetData) = {
+	let machine = llvm::TargetMachine::host();
+	let data = machine.data();
+	(machine, data)
+});
+
+/// The size and alignment of a type in bytes.
+pub fn size_align(ty: Type) -> (u64, u32) {
+	let ty = llvm_type(ty);
+	TARGET.with(|&(_, ref data)| (data.size_of(ty), data.align_of(ty)))
+}
+
+/// The width in bits of pointers on the target, and so of `ISize` and `USize`.
+pub fn pointer_width() -> u32 {
+	TARGET.with(|&(_, ref data)| data.pointer_width())
+}
+
+/// What happens when integer arithmetic overflows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+	/// Abort with where the operation is.
+	Trap,
+	/// Wrap around, as two's complement does.
+	Wrap,
+	/// Assume that it does not happen, so that LLVM can optimize more.
+	Unchecked,
+}
+
+/// How the program is built.
+pub struct Options<'a> {
+	/// The paths of the program's files, for reporting where runtime checks fail.
+	pub files: &'a [PathBuf],
+	/// Leaves out `@assert` and the bounds checks of lane indices.
+	pub release: bool,
+	pub overflow: Overflow,
+	/// Checks integer division and remainder for division by zero and overflow.
+	pub div_checks: bool,
+}
+
+/// Builds the checked program into an LLVM module and prints it to the given file.
+/// Returns false if the generated module does not verify.
+pub fn build(items: &Program, program: &mut checker::Program, options: &Options,
+             filename: &str) -> bool {
+	let mut module = llvm::Module::new(b"thang");
+	TARGET.with(|&(ref machine, ref data)| {
+		module.set_target(&machine.triple());
+		module.set_data_layout(&data.layout());
+	});
+	let mut consts = HashMap::new();
+
+	// Declare every function first so that their order does not matter.
+	for item in items {
+		match *item {
+			Item::Const(ref id, _, ref expr) => { consts.insert(&id.val[..], expr); },
+			Item::Static(ref id, _, ref expr) => {
+				let var = program.scope.get_var(&id.val).unwrap();
+				let mut global = module.add_global(llvm_type(var.ty), format!("thang.{}", id.val).as_bytes());
+				global.set_linkage(llvm::LINK_INTERNAL);
+				global.set_initializer(build_const(expr, &consts));
+				var.llvm = Some(global);
+			},
+			Item::Function(ref def) if def.generics.is_empty() => {
+				let function = program.scope.get_fn_mut(&def.name.val).unwrap();
+				// Closures take their environment first.
+				let env = function.captures.is_some() as u32;
+				let mut params: Vec<llvm::Type> = function.captures.iter().map(|_| byte_ptr()).collect();
+				params.extend(function.params.iter().map(|&(_, var)| llvm_type(var.ty)));
+				let ret = function.ret_ty.map_or(llvm::Type::void(), llvm_type);
+				let ty  = llvm::Type::function(ret, &mut params[..]);
+
+				let symbol = symbol(&def.name.val, function);
+				let mut val = if function.export || def.name.val == "main" {
+					module.add_function(symbol.as_bytes(), ty, llvm::CALL_C)
+				} else {
+					let mut val = module.add_function(symbol.as_bytes(), ty, llvm::CALL_FAST);
+					val.set_linkage(llvm::LINK_INTERNAL);
+					val
+				};
+				if env == 1 {
+					val.get_param(0).set_name(b"env");
+				}
+				for (i, &(ref param, _)) in def.params.iter().enumerate() {
+					val.get_param(i as u32 + env).set_name(param.val.as_bytes());
+				}
+				function.llvm = Some(val);
+			},
+			// Only the instances of generic functions are built.
+			Item::Function(_) | Item::TypeAlias(..) | Item::Import(_) => (),
+		}
+	}
+
+	let mut builder = Builder {
+		module: module,
+		consts: consts,
+		options: options,
+		externs: HashMap::new(),
+		fn_values: HashMap::new(),
+	};
+	for item in items {
+		if let Item::Function(ref def) = *item {
+			if !def.generics.is_empty() { continue; }
+			let function = program.scope.get_fn(&def.name.val).unwrap().clone();
+			builder.build_function(def, &function, program.scope.enter());
+		}
+	}
+	program.scope.rewind();
+
+	let valid = builder.module.verify();
+	builder.module.print(filename.as_bytes());
+	valid
+}
+
+/// The symbol a function is emitted under.
+/// Exported functions keep their own name, without their module, so they can be linked
+/// against, and `main` is the entry point called by the shim.
+pub fn symbol(name: &str, function: &checker::Function) -> String {
+	if function.export {
+		name.rsplit('.').next().unwrap().to_string()
+	} else if name == "main" {
+		"thang_main".to_string()
+	} else {
+		format!("thang.{}", name)
+	}
+}
+
+pub fn llvm_type(ty: Type) -> llvm::Type {
+	match ty.complete() {
+		Type::Bool                                  => llvm::Type::bool(),
+		Type::Char                                  => llvm::Type::i32(),
+		Type::Num(NumType::Signed(SignedType::I8))  |
+		Type::Num(NumType::Unsigned(UnsignedType::U8))  => llvm::Type::i8(),
+		Type::Num(NumType::Signed(SignedType::I16)) |
+		Type::Num(NumType::Unsigned(UnsignedType::U16)) => llvm::Type::i16(),
+		Type::Num(NumType::Signed(SignedType::I32)) |
+		Type::Num(NumType::Unsigned(UnsignedType::U32)) => llvm::Type::i32(),
+		Type::Num(NumType::Signed(SignedType::I64)) |
+		Type::Num(NumType::Unsigned(UnsignedType::U64)) => llvm::Type::i64(),
+		Type::Num(NumType::Signed(SignedType::I128)) |
+		Type::Num(NumType::Unsigned(UnsignedType::U128)) => llvm::Type::int(128),
+		Type::Num(NumType::Signed(SignedType::ISize)) |
+		Type::Num(NumType::Unsigned(UnsignedType::USize)) => llvm::Type::int(pointer_width()),
+		Type::Num(NumType::Float(FloatType::F16))   => llvm::Type::f16(),
+		Type::Num(NumType::Float(FloatType::F32))   => llvm::Type::f32(),
+		Type::Num(NumType::Float(FloatType::F64))   => llvm::Type::f64(),
+		Type::Num(NumType::Float(FloatType::F128))  => llvm::Type::f128(),
+		Type::Vector(num, lanes) => llvm::Type::vector(llvm_type(Type::Num(num)), lanes),
+		// A pointer to the function, and its environment.
+		Type::Fn(_) => llvm::Type::structure(&mut [byte_ptr(), byte_ptr()]),
+		ty => panic!("No LLVM type for '{}'.", ty),
+	}
+}
+
+fn byte_ptr() -> llvm::Type {
+	llvm::Type::pointer(llvm::Type::i8())
+}
+
+/// The environment of a closure, which holds the values of its captures.
+fn env_type(captures: &[(String, checker::Variable)]) -> llvm::Type {
+	let mut elems: Vec<llvm::Type> = captures.iter().map(|&(_, var)| llvm_type(var.ty)).collect();
+	llvm::Type::structure(&mut elems[..])
+}
+
+struct Builder<'a> {
+	module: llvm::Module,
+	consts: HashMap<&'a str, &'a Expr>,
+	options: &'a Options<'a>,
+	/// The external functions which have been declared.
+	externs: HashMap<String, llvm::Value>,
+	/// The functions which let named functions be called as function values.
+	fn_values: HashMap<String, llvm::Value>,
+}
+
+/// The state of the function currently being built.
+struct FnBuilder {
+	val: llvm::Value,
+	builder: llvm::Builder,
+	/// Stack slots are allocated in the entry block so that loops do not grow the stack.
+	allocas: llvm::Builder,
+}
+
+impl<'a> Builder<'a> {
+	fn build_function(&mut self, def: &FnDef, function: &checker::Function, scope: &mut Scope) {
+		let mut val = function.llvm.unwrap();
+		let entry = val.append_basic_block(b"entry");
+		let body  = val.append_basic_block(b"body");
+		let mut func = FnBuilder {
+			val: val,
+			builder: llvm::Builder::new(body),
+			allocas: llvm::Builder::new(entry),
+		};
+
+		// Captures are copied out of the environment, so each call starts with their values.
+		let mut env_len = 0;
+		if let Some(ref captures) = function.captures {
+			env_len = 1;
+			let env_ptr = llvm::Type::pointer(env_type(captures));
+			let env = func.val.get_param(0);
+			let env = func.allocas.bit_cast(env, env_ptr, b"env");
+			for (i, &(ref name, var)) in captures.iter().enumerate() {
+				let ptr = func.allocas.struct_gep(env, i as u32, b"");
+				let val = func.allocas.load(ptr, name.as_bytes());
+				let ptr = func.allocas.alloca(llvm_type(var.ty), name.as_bytes());
+				func.allocas.store(val, ptr);
+				scope.get_var(name).unwrap().llvm = Some(ptr);
+			}
+		}
+		for (i, &(ref name, var)) in function.params.iter().enumerate() {
+			let ptr = func.allocas.alloca(llvm_type(var.ty), name.as_bytes());
+			let param = func.val.get_param(i as u32 + env_len);
+			func.allocas.store(param, ptr);
+			scope.get_var(name).unwrap().llvm = Some(ptr);
+		}
+
+		self.build_block(&mut func, &def.block.val, scope);
+		// The checker makes sure that functions with a return type cannot reach their end.
+		if !func.builder.is_terminated() {
+			match function.ret_ty {
+				None    => func.builder.ret_void(),
+				Some(_) => func.builder.unreachable(),
+			};
+		}
+		func.allocas.br(body);
+	}
+
+	fn build_block(&mut self, func: &mut FnBuilder, block: &Block, scope: &mut Scope) {
+		for statement in block {
+			if func.builder.is_terminated() {
+				// Code after a return gets a block of its own, which nothing branches to.
+				let dead = func.val.append_basic_block(b"dead");
+				func.builder.position_at_end(dead);
+			}
+			match *statement {
+				Statement::Declaration(ref id, _, ref expr) => {
+					let ty  = scope.get_var(&id.val).unwrap().ty;
+					let ptr = func.allocas.alloca(llvm_type(ty), id.val.as_bytes());
+					if let Some(ref expr) = *expr {
+						let val = self.build_expr(func, expr, scope);
+						func.builder.store(val, ptr);
+					}
+					scope.get_var(&id.val).unwrap().llvm = Some(ptr);
+				},
+				Statement::Assignment(ref id, ref expr) => {
+					let val = self.build_expr(func, expr, scope);
+					let ptr = scope.get_var(&id.val).unwrap().llvm.unwrap();
+					func.builder.store(val, ptr);
+				},
+				Statement::If(ref conditions, ref blocks, ref else_block) => {
+					let end = func.val.append_basic_block(b"endif");
+					for (condition, block) in conditions.iter().zip(blocks.iter()) {
+						let cond = self.build_expr(func, condition, scope);
+						let then = func.val.append_basic_block(b"then");
+						let next = func.val.append_basic_block(b"else");
+						func.builder.cond_br(cond, then, next);
+
+						func.builder.position_at_end(then);
+						self.build_block(func, &block.val, scope.enter());
+						if !func.builder.is_terminated() { func.builder.br(end); }
+						func.builder.position_at_end(next);
+					}
+					if let Some(ref block) = *else_block {
+						self.build_block(func, &block.val, scope.enter());
+					}
+					if !func.builder.is_terminated() { func.builder.br(end); }
+					func.builder.position_at_end(end);
+				},
+				Statement::Loop(ref block) => {
+					let body = func.val.append_basic_block(b"loop");
+					func.builder.br(body);
+					func.builder.position_at_end(body);
+					self.build_block(func, &block.val, scope.enter());
+					if !func.builder.is_terminated() { func.builder.br(body); }
+					let end = func.val.append_basic_block(b"endloop");
+					func.builder.position_at_end(end);
+				},
+				Statement::Block(ref block) => {
+					self.build_block(func, &block.val, scope.enter());
+				},
+				Statement::Return(ref ret) => match ret.val {
+					Some(ref expr) => {
+						let val = self.build_expr(func, expr, scope);
+						func.builder.ret(val);
+					},
+					None => { func.builder.ret_void(); },
+				},
+				Statement::Expr(ref expr) => { self.build_expr(func, expr, scope); },
+				Statement::Assert(ref expr) => if !self.options.release {
+					self.build_assert(func, expr, scope);
+				},
+				Statement::StaticAssert(..) => (),
+				Statement::Print(ref id) => {
+					let (val, ty) = self.build_var(func, id, scope);
+					self.build_print(func, val, ty);
+				},
+				Statement::Error(_) => unreachable!(),
+			}
+		}
+	}
+
+	fn build_expr(&mut self, func: &mut FnBuilder, expr: &Expr, scope: &mut Scope) -> llvm::Value {
+		let mut stack: Vec<(llvm::Value, Type)> = Vec::new();
+		for (i, &Node { ref token, val: (ref e, ty) }) in expr.iter().enumerate() {
+			let ty = ty.complete();
+			let val = match *e {
+				ExprToken::IntLit(val) => match ty {
+					Type::Num(NumType::Float(_)) => llvm::Value::const_real(llvm_type(ty), val as f64),
+					_                            => llvm::Value::const_int(llvm_type(ty), val),
+				},
+				ExprToken::FloatLit(val) => llvm::Value::const_real(llvm_type(ty), val),
+				ExprToken::BoolLit(val)  => llvm::Value::const_int(llvm::Type::bool(), val as u128),
+				ExprToken::CharLit(val)  => llvm::Value::const_int(llvm::Type::i32(), val as u128),
+				ExprToken::StringLit(_)  => unreachable!(), // rejected by the checker
+				ExprToken::Closure(_)    => unreachable!(), // lifted out by the checker
+				ExprToken::TypeQuery(..) => unreachable!(), // replaced by the checker
+				ExprToken::Cast(_) => {
+					let (val, from) = stack.pop().unwrap();
+					self.build_cast(func, val, from, ty)
+				},
+				ExprToken::Vector(num) => {
+					let elems = stack.split_off(stack.len() - num as usize);
+					let mut vector = llvm::Value::undef(llvm_type(ty));
+					for (lane, (elem, _)) in elems.into_iter().enumerate() {
+						let index = llvm::Value::const_int(llvm::Type::i32(), lane as u128);
+						vector = func.builder.insert_element(vector, elem, index, b"vec");
+					}
+					vector
+				},
+				ExprToken::Extract => {
+					let (index, index_ty) = stack.pop().unwrap();
+					let (vector, vector_ty) = stack.pop().unwrap();
+					self.build_lane_check(func, index, index_ty, vector_ty, token);
+					func.builder.extract_element(vector, index, b"lane")
+				},
+				ExprToken::Insert => {
+					let (val, _) = stack.pop().unwrap();
+					let (index, index_ty) = stack.pop().unwrap();
+					let (vector, vector_ty) = stack.pop().unwrap();
+					self.build_lane_check(func, index, index_ty, vector_ty, token);
+					func.builder.insert_element(vector, val, index, b"insert")
+				},
+				ExprToken::Id(ref id) if scope.get_var(id).is_some() => {
+					self.build_var(func, id, scope).0
+				},
+				ExprToken::Id(ref id)    => self.build_fn_value(func, id, scope),
+				ExprToken::Op(op) if op.is_binary() => {
+					let (r, _)  = stack.pop().unwrap();
+					let (l, ty) = stack.pop().unwrap();
+					match op {
+						Op::Add | Op::Sub | Op::Mul if self.traps(ty) => {
+							self.build_checked(func, op, ty, l, r, token)
+						},
+						Op::Div | Op::Mod if self.checks_div(ty) => {
+							self.build_div_check(func, ty, l, r, token);
+							build_binop(&mut func.builder, op, ty, l, r, self.options.overflow)
+						},
+						_ => build_binop(&mut func.builder, op, ty, l, r, self.options.overflow),
+					}
+				},
+				ExprToken::Op(op) => {
+					let (val, ty) = stack.pop().unwrap();
+					match op {
+						// A negative literal such as `-128` is in range even though its
+						// magnitude is not.
+						Op::Neg if self.traps(ty) && !is_int_lit(&expr[i - 1].val.0) => {
+							let zero = llvm::Value::const_int(llvm_type(ty), 0);
+							self.build_checked(func, Op::Sub, ty, zero, val, token)
+						},
+						Op::Inv if self.checks_div(ty) => {
+							let one = splat(ty, llvm::Value::const_int(llvm_type(ty.elem()), 1));
+							self.build_div_check(func, ty, one, val, token);
+							build_unop(&mut func.builder, op, ty, val, self.options.overflow)
+						},
+						_ => build_unop(&mut func.builder, op, ty, val, self.options.overflow),
+					}
+				},
+				ExprToken::Call(ref id, num) if scope.get_var(id).is_some() => {
+					let args = stack.split_off(stack.len() - num as usize);
+					let (val, fn_ty) = self.build_var(func, id, scope);
+					let fn_type = match fn_ty {
+						Type::Fn(fn_type) => fn_type,
+						_ => unreachable!(),
+					};
+					let mut params = vec![byte_ptr()];
+					params.extend(fn_type.params().into_iter().map(llvm_type));
+					let ret = fn_type.ret().map_or(llvm::Type::void(), llvm_type);
+					let ty  = llvm::Type::pointer(llvm::Type::function(ret, &mut params[..]));
+
+					let ptr = func.builder.extract_value(val, 0, b"");
+					let ptr = func.builder.bit_cast(ptr, ty, b"");
+					let mut args: Vec<llvm::Value> = Some(func.builder.extract_value(val, 1, b"env"))
+						.into_iter().chain(args.into_iter().map(|(val, _)| val)).collect();
+					func.builder.call_with(ptr, &mut args[..], llvm::CALL_FAST, b"")
+				},
+				ExprToken::Call(ref id, num) => {
+					let mut args: Vec<llvm::Value> = stack.split_off(stack.len() - num as usize)
+						.into_iter().map(|(val, _)| val).collect();
+					let function = scope.get_fn(id).unwrap().llvm.unwrap();
+					func.builder.call(function, &mut args[..], b"")
+				},
+			};
+			stack.push((val, ty));
+		}
+		stack.pop().unwrap().0
+	}
+
+	/// Builds a function value, which is a pointer to a function that takes an environment
+	/// before its parameters, and the environment.
+	/// Closures get their captures copied into a new environment.
+	fn build_fn_value(&mut self, func: &mut FnBuilder, id: &str, scope: &mut Scope) -> llvm::Value {
+		let function = scope.get_fn(id).unwrap().clone();
+		let (ptr, env) = match function.captures {
+			Some(ref captures) if !captures.is_empty() => {
+				let ty = env_type(captures);
+				let malloc = self.declare("malloc", llvm::Type::function(byte_ptr(), &mut [llvm::Type::i64()]));
+				let raw = func.builder.call(malloc, &mut [llvm::Value::size_of(ty)], b"");
+				let env = func.builder.bit_cast(raw, llvm::Type::pointer(ty), b"env");
+				for (i, &(ref name, _)) in captures.iter().enumerate() {
+					let (val, _) = self.build_var(func, name, scope);
+					let ptr = func.builder.struct_gep(env, i as u32, b"");
+					func.builder.store(val, ptr);
+				}
+				(function.llvm.unwrap(), raw)
+			},
+			Some(_) => (function.llvm.unwrap(), llvm::Value::const_null(byte_ptr())),
+			None    => (self.fn_value(id, &function), llvm::Value::const_null(byte_ptr())),
+		};
+
+		let ptr = func.builder.bit_cast(ptr, byte_ptr(), b"");
+		let val = llvm::Value::undef(llvm_type(function.ty()));
+		let val = func.builder.insert_value(val, ptr, 0, b"");
+		func.builder.insert_value(val, env, 1, b"")
+	}
+
+	/// Creates the function that calls a named function when it is used as a function value.
+	fn fn_value(&mut self, id: &str, function: &checker::Function) -> llvm::Value {
+		if let Some(&val) = self.fn_values.get(id) {
+			return val;
+		}
+		let mut params = vec![byte_ptr()];
+		params.extend(function.params.iter().map(|&(_, var)| llvm_type(var.ty)));
+		let ret = function.ret_ty.map_or(llvm::Type::void(), llvm_type);
+		let ty  = llvm::Type::function(ret, &mut params[..]);
+
+		let name = format!("{}.value", symbol(id, function));
+		let mut val = self.module.add_function(name.as_bytes(), ty, llvm::CALL_FAST);
+		val.set_linkage(llvm::LINK_INTERNAL);
+		let mut builder = llvm::Builder::new(val.append_basic_block(b"entry"));
+		let mut args: Vec<llvm::Value> = (1..params.len()).map(|i| val.get_param(i as u32)).collect();
+		let res = builder.call(function.llvm.unwrap(), &mut args[..], b"");
+		match function.ret_ty {
+			Some(_) => builder.ret(res),
+			None    => builder.ret_void(),
+		};
+		self.fn_values.insert(id.to_string(), val);
+		val
+	}
+
+	/// Loads a variable, or builds the expression of a constant.
+	fn build_var(&mut self, func: &mut FnBuilder, id: &str,
+	             scope: &mut Scope) -> (llvm::Value, Type) {
+		let var = *scope.get_var(id).unwrap();
+		match var.llvm {
+			Some(ptr) => (func.builder.load(ptr, id.as_bytes()), var.ty),
+			None      => {
+				let expr = self.consts[id];
+				(self.build_expr(func, expr, scope), var.ty)
+			},
+		}
+	}
+
+	fn build_print(&mut self, func: &mut FnBuilder, val: llvm::Value, ty: Type) {
+		if ty.int_width() == Some(128) {
+			return self.build_print_wide(func, val, ty);
+		}
+		if ty == Type::Char {
+			return self.build_print_char(func, val);
+		}
+		if let Type::Vector(num, lanes) = ty {
+			// A lane per line.
+			for lane in 0..lanes {
+				let index = llvm::Value::const_int(llvm::Type::i32(), lane as u128);
+				let elem = func.builder.extract_element(val, index, b"lane");
+				self.build_print(func, elem, Type::Num(num));
+			}
+			return;
+		}
+		let b = &mut func.builder;
+		let (format, val): (&[u8], llvm::Value) = match ty {
+			Type::Num(NumType::Signed(_))   if ty.int_width() == Some(64) => (b"%lld\n", val),
+			Type::Num(NumType::Unsigned(_)) if ty.int_width() == Some(64) => (b"%llu\n", val),
+			Type::Num(NumType::Signed(_))   => (b"%d\n", b.s_ext_or_bit_cast(val, llvm::Type::i32(), b"")),
+			Type::Num(NumType::Unsigned(_)) => (b"%u\n", b.z_ext_or_bit_cast(val, llvm::Type::i32(), b"")),
+			Type::Num(NumType::Float(FloatType::F16)) |
+			Type::Num(NumType::Float(FloatType::F32)) => (b"%f\n", b.fp_ext(val, llvm::Type::f64(), b"")),
+			// printf has no format for quadruple precision, but long double comes close.
+			Type::Num(NumType::Float(FloatType::F128)) => {
+				(b"%Lf\n", b.fp_trunc(val, llvm::Type::x86_fp80(), b""))
+			},
+			Type::Num(NumType::Float(_))    => (b"%f\n", val),
+			_                               => (b"%d\n", b.z_ext_or_bit_cast(val, llvm::Type::i32(), b"")),
+		};
+		let format = b.global_string_ptr(format, b"format");
+		let printf = self.declare("printf", llvm::Type::var_arg_function(llvm::Type::i32(), &mut [byte_ptr()]));
+		b.call(printf, &mut [format, val], b"");
+	}
+
+	/// Prints a 128-bit integer, which printf has no format for, as up to three parts
+	/// of 19 digits.
+	fn build_print_wide(&mut self, func: &mut FnBuilder, val: llvm::Value, ty: Type) {
+		let printf = self.declare("printf", llvm::Type::var_arg_function(llvm::Type::i32(), &mut [byte_ptr()]));
+		let b = &mut func.builder;
+		let int = llvm::Type::int(128);
+		let zero = llvm::Value::const_int(int, 0);
+		let no_sign = b.global_string_ptr(b"", b"sign");
+		let (sign, val) = match ty {
+			Type::Num(NumType::Signed(_)) => {
+				let is_neg = b.i_cmp(llvm::INT_SLT, val, zero, b"is_neg");
+				// The magnitude of the minimum is still right when read as unsigned.
+				let neg = b.sub(zero, val, b"neg");
+				let minus = b.global_string_ptr(b"-", b"sign");
+				(b.select(is_neg, minus, no_sign, b"sign"), b.select(is_neg, neg, val, b"abs"))
+			},
+			_ => (no_sign, val),
+		};
+		let part = llvm::Value::const_int(int, 10_000_000_000_000_000_000);
+		let low  = b.u_rem(val, part, b"low");
+		let rest = b.u_div(val, part, b"rest");
+		let mid  = b.u_rem(rest, part, b"mid");
+		let high = b.u_div(rest, part, b"high");
+		let has_high = b.i_cmp(llvm::INT_NE, high, zero, b"has_high");
+		let has_mid  = b.i_cmp(llvm::INT_NE, rest, zero, b"has_mid");
+		let low  = b.trunc(low,  llvm::Type::i64(), b"");
+		let mid  = b.trunc(mid,  llvm::Type::i64(), b"");
+		let high = b.trunc(high, llvm::Type::i64(), b"");
+
+		// The leading part is printed without padding, and the ones after it with zeros.
+		let three = b.global_string_ptr(b"%s%llu%019llu%019llu\n", b"format");
+		let two   = b.global_string_ptr(b"%s%llu%019llu\n", b"format");
+		let one   = b.global_string_ptr(b"%s%llu\n", b"format");
+		let format = b.select(has_mid, two, one, b"");
+		let format = b.select(has_high, three, format, b"format");
+		let first  = b.select(has_mid, mid, low, b"");
+		let first  = b.select(has_high, high, first, b"first");
+		let second = b.select(has_high, mid, low, b"second");
+		b.call(printf, &mut [format, sign, first, second, low], b"");
+	}
+
+	/// Prints a character encoded as UTF-8.
+	fn build_print_char(&mut self, func: &mut FnBuilder, val: llvm::Value) {
+		let printf = self.declare("printf", llvm::Type::var_arg_function(llvm::Type::i32(), &mut [byte_ptr()]));
+		let b = &mut func.builder;
+		let int = llvm::Type::i64();
+		let c = b.z_ext(val, int, b"c");
+		let num = |n: u64| llvm::Value::const_int(int, n as u128);
+		// The bits of the code point from the given one on, after the marker of a byte.
+		let byte = |b: &mut llvm::Builder, marker: u64, shift: u32, width: u64| {
+			let bits = b.u_div(c, num(1 << shift), b"");
+			let bits = b.u_rem(bits, num(1 << width), b"");
+			b.add(bits, num(marker), b"byte")
+		};
+		let cont0  = byte(b, 0x80,  0, 6);
+		let cont6  = byte(b, 0x80,  6, 6);
+		let cont12 = byte(b, 0x80, 12, 6);
+		let lead2  = byte(b, 0xc0,  6, 5);
+		let lead3  = byte(b, 0xe0, 12, 4);
+		let lead4  = byte(b, 0xf0, 18, 3);
+
+		// The bytes are packed little endian into an integer, with zeros to end the string.
+		let pack = |b: &mut llvm::Builder, bytes: &[llvm::Value]| {
+			let mut packed = bytes[0];
+			for (i, &byte) in bytes.iter().enumerate().skip(1) {
+				let byte = b.mul(byte, num(1 << (8 * i)), b"");
+				packed = b.add(packed, byte, b"");
+			}
+			packed
+		};
+		let two   = pack(b, &[lead2, cont0]);
+		let three = pack(b, &[lead3, cont6, cont0]);
+		let four  = pack(b, &[lead4, cont12, cont6, cont0]);
+		let is_one   = b.i_cmp(llvm::INT_ULT, c, num(0x80), b"");
+		let is_two   = b.i_cmp(llvm::INT_ULT, c, num(0x800), b"");
+		let is_three = b.i_cmp(llvm::INT_ULT, c, num(0x10000), b"");
+		let packed = b.select(is_three, three, four, b"");
+		let packed = b.select(is_two, two, packed, b"");
+		let packed = b.select(is_one, c, packed, b"utf8");
+
+		let buf = func.allocas.alloca(int, b"utf8");
+		b.store(packed, buf);
+		let string = b.bit_cast(buf, byte_ptr(), b"");
+		let format = b.global_string_ptr(b"%s\n", b"format");
+		b.call(printf, &mut [format, string], b"");
+	}
+
+	/// Converts a value the way `@cast` does: integers wrap around, and floats saturate at
+	/// the bounds of integers, with NaN becoming 0.
+	/// Code points which are not characters become U+FFFD, the replacement character.
+	fn build_cast(&mut self, func: &mut FnBuilder, val: llvm::Value, from: Type, to: Type) -> llvm::Value {
+		let llvm_to = llvm_type(to);
+		if to == Type::Char && from != Type::Char {
+			let b = &mut func.builder;
+			let num = |n: u128| llvm::Value::const_int(llvm_to, n);
+			let below_surrogates = b.i_cmp(llvm::INT_ULT, val, num(0xd800), b"");
+			let above_surrogates = b.i_cmp(llvm::INT_UGE, val, num(0xe000), b"");
+			let in_range = b.i_cmp(llvm::INT_ULT, val, num(0x110000), b"");
+			let valid = b.and(above_surrogates, in_range, b"");
+			let valid = b.or(below_surrogates, valid, b"valid");
+			return b.select(valid, val, num(0xfffd), b"cast");
+		}
+		match (from.float_width(), to.float_width()) {
+			(Some(from_width), Some(to_width)) => {
+				if from_width < to_width {
+					func.builder.fp_ext(val, llvm_to, b"cast")
+				} else if from_width > to_width {
+					func.builder.fp_trunc(val, llvm_to, b"cast")
+				} else { val }
+			},
+			(Some(from_width), None) => {
+				let name = format!("llvm.fpto{}i.sat.i{}.f{}", by_type!(to, "s", "u", unreachable!()),
+				                   llvm_to.int_width(), from_width);
+				let intrinsic = self.declare(&name, llvm::Type::function(llvm_to, &mut [llvm_type(from)]));
+				func.builder.call(intrinsic, &mut [val], b"cast")
+			},
+			(None, Some(_)) => by_type!(from, func.builder.si_to_fp(val, llvm_to, b"cast"),
+			                                  func.builder.ui_to_fp(val, llvm_to, b"cast"),
+			                                  unreachable!()),
+			(None, None) => {
+				let (from_width, to_width) = (llvm_type(from).int_width(), llvm_to.int_width());
+				if from_width > to_width {
+					func.builder.trunc(val, llvm_to, b"cast")
+				} else if from_width < to_width {
+					by_type!(from, func.builder.s_ext(val, llvm_to, b"cast"),
+					               func.builder.z_ext(val, llvm_to, b"cast"),
+					               unreachable!())
+				} else { val }
+			},
+		}
+	}
+
+	/// Whether arithmetic on the type aborts when it overflows.
+	/// Arithmetic on vectors wraps around, as SIMD instructions do.
+	fn traps(&self, ty: Type) -> bool {
+		match ty {
+			Type::Num(NumType::Signed(_)) | Type::Num(NumType::Unsigned(_)) => {
+				self.options.overflow == Overflow::Trap
+			},
+			_ => false,
+		}
+	}
+
+	/// Whether division on the type aborts when it divides by zero or overflows.
+	fn checks_div(&self, ty: Type) -> bool {
+		match ty.elem() {
+			Type::Num(NumType::Signed(_)) | Type::Num(NumType::Unsigned(_)) => self.options.div_checks,
+			_ => false,
+		}
+	}
+
+	/// Aborts the program when integer division of `l` by `r` is undefined: when `r` is zero
+	/// or, for signed types, when `l` is the minimum and `r` is -1. Vectors are checked lane
+	/// by lane.
+	fn build_div_check(&mut self, func: &mut FnBuilder, ty: Type,
+	                   l: llvm::Value, r: llvm::Value, token: &FullToken) {
+		let int = llvm_type(ty.elem());
+		let zero = splat(ty, llvm::Value::const_int(int, 0));
+		let by_zero = func.builder.i_cmp(llvm::INT_EQ, r, zero, b"by_zero");
+		let by_zero = any_lane(&mut func.builder, by_zero);
+		self.build_trap_if(func, by_zero, token, "division by zero");
+		if let Type::Num(NumType::Signed(_)) = ty.elem() {
+			let min = splat(ty, llvm::Value::const_int(int, 1 << (int.int_width() - 1)));
+			let neg_one = splat(ty, llvm::Value::const_int(int, !0));
+			let is_min = func.builder.i_cmp(llvm::INT_EQ, l, min, b"is_min");
+			let is_neg_one = func.builder.i_cmp(llvm::INT_EQ, r, neg_one, b"is_neg_one");
+			let overflowed = func.builder.and(is_min, is_neg_one, b"overflow");
+			let overflowed = any_lane(&mut func.builder, overflowed);
+			self.build_trap_if(func, overflowed, token, "division overflow");
+		}
+	}
+
+	/// Aborts the program when the index of a lane is beyond the end of the vector.
+	/// Negative indices are too, as they are compared as unsigned.
+	fn build_lane_check(&mut self, func: &mut FnBuilder, index: llvm::Value, index_ty: Type,
+	                    vector_ty: Type, token: &FullToken) {
+		if self.options.release {
+			return;
+		}
+		let lanes = match vector_ty {
+			Type::Vector(_, lanes) => lanes,
+			_ => unreachable!(),
+		};
+		let lanes = llvm::Value::const_int(llvm_type(index_ty), lanes as u128);
+		let out = func.builder.i_cmp(llvm::INT_UGE, index, lanes, b"out_of_bounds");
+		self.build_trap_if(func, out, token, "lane index out of bounds");
+	}
+
+	/// Builds integer addition, subtraction or multiplication which aborts when it overflows.
+	fn build_checked(&mut self, func: &mut FnBuilder, op: Op, ty: Type,
+	                 l: llvm::Value, r: llvm::Value, token: &FullToken) -> llvm::Value {
+		let int = llvm_type(ty);
+		let name = match op {
+			Op::Add => "add",
+			Op::Sub => "sub",
+			Op::Mul => "mul",
+			_ => unreachable!(),
+		};
+		let name = format!("llvm.{}{}.with.overflow.i{}", by_type!(ty, "s", "u", unreachable!()),
+		                   name, int.int_width());
+		let ret = llvm::Type::structure(&mut [int, llvm::Type::bool()]);
+		let intrinsic = self.declare(&name, llvm::Type::function(ret, &mut [int, int]));
+		let res = func.builder.call(intrinsic, &mut [l, r], b"");
+		let overflowed = func.builder.extract_value(res, 1, b"overflow");
+		self.build_trap_if(func, overflowed, token, "arithmetic overflow");
+		func.builder.extract_value(res, 0, b"")
+	}
+
+	/// Aborts the program with where the assertion is when the condition is false.
+	fn build_assert(&mut self, func: &mut FnBuilder, expr: &Node<Expr>, scope: &mut Scope) {
+		let cond = self.build_expr(func, &expr.val, scope);
+		let failed = func.builder.not(cond, b"failed");
+		self.build_trap_if(func, failed, &expr.token, "assertion failed");
+	}
+
+	/// Aborts the program with a message naming the place in the source when the condition
+	/// is true.
+	fn build_trap_if(&mut self, func: &mut FnBuilder, cond: llvm::Value, token: &FullToken,
+	                 message: &str) {
+		let trap = func.val.append_basic_block(b"trap");
+		let ok   = func.val.append_basic_block(b"ok");
+		func.builder.cond_br(cond, trap, ok);
+
+		func.builder.position_at_end(trap);
+		let message = format!("{}:{}:{}: {}\n", self.options.files[token.file as usize].display(),
+		                      token.line, token.column, message);
+		let size = llvm::Type::int(pointer_width());
+		let fflush = self.declare("fflush", llvm::Type::function(llvm::Type::i32(), &mut [byte_ptr()]));
+		let write  = self.declare("write",  llvm::Type::function(size, &mut [llvm::Type::i32(), byte_ptr(), size]));
+		let abort  = self.declare("abort",  llvm::Type::function(llvm::Type::void(), &mut []));
+		// Output is buffered, and aborting does not flush it. The message goes to stderr,
+		// after what the program printed.
+		func.builder.call(fflush, &mut [llvm::Value::const_null(byte_ptr())], b"");
+		let len = llvm::Value::const_int(size, message.len() as u128);
+		let message = func.builder.global_string_ptr(message.as_bytes(), b"trap");
+		func.builder.call(write, &mut [llvm::Value::const_int(llvm::Type::i32(), 2), message, len], b"");
+		func.builder.call(abort, &mut [], b"");
+		func.builder.unreachable();
+
+		func.builder.position_at_end(ok);
+	}
+
+	/// Declares an external function, such as one of the C library or an LLVM intrinsic,
+	/// the first time it is used.
+	fn declare(&mut self, name: &str, ty: llvm::Type) -> llvm::Value {
+		if let Some(&val) = self.externs.get(name) {
+			return val;
+		}
+		let val = self.module.add_function(name.as_bytes(), ty, llvm::CALL_C);
+		self.externs.insert(name.to_string(), val);
+		val
+	}
+}
+
+/// Folds the initializer of a static, which the checker made sure only uses literals,
+/// operators and constants.
+fn build_const(expr: &Expr, consts: &HashMap<&str, &Expr>) -> llvm::Value {
+	let mut b = llvm::Builder::detached();
+	let mut stack: Vec<(llvm::Value, Type)> = Vec::new();
+	for &Node { token: _, val: (ref e, ty) } in expr {
+		let ty = ty.complete();
+		let val = match *e {
+			ExprToken::IntLit(val) => match ty {
+				Type::Num(NumType::Float(_)) => llvm::Value::const_real(llvm_type(ty), val as f64),
+				_                            => llvm::Value::const_int(llvm_type(ty), val),
+			},
+			ExprToken::FloatLit(val) => llvm::Value::const_real(llvm_type(ty), val),
+			ExprToken::BoolLit(val)  => llvm::Value::const_int(llvm::Type::bool(), val as u128),
+			ExprToken::CharLit(val)  => llvm::Value::const_int(llvm::Type::i32(), val as u128),
+			ExprToken::Id(ref id)    => build_const(consts[&id[..]], consts),
+			ExprToken::Op(op) if op.is_binary() => {
+				let (r, _)  = stack.pop().unwrap();
+				let (l, ty) = stack.pop().unwrap();
+				build_binop(&mut b, op, ty, l, r, Overflow::Unchecked)
+			},
+			ExprToken::Op(op) => {
+				let (val, ty) = stack.pop().unwrap();
+				build_unop(&mut b, op, ty, val, Overflow::Unchecked)
+			},
+			_ => unreachable!(),
+		};
+		stack.push((val, ty));
+	}
+	stack.pop().unwrap().0
+}
+
+/// Makes a vector of the type out of a constant of its lanes. Other types take the constant
+/// as it is.
+fn splat(ty: Type, val: llvm::Value) -> llvm::Value {
+	match ty {
+		Type::Vector(_, lanes) => llvm::Value::const_splat(val, lanes),
+		_ => val,
+	}
+}
+
+/// Whether any lane of a comparison of vectors holds. Booleans are left as they are.
+fn any_lane(b: &mut llvm::Builder, cond: llvm::Value) -> llvm::Value {
+	let ty = cond.get_type();
+	if !ty.is_vector() {
+		return cond;
+	}
+	let bits = llvm::Type::int(ty.vector_size());
+	let mask = b.bit_cast(cond, bits, b"");
+	b.i_cmp(llvm::INT_NE, mask, llvm::Value::const_int(bits, 0), b"any")
+}
+
+/// Whether every lane of a comparison of vectors holds. Booleans are left as they are.
+fn all_lanes(b: &mut llvm::Builder, cond: llvm::Value) -> llvm::Value {
+	let ty = cond.get_type();
+	if !ty.is_vector() {
+		return cond;
+	}
+	let bits = llvm::Type::int(ty.vector_size());
+	let mask = b.bit_cast(cond, bits, b"");
+	b.i_cmp(llvm::INT_EQ, mask, llvm::Value::const_int(bits, !0), b"all")
+}
+
+fn is_int_lit(e: &ExprToken) -> bool {
+	match *e {
+		ExprToken::IntLit(_) => true,
+		_ => false,
+	}
+}
+
+/// Builds a binary operation. Integer arithmetic which may overflow wraps around unless the
+/// overflow is `Unchecked`; trapping arithmetic is built with `Builder::build_checked`.
+fn build_binop(b: &mut llvm::Builder, op: Op, ty: Type,
+               l: llvm::Value, r: llvm::Value, overflow: Overflow) -> llvm::Value {
+	let wrap = match op {
+		Op::WrapAdd | Op::WrapSub | Op::WrapMul => true,
+		_ => overflow != Overflow::Unchecked,
+	};
+	match op {
+		Op::Add | Op::WrapAdd if wrap => by_type!(ty, b.add(l, r, b"add"), b.add(l, r, b"add"), b.f_add(l, r, b"add")),
+		Op::Sub | Op::WrapSub if wrap => by_type!(ty, b.sub(l, r, b"sub"), b.sub(l, r, b"sub"), b.f_sub(l, r, b"sub")),
+		Op::Mul | Op::WrapMul if wrap => by_type!(ty, b.mul(l, r, b"mul"), b.mul(l, r, b"mul"), b.f_mul(l, r, b"mul")),
+		Op::Add => by_type!(ty, b.nsw_add(l, r, b"add"), b.nuw_add(l, r, b"add"), b.f_add(l, r, b"add")),
+		Op::Sub => by_type!(ty, b.nsw_sub(l, r, b"sub"), b.nuw_sub(l, r, b"sub"), b.f_sub(l, r, b"sub")),
+		Op::Mul => by_type!(ty, b.nsw_mul(l, r, b"mul"), b.nuw_mul(l, r, b"mul"), b.f_mul(l, r, b"mul")),
+		Op::Div => by_type!(ty,   b.s_div(l, r, b"div"),   b.u_div(l, r, b"div"), b.f_div(l, r, b"div")),
+		Op::Mod => by_type!(ty,   b.s_rem(l, r, b"rem"),   b.u_rem(l, r, b"rem"), b.f_rem(l, r, b"rem")),
+		Op::And => b.and(l, r, b"and"),
+		Op::Or  => b.or( l, r, b"or"),
+		// Vectors are equal when all of their lanes are.
+		Op::Eq  => {
+			let eq = by_type!(ty, b.i_cmp(llvm::INT_EQ,  l, r, b"eq"),
+			                      b.i_cmp(llvm::INT_EQ,  l, r, b"eq"),
+			                      b.f_cmp(llvm::REAL_OEQ, l, r, b"eq"));
+			all_lanes(b, eq)
+		},
+		Op::Neq => {
+			let ne = by_type!(ty, b.i_cmp(llvm::INT_NE,  l, r, b"ne"),
+			                      b.i_cmp(llvm::INT_NE,  l, r, b"ne"),
+			                      b.f_cmp(llvm::REAL_ONE, l, r, b"ne"));
+			any_lane(b, ne)
+		},
+		Op::Gt  => by_type!(ty, b.i_cmp(llvm::INT_SGT, l, r, b"gt"),
+		                        b.i_cmp(llvm::INT_UGT, l, r, b"gt"),
+		                        b.f_cmp(llvm::REAL_OGT, l, r, b"gt")),
+		Op::Lt  => by_type!(ty, b.i_cmp(llvm::INT_SLT, l, r, b"lt"),
+		                        b.i_cmp(llvm::INT_ULT, l, r, b"lt"),
+		                        b.f_cmp(llvm::REAL_OLT, l, r, b"lt")),
+		Op::Geq => by_type!(ty, b.i_cmp(llvm::INT_SGE, l, r, b"ge"),
+		                        b.i_cmp(llvm::INT_UGE, l, r, b"ge"),
+		                        b.f_cmp(llvm::REAL_OGE, l, r, b"ge")),
+		Op::Leq => by_type!(ty, b.i_cmp(llvm::INT_SLE, l, r, b"le"),
+		                        b.i_cmp(llvm::INT_ULE, l, r, b"le"),
+		                        b.f_cmp(llvm::REAL_OLE, l, r, b"le")),
+		Op::Pow => unimplemented!(),
+		_ => unreachable!(),
+	}
+}
+
+fn build_unop(b: &mut llvm::Builder, op: Op, ty: Type, val: llvm::Value,
+              overflow: Overflow) -> llvm::Value {
+	match op {
+		Op::Not => b.not(val, b"not"),
+		Op::Neg if overflow != Overflow::Unchecked => {
+			by_type!(ty, b.neg(val, b"neg"), b.neg(val, b"neg"), b.f_neg(val, b"neg"))
+		},
+		Op::Neg => by_type!(ty, b.nsw_neg(val, b"neg"), b.nuw_neg(val, b"neg"), b.f_neg(val, b"neg")),
+		Op::Inv => {
+			let llvm_ty = llvm_type(ty.elem());
+			by_type!(ty, b.s_div(splat(ty, llvm::Value::const_int(llvm_ty, 1)), val, b"inv"),
+			             b.u_div(splat(ty, llvm::Value::const_int(llvm_ty, 1)), val, b"inv"),
+			             b.f_div(splat(ty, llvm::Value::const_real(llvm_ty, 1.0)), val, b"inv"))
+		},
+		_ => unreachable!(),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::env;
+	use std::fs::File;
+	use std::io::Read;
+	use parser;
+
+	/// Builds a program, and returns its LLVM IR.
+	fn build_ir(code: &str, name: &str, div_checks: bool) -> String {
+		let (mut items, e) = parser::parse(code);
+		assert_eq!(e, Vec::new());
+		let mut program = checker::Program::new();
+		checker::check_program(&mut items, &mut program).unwrap();
+		let files = [PathBuf::from("main.eb")];
+		let options = Options { files: &files, release: false, overflow: Overflow::Trap, div_checks: div_checks };
+		let path = env::temp_dir().join(format!("thang_{}.ll", name));
+		assert!(build(&items, &mut program, &options, path.to_str().unwrap()));
+		let mut ir = String::new();
+		File::open(&path).unwrap().read_to_string(&mut ir).unwrap();
+		ir
+	}
+
+	/// The IR of a function, from its definition to its end.
+	fn function<'a>(ir: &'a str, name: &str) -> &'a str {
+		let start = ir.find(&format!(" @thang.{}(", name)).unwrap();
+		let start = ir[..start].rfind("define").unwrap();
+		&ir[start..start + ir[start..].find("\n}").unwrap()]
+	}
+
+	#[test]
+	fn div_test() {
+		let code = "fn div(a: I32, b: I32) -> I32 { return a / b }\n\
+		            fn rem(a: I8, b: I8) -> I8 { return a % b }\n\
+		            fn udiv(a: U64, b: U64) -> U64 { return a / b }\n\
+		            fn fdiv(a: F64, b: F64) -> F64 { return a / b }\n\
+		            fn main() {}";
+		let ir = build_ir(code, "div_test", true);
+		let aborts = |name: &str| function(&ir, name).matches("call void @abort()").count();
+		// Signed division checks for zero and for the minimum divided by -1.
+		let div = function(&ir, "div");
+		assert!(div.contains("sdiv i32") && div.contains("-2147483648") && div.contains(", -1"));
+		assert_eq!(aborts("div"), 2);
+		let rem = function(&ir, "rem");
+		assert!(rem.contains("srem i8") && rem.contains("-128") && rem.contains(", -1"));
+		assert_eq!(aborts("rem"), 2);
+		assert!(function(&ir, "udiv").contains("udiv i64"));
+		assert_eq!(aborts("udiv"), 1);
+		assert!(function(&ir, "fdiv").contains("fdiv double"));
+		assert_eq!(aborts("fdiv"), 0);
+		// The messages name the operator, and go to stderr.
+		assert!(ir.contains("c\"main.eb:1:42: division by zero\\0A\\00\""));
+		assert!(ir.contains("c\"main.eb:1:42: division overflow\\0A\\00\""));
+		assert!(ir.contains("c\"main.eb:2:39: division overflow\\0A\\00\""));
+		assert!(div.contains("@write(i32 2, "));
+
+		let ir = build_ir(code, "div_test_unchecked", false);
+		assert!(!ir.contains("@abort"));
+		assert!(function(&ir, "div").contains("sdiv i32"));
+	}
+
+	#[test]
+	fn wide_int_test() {
+		let ir = build_ir("fn wide(a: I128, b: U128) -> U128 { return @cast(U128, a) + b }\n\
+		                   fn size(a: USize, b: ISize) -> ISize { return @cast(ISize, a) - b }\n\
+		                   fn narrow(a: U128) -> U16 { return @cast(U16, a) }\n\
+		                   fn widen(a: ISize, b: USize) -> I128 { return @cast(I128, a) + @cast(I128, b) }\n\
+		                   fn float(a: I128, b: USize) -> F64 { return @cast(F64, a) + @cast(F64, b) }\n\
+		                   fn main() {}", "wide_int_test", true);
+		let wide = function(&ir, "wide");
+		assert!(wide.starts_with("define internal fastcc i128 @thang.wide(i128 %a, i128 %b)"));
+		assert!(wide.contains("@llvm.uadd.with.overflow.i128("));
+		// Pointer-sized integers are as wide as the pointers of the target.
+		let size = format!("i{}", pointer_width());
+		assert!(function(&ir, "size").starts_with(
+			&format!("define internal fastcc {0} @thang.size({0} %a, {0} %b)", size)));
+		assert!(function(&ir, "size").contains(&format!("@llvm.ssub.with.overflow.{}(", size)));
+		let narrow = function(&ir, "narrow");
+		assert!(narrow.contains("trunc i128 ") && narrow.contains(" to i16"));
+		let widen = function(&ir, "widen");
+		assert!(widen.contains(&format!("sext {} ", size)) && widen.contains(&format!("zext {} ", size)));
+		let float = function(&ir, "float");
+		assert!(float.contains("sitofp i128 ") && float.contains(&format!("uitofp {} ", size)));
+	}
+}
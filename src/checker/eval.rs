@@ -0,0 +1,579 @@
+use std::{char, cmp, f64, i64, i128};
+use std::collections::HashMap;
+
+use checker::{Error, ErrorType, Result};
+use parser::{FullToken, Token};
+use parser::ast::*;
+use types::*;
+
+/// The most statements and expression nodes a single initializer may evaluate.
+const MAX_STEPS: u32 = 1_000_000;
+/// The deepest calls may nest during evaluation.
+const MAX_DEPTH: u32 = 256;
+
+/// A value known at compile time. Integers are kept in an `i128`, except that those of
+/// `U128` are kept as their bits, as the largest do not fit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+	Int(i128),
+	Float(f64),
+	Bool(bool),
+	Char(u32),
+}
+impl Value {
+	/// The literal which stands for the value in the program.
+	fn node(self, token: FullToken, ty: Type) -> Node<(ExprToken, Type)> {
+		let e = match self {
+			Value::Int(val)   => ExprToken::IntLit(val as u128),
+			Value::Float(val) => ExprToken::FloatLit(val),
+			Value::Bool(val)  => ExprToken::BoolLit(val),
+			Value::Char(val)  => ExprToken::CharLit(val),
+		};
+		Node::new((e, ty), token)
+	}
+}
+
+/// Evaluates the initializers of constants and statics, and replaces each with a literal
+/// of its value. Functions called by an initializer must be pure: they may only use their
+/// parameters, their locals, constants and other pure functions.
+/// Records the error of each global which fails; those using it fail with a caused error.
+pub fn fold_globals(items: &mut Program, errors: &mut Vec<Error>) {
+	let values = {
+		let mut eval = Evaluator::new(items);
+		let mut values = Vec::new();
+		for (i, item) in items.iter().enumerate() {
+			eval.reset();
+			let val = match *item {
+				Item::Const(ref id, ref type_name, ref expr) => {
+					eval.constant(&id.val, &id.token).map_err(|e| suffix_fix(e, type_name, expr))
+				},
+				Item::Static(ref id, ref type_name, ref expr) => {
+					eval.expr(expr, &mut Vec::new()).map_err(|e| suffix_fix(e, type_name, expr))
+						.and_then(|val| val.ok_or(Error::not_constant(&id.token)))
+				},
+				_ => continue,
+			};
+			match val {
+				Ok(val) => values.push((i, val)),
+				Err(e)  => errors.push(e),
+			}
+		}
+		values
+	};
+	for (i, val) in values {
+		match items[i] {
+			Item::Const(_, _, ref mut expr) | Item::Static(_, _, ref mut expr) => {
+				let root = expr.pop().unwrap();
+				*expr = vec![val.node(root.token, root.val.1.complete())];
+			},
+			_ => unreachable!(),
+		}
+	}
+}
+
+/// Gives a literal which overflows the type literals default to a larger one, if it is all
+/// of the initializer of a global without a type.
+fn suffix_fix(e: Error, type_name: &Option<TypeName>, expr: &Expr) -> Error {
+	let (literal, negated) = match expr[..] {
+		[ref literal] => (literal, false),
+		[ref literal, Node { val: (ExprToken::Op(Op::Neg), _), .. }] => (literal, true),
+		_ => return e,
+	};
+	let val = match (&e.info, type_name, &literal.token.token) {
+		(&ErrorType::Overflow(_), &None, &Token::Int(val, NumType::Unknown))
+			if expr.iter().any(|node| node.token == e.token) => val,
+		_ => return e,
+	};
+	let suffix = if val <= i64::MAX as u128 {
+		"i64"
+	} else if val <= i128::MAX as u128 {
+		"i128"
+	} else if !negated {
+		"u128"
+	} else {
+		return e;
+	};
+	let mut e = e.fix(&literal.token.after(), suffix);
+	e.help = Some(format!("give the literal a larger type, as in `{}{}`", val, suffix));
+	e
+}
+
+/// Evaluates the static assertions in the bodies of functions, recording the first
+/// failure of each.
+pub fn check_static_asserts(items: &Program, errors: &mut Vec<Error>) {
+	let mut eval = Evaluator::new(items);
+	for item in items {
+		if let Item::Function(ref def) = *item {
+			if def.generics.is_empty() {
+				eval.reset();
+				if let Err(e) = eval.static_asserts(&def.block.val) { errors.push(e); }
+			}
+		}
+	}
+}
+
+/// The variables of a function call, innermost block last.
+type Locals<'a> = Vec<HashMap<&'a str, Option<Value>>>;
+
+/// What running a statement leads to.
+enum Flow {
+	Next,
+	Return(Option<Value>),
+}
+
+struct Evaluator<'a> {
+	functions: HashMap<&'a str, &'a FnDef>,
+	consts: HashMap<&'a str, &'a Expr>,
+	values: HashMap<&'a str, Value>,
+	/// The constants which could not be evaluated.
+	failed: Vec<&'a str>,
+	/// The constants being evaluated, to find constants which depend on themselves.
+	evaluating: Vec<&'a str>,
+	/// The call being evaluated, which is blamed for statements that have no token.
+	call: FullToken,
+	steps: u32,
+	depth: u32,
+}
+impl<'a> Evaluator<'a> {
+	fn new(items: &'a Program) -> Evaluator<'a> {
+		let mut functions = HashMap::new();
+		let mut consts = HashMap::new();
+		for item in items {
+			match *item {
+				Item::Function(ref def) if def.generics.is_empty() => {
+					functions.insert(&def.name.val[..], def);
+				},
+				Item::Const(ref id, _, ref expr) => { consts.insert(&id.val[..], expr); },
+				_ => (),
+			}
+		}
+		Evaluator {
+			functions: functions,
+			consts: consts,
+			values: HashMap::new(),
+			failed: Vec::new(),
+			evaluating: Vec::new(),
+			call: FullToken::none(0, 0),
+			steps: 0,
+			depth: 0,
+		}
+	}
+
+	/// Starts evaluating something else, after an error may have left an evaluation unfinished.
+	fn reset(&mut self) {
+		self.evaluating.clear();
+		self.call = FullToken::none(0, 0);
+		self.steps = 0;
+		self.depth = 0;
+	}
+
+	/// The value of a constant, which is evaluated the first time it is needed.
+	fn constant(&mut self, id: &'a str, token: &FullToken) -> Result<Value> {
+		if let Some(&val) = self.values.get(id) {
+			return Ok(val);
+		}
+		if self.failed.contains(&id) {
+			return Err(Error::failed_constant(token));
+		}
+		if self.evaluating.contains(&id) {
+			return Err(Error::not_constant(token));
+		}
+		let expr = self.consts[id];
+		self.evaluating.push(id);
+		let (steps, depth) = (self.steps, self.depth);
+		self.steps = 0;
+		self.depth = 0;
+		let val = self.expr(expr, &mut Vec::new()).and_then(|val| val.ok_or(Error::not_constant(token)));
+		if val.is_err() { self.failed.push(id); }
+		let val = try!(val);
+		self.steps = steps;
+		self.depth = depth;
+		self.evaluating.pop();
+		self.values.insert(id, val);
+		Ok(val)
+	}
+
+	fn static_asserts(&mut self, block: &'a Block) -> Result<()> {
+		for statement in block {
+			match *statement {
+				Statement::StaticAssert(ref expr, ref message) => {
+					self.steps = 0;
+					if try!(self.expr(&expr.val, &mut Vec::new())) != Some(Value::Bool(true)) {
+						return Err(Error::static_assert_failed(&expr.token, message.clone()));
+					}
+				},
+				Statement::If(_, ref blocks, ref else_block) => {
+					for block in blocks.iter().chain(else_block.iter()) {
+						try!(self.static_asserts(&block.val));
+					}
+				},
+				Statement::Loop(ref block) | Statement::Block(ref block) => {
+					try!(self.static_asserts(&block.val));
+				},
+				_ => (),
+			}
+		}
+		Ok(())
+	}
+
+	fn step(&mut self, token: &FullToken) -> Result<()> {
+		self.steps += 1;
+		if self.steps > MAX_STEPS {
+			return Err(Error::eval_limit(token));
+		}
+		Ok(())
+	}
+
+	fn expr(&mut self, expr: &'a Expr, locals: &mut Locals<'a>) -> Result<Option<Value>> {
+		let mut stack: Vec<(Value, Type)> = Vec::new();
+		let mut negated = false;
+		for (i, &Node { ref token, val: (ref e, ty) }) in expr.iter().enumerate() {
+			try!(self.step(token));
+			let ty = ty.complete();
+			let val = match *e {
+				// A negative literal such as `-128` is in range even though its magnitude is not,
+				// so its magnitude is negated before it is checked, and the negation is skipped.
+				ExprToken::IntLit(val) if ty.int_bounds().is_some() &&
+				                          expr.get(i + 1).map(|next| &next.val.0) ==
+				                          Some(&ExprToken::Op(Op::Neg)) => {
+					negated = true;
+					let neg = if val <= 1 << 127 { Some((val as i128).wrapping_neg()) } else { None };
+					try!(int(neg, ty, &expr[i + 1].token))
+				},
+				ExprToken::IntLit(val) => try!(int_literal(val, ty, token)),
+				ExprToken::FloatLit(val) => Value::Float(round(val, ty)),
+				ExprToken::BoolLit(val)  => Value::Bool(val),
+				ExprToken::CharLit(val)  => Value::Char(val),
+				ExprToken::Id(ref id) => match local(locals, id) {
+					Some(Some(val)) => val,
+					Some(None)      => return Err(Error::not_constant(token)),
+					None if self.consts.contains_key(&id[..]) => try!(self.constant(id, token)),
+					// Statics and functions used as values.
+					None => return Err(Error::not_constant(token)),
+				},
+				ExprToken::Op(Op::Neg) if negated => {
+					negated = false;
+					stack.pop().unwrap().0
+				},
+				ExprToken::Op(op) if op.is_binary() => {
+					let (r, _)  = stack.pop().unwrap();
+					let (l, ty) = stack.pop().unwrap();
+					try!(binop(op, ty, l, r, token))
+				},
+				ExprToken::Op(op) => {
+					let (val, ty) = stack.pop().unwrap();
+					try!(unop(op, ty, val, token))
+				},
+				ExprToken::Call(ref id, num) => {
+					let args: Vec<Value> = stack.split_off(stack.len() - num as usize)
+						.into_iter().map(|(val, _)| val).collect();
+					if local(locals, id).is_some() {
+						return Err(Error::not_constant(token));
+					}
+					let def = match self.functions.get(&id[..]) {
+						Some(&def) => def,
+						None       => return Err(Error::not_constant(token)),
+					};
+					match try!(self.call(def, args, token)) {
+						Some(val) => val,
+						None      => return Ok(None), // a call without a value ends the expression
+					}
+				},
+				// Values are single numbers, so vectors are only built at run time.
+				ExprToken::StringLit(_) | ExprToken::Closure(_) |
+				ExprToken::Vector(_) | ExprToken::Extract | ExprToken::Insert => {
+					return Err(Error::not_constant(token));
+				},
+				ExprToken::Cast(_) => {
+					let (val, from) = stack.pop().unwrap();
+					try!(cast(val, from, ty, token))
+				},
+				ExprToken::TypeQuery(..) => unreachable!(), // replaced by the checker
+			};
+			stack.push((val, ty));
+		}
+		Ok(stack.pop().map(|(val, _)| val))
+	}
+
+	fn call(&mut self, def: &'a FnDef, args: Vec<Value>, token: &FullToken) -> Result<Option<Value>> {
+		if self.depth == MAX_DEPTH {
+			return Err(Error::eval_limit(token));
+		}
+		let mut locals = vec![def.params.iter().zip(args.into_iter())
+			.map(|(&(ref id, _), val)| (&id.val[..], Some(val))).collect()];
+		let call = ::std::mem::replace(&mut self.call, token.clone());
+		self.depth += 1;
+		let flow = try!(self.block(&def.block, &mut locals));
+		self.depth -= 1;
+		self.call = call;
+		Ok(match flow {
+			Flow::Return(val) => val,
+			Flow::Next        => None,
+		})
+	}
+
+	fn block(&mut self, block: &'a Node<Block>, locals: &mut Locals<'a>) -> Result<Flow> {
+		locals.push(HashMap::new());
+		for statement in &block.val {
+			try!(self.step(&block.token));
+			match *statement {
+				Statement::Declaration(ref id, _, ref expr) => {
+					let val = match *expr {
+						Some(ref expr) => try!(self.expr(expr, locals)),
+						None           => None,
+					};
+					locals.last_mut().unwrap().insert(&id.val, val);
+				},
+				Statement::Assignment(ref id, ref expr) => {
+					let val = try!(self.expr(expr, locals));
+					match locals.iter_mut().rev().filter_map(|vars| vars.get_mut(&id.val[..])).next() {
+						Some(var) => *var = val,
+						None      => return Err(Error::not_constant(&id.token)),
+					}
+				},
+				Statement::If(ref conditions, ref blocks, ref else_block) => {
+					let mut chosen = else_block.as_ref();
+					for (condition, block) in conditions.iter().zip(blocks.iter()) {
+						if try!(self.expr(condition, locals)) == Some(Value::Bool(true)) {
+							chosen = Some(block);
+							break;
+						}
+					}
+					if let Some(block) = chosen {
+						if let Flow::Return(val) = try!(self.block(block, locals)) {
+							locals.pop();
+							return Ok(Flow::Return(val));
+						}
+					}
+				},
+				Statement::Loop(ref block) => loop {
+					try!(self.step(&block.token));
+					if let Flow::Return(val) = try!(self.block(block, locals)) {
+						locals.pop();
+						return Ok(Flow::Return(val));
+					}
+				},
+				Statement::Block(ref block) => {
+					if let Flow::Return(val) = try!(self.block(block, locals)) {
+						locals.pop();
+						return Ok(Flow::Return(val));
+					}
+				},
+				Statement::Return(ref ret) => {
+					let val = match ret.val {
+						Some(ref expr) => try!(self.expr(expr, locals)),
+						None           => None,
+					};
+					locals.pop();
+					return Ok(Flow::Return(val));
+				},
+				Statement::Expr(ref expr) => { try!(self.expr(expr, locals)); },
+				Statement::Assert(ref expr) => {
+					if try!(self.expr(&expr.val, locals)) != Some(Value::Bool(true)) {
+						return Err(Error::assert_failed(&expr.token));
+					}
+				},
+				Statement::StaticAssert(..) => (),
+				Statement::Print(_) => return Err(Error::not_constant(&self.call)),
+				Statement::Error(ref start) => return Err(Error::not_parsed(&start.token)),
+			}
+		}
+		locals.pop();
+		Ok(Flow::Next)
+	}
+}
+
+fn local(locals: &Locals, id: &str) -> Option<Option<Value>> {
+	locals.iter().rev().filter_map(|vars| vars.get(id)).next().cloned()
+}
+
+/// Makes sure that an integer fits in its type.
+fn int(val: Option<i128>, ty: Type, token: &FullToken) -> Result<Value> {
+	let (min, max) = ty.int_bounds().unwrap();
+	match val {
+		Some(val) if val >= min && (val < 0 || val as u128 <= max) => Ok(Value::Int(val)),
+		_ => Err(Error::overflow(token, ty)),
+	}
+}
+
+/// Wraps an integer around into the range of its type, as two's complement does.
+fn wrap(val: i128, ty: Type) -> Value {
+	let width = ty.int_width().unwrap();
+	if width == 128 {
+		// The wrapping operations of an `i128` already did.
+		return Value::Int(val);
+	}
+	let (min, _) = ty.int_bounds().unwrap();
+	Value::Int(min + val.wrapping_sub(min).rem_euclid(1 << width))
+}
+
+fn int_literal(val: u128, ty: Type, token: &FullToken) -> Result<Value> {
+	match ty {
+		Type::Num(NumType::Float(_)) => Ok(Value::Float(round(val as f64, ty))),
+		_ if is_u128(ty)             => Ok(Value::Int(val as i128)),
+		_                            => int(Some(val as i128), ty, token),
+	}
+}
+
+fn is_u128(ty: Type) -> bool {
+	ty == Type::Num(NumType::Unsigned(UnsignedType::U128))
+}
+
+/// Rounds a float to the precision of its type. `F128` is evaluated at the precision of `F64`.
+fn round(val: f64, ty: Type) -> f64 {
+	match ty {
+		Type::Num(NumType::Float(FloatType::F16)) => round_f16(val),
+		Type::Num(NumType::Float(FloatType::F32)) => val as f32 as f64,
+		_                                         => val,
+	}
+}
+
+/// Rounds to the nearest half-precision float, with ties to even.
+fn round_f16(val: f64) -> f64 {
+	if !val.is_finite() || val == 0.0 {
+		return val;
+	}
+	// Halfs have 10 bits after the point, and subnormals the exponent of the smallest normal.
+	let exp = ((val.to_bits() >> 52) & 0x7ff) as i32 - 1023;
+	let step = 2f64.powi(cmp::max(exp, -14) - 10);
+	let scaled = val / step;
+	let mut rounded = scaled.round();
+	if (rounded - scaled).abs() == 0.5 && rounded % 2.0 != 0.0 {
+		rounded -= scaled.signum();
+	}
+	let val = rounded * step;
+	if val.abs() > 65504.0 { val.signum() * f64::INFINITY } else { val }
+}
+
+/// Converts a value the way `@cast` does: integers wrap around, and floats saturate at the
+/// bounds of integers, with NaN becoming 0. Only valid code points become characters.
+fn cast(val: Value, from: Type, to: Type, token: &FullToken) -> Result<Value> {
+	Ok(match (val, to.int_bounds()) {
+		(Value::Char(val), _) => Value::Int(val as i128),
+		(Value::Int(val), _) if to == Type::Char => match char::from_u32(val as u32) {
+			Some(_) => Value::Char(val as u32),
+			None    => return Err(Error::invalid_char(token, val as u32)),
+		},
+		(Value::Int(val), Some(_)) => wrap(val, to),
+		(Value::Int(val), None) if is_u128(from) => Value::Float(round(val as u128 as f64, to)),
+		(Value::Int(val), None) => Value::Float(round(val as f64, to)),
+		// Casts of floats to integers already saturate.
+		(Value::Float(val), Some(_)) if is_u128(to) => Value::Int(val as u128 as i128),
+		(Value::Float(val), Some((min, max))) => {
+			Value::Int(cmp::min(cmp::max(val as i128, min), max as i128))
+		},
+		(Value::Float(val), None) => Value::Float(round(val, to)),
+		(Value::Bool(_), _) => unreachable!(),
+	})
+}
+
+fn binop(op: Op, ty: Type, l: Value, r: Value, token: &FullToken) -> Result<Value> {
+	Ok(match (l, r) {
+		(Value::Int(l), Value::Int(r)) if is_u128(ty) => try!(u128_binop(op, ty, l as u128, r as u128, token)),
+		(Value::Int(l), Value::Int(r)) => match op {
+			Op::Add => try!(int(l.checked_add(r), ty, token)),
+			Op::Sub => try!(int(l.checked_sub(r), ty, token)),
+			Op::Mul => try!(int(l.checked_mul(r), ty, token)),
+			Op::Div | Op::Mod if r == 0 => return Err(Error::div_by_zero(token)),
+			// The minimum divided by -1 overflows, and the remainder traps for it at run time.
+			Op::Div | Op::Mod if r == -1 && l == ty.int_bounds().unwrap().0 => {
+				return Err(Error::overflow(token, ty));
+			},
+			Op::Div => try!(int(l.checked_div(r), ty, token)),
+			Op::Mod => try!(int(l.checked_rem(r), ty, token)),
+			Op::WrapAdd => wrap(l.wrapping_add(r), ty),
+			Op::WrapSub => wrap(l.wrapping_sub(r), ty),
+			Op::WrapMul => wrap(l.wrapping_mul(r), ty),
+			Op::And => Value::Int(l & r),
+			Op::Or  => Value::Int(l | r),
+			Op::Eq  => Value::Bool(l == r),
+			Op::Neq => Value::Bool(l != r),
+			Op::Gt  => Value::Bool(l >  r),
+			Op::Lt  => Value::Bool(l <  r),
+			Op::Geq => Value::Bool(l >= r),
+			Op::Leq => Value::Bool(l <= r),
+			_ => return Err(Error::not_constant(token)),
+		},
+		(Value::Float(l), Value::Float(r)) => match op {
+			Op::Add | Op::WrapAdd => Value::Float(round(l + r, ty)),
+			Op::Sub | Op::WrapSub => Value::Float(round(l - r, ty)),
+			Op::Mul | Op::WrapMul => Value::Float(round(l * r, ty)),
+			Op::Div => Value::Float(round(l / r, ty)),
+			Op::Mod => Value::Float(round(l % r, ty)),
+			Op::Eq  => Value::Bool(l == r),
+			Op::Neq => Value::Bool(l != r),
+			Op::Gt  => Value::Bool(l >  r),
+			Op::Lt  => Value::Bool(l <  r),
+			Op::Geq => Value::Bool(l >= r),
+			Op::Leq => Value::Bool(l <= r),
+			_ => return Err(Error::not_constant(token)),
+		},
+		(Value::Char(l), Value::Char(r)) => match op {
+			Op::Eq  => Value::Bool(l == r),
+			Op::Neq => Value::Bool(l != r),
+			Op::Gt  => Value::Bool(l >  r),
+			Op::Lt  => Value::Bool(l <  r),
+			Op::Geq => Value::Bool(l >= r),
+			Op::Leq => Value::Bool(l <= r),
+			_ => return Err(Error::not_constant(token)),
+		},
+		(Value::Bool(l), Value::Bool(r)) => match op {
+			Op::And => Value::Bool(l && r),
+			Op::Or  => Value::Bool(l || r),
+			Op::Eq  => Value::Bool(l == r),
+			Op::Neq => Value::Bool(l != r),
+			_ => return Err(Error::not_constant(token)),
+		},
+		_ => return Err(Error::not_constant(token)),
+	})
+}
+
+/// `binop` for the bits of `U128`s.
+fn u128_binop(op: Op, ty: Type, l: u128, r: u128, token: &FullToken) -> Result<Value> {
+	let int = |val: Option<u128>| match val {
+		Some(val) => Ok(Value::Int(val as i128)),
+		None      => Err(Error::overflow(token, ty)),
+	};
+	Ok(match op {
+		Op::Add => try!(int(l.checked_add(r))),
+		Op::Sub => try!(int(l.checked_sub(r))),
+		Op::Mul => try!(int(l.checked_mul(r))),
+		Op::Div | Op::Mod if r == 0 => return Err(Error::div_by_zero(token)),
+		Op::Div => Value::Int((l / r) as i128),
+		Op::Mod => Value::Int((l % r) as i128),
+		Op::WrapAdd => Value::Int(l.wrapping_add(r) as i128),
+		Op::WrapSub => Value::Int(l.wrapping_sub(r) as i128),
+		Op::WrapMul => Value::Int(l.wrapping_mul(r) as i128),
+		Op::And => Value::Int((l & r) as i128),
+		Op::Or  => Value::Int((l | r) as i128),
+		Op::Eq  => Value::Bool(l == r),
+		Op::Neq => Value::Bool(l != r),
+		Op::Gt  => Value::Bool(l >  r),
+		Op::Lt  => Value::Bool(l <  r),
+		Op::Geq => Value::Bool(l >= r),
+		Op::Leq => Value::Bool(l <= r),
+		_ => return Err(Error::not_constant(token)),
+	})
+}
+
+fn unop(op: Op, ty: Type, val: Value, token: &FullToken) -> Result<Value> {
+	let unsigned = match ty {
+		Type::Num(NumType::Unsigned(_)) => true,
+		_                               => false,
+	};
+	Ok(match (op, val) {
+		(Op::Not, Value::Bool(val))  => Value::Bool(!val),
+		(Op::Not, Value::Int(val))   => match ty {
+			Type::Num(NumType::Signed(_)) => Value::Int(!val),
+			_                             => Value::Int(!val & ty.int_bounds().unwrap().1 as i128),
+		},
+		(Op::Neg, Value::Int(0))     => Value::Int(0),
+		(Op::Neg, Value::Int(val))   => try!(int(if unsigned { None } else { val.checked_neg() }, ty, token)),
+		(Op::Neg, Value::Float(val)) => Value::Float(-val),
+		(Op::Inv, Value::Int(0))     => return Err(Error::div_by_zero(token)),
+		(Op::Inv, Value::Int(val)) if unsigned => Value::Int((1 / val as u128) as i128),
+		(Op::Inv, Value::Int(val))   => Value::Int(1 / val),
+		(Op::Inv, Value::Float(val)) => Value::Float(round(1.0 / val, ty)),
+		_ => return Err(Error::not_constant(token)),
+	})
+}
@@ -1,47 +1,187 @@
 use std::collections::HashMap;
-use std::{result, fmt};
+use std::{char, result, fmt, mem};
 use std::ptr;
 
-use builder::llvm;
-use parser::FullToken;
+use builder::{self, llvm};
+use diagnostic::{self, Diagnostic, Fix};
+use parser::{FullToken, Token};
+use parser::ast;
 use parser::ast::*;
-use types::{Type, NumType};
+use types::{Type, NumType, UnsignedType, FnType, TYPE_NAMES, FAMILY_NAMES};
+
+mod eval;
 
 pub struct Program {
-	global_variables: HashMap<String, Variable>,
-	global_functions: HashMap<String, Variable>,
+	pub scope: Scope,
+}
+impl Program {
+	pub fn new() -> Program {
+		Program { scope: Scope::new_root() }
+	}
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Variable {
 	pub ty: Type,
+	pub mutable: bool,
 	pub llvm: Option<llvm::Value>,
 }
 impl Variable {
 	fn new(ty: Type) -> Variable {
-		Variable { ty: ty, llvm: None }
+		Variable { ty: ty, mutable: true, llvm: None }
+	}
+	fn constant(ty: Type) -> Variable {
+		Variable { ty: ty, mutable: false, llvm: None }
 	}
 }
 
+#[derive(Clone)]
 pub struct Function {
+	/// For generic functions, parameters of a type parameter have its constraint as their type.
 	pub params: Vec<(String, Variable)>,
 	pub ret_ty: Option<Type>,
-	pub scope: Scope,
+	pub export: bool,
 	pub llvm: Option<llvm::Value>,
+	pub generic: Option<Generic>,
+	/// For closures, the variables they capture. Closures take these in an environment,
+	/// which is passed before the other parameters.
+	pub captures: Option<Vec<(String, Variable)>>,
+}
+impl Function {
+	pub fn ty(&self) -> Type {
+		let params = self.params.iter().map(|&(_, var)| var.ty).collect();
+		Type::Fn(FnType::new(params, self.ret_ty))
+	}
+}
+
+/// A function found while checking another one, which needs to be checked too.
+/// Either an instance of a generic function, or a closure along with its captures.
+type NewFn = (FnDef, Option<Vec<(String, Variable)>>);
+
+/// The template of a generic function, which is instantiated once for each set of types
+/// it is called with.
+#[derive(Clone)]
+pub struct Generic {
+	pub def: FnDef,
+	/// The constraint of each type parameter.
+	pub constraints: Vec<Type>,
+	/// The type parameter that each parameter has as its type, if any.
+	pub params: Vec<Option<usize>>,
+	/// The type parameter that is returned, if any.
+	pub ret: Option<usize>,
+}
+impl Generic {
+	/// Creates a copy of the function with the type parameters replaced by the given types.
+	fn instance(&self, name: &str, types: &[Type]) -> FnDef {
+		let mut def = self.def.clone();
+		let names: Vec<Id> = def.generics.iter().map(|&(ref id, _)| id.val.clone()).collect();
+		def.name.val = name.to_string();
+		def.generics = Vec::new();
+		{
+			// Type parameters can also appear within function types.
+			let substitute = &mut |type_name: &mut Node<Id>| {
+				let mut out = String::new();
+				let mut word = String::new();
+				for c in type_name.val.chars().chain(Some('\0')) {
+					if c.is_alphanumeric() || c == '_' {
+						word.push(c);
+						continue;
+					}
+					match names.iter().position(|name| *name == word) {
+						Some(i) => out.push_str(&types[i].to_string()),
+						None    => out.push_str(&word),
+					}
+					word.clear();
+					if c != '\0' { out.push(c); }
+				}
+				type_name.val = out;
+			};
+			substitute_fn(&mut def, substitute);
+		}
+		def
+	}
+}
+fn substitute_fn(def: &mut FnDef, substitute: &mut FnMut(&mut Node<Id>)) {
+	for &mut (_, ref mut type_name) in &mut def.params {
+		substitute(type_name);
+	}
+	if let Some(ref mut type_name) = def.ret {
+		substitute(type_name);
+	}
+	substitute_block(&mut def.block.val, substitute);
+}
+fn substitute_block(block: &mut Block, substitute: &mut FnMut(&mut Node<Id>)) {
+	for statement in block {
+		match *statement {
+			Statement::Declaration(_, ref mut type_name, ref mut expr) => {
+				match *type_name {
+					Some(TypeName::Name(ref mut type_name)) => substitute(type_name),
+					Some(TypeName::TypeOf(ref mut expr))    => substitute_expr(&mut expr.val, substitute),
+					None => (),
+				}
+				if let Some(ref mut expr) = *expr {
+					substitute_expr(expr, substitute);
+				}
+			},
+			Statement::Assignment(_, ref mut expr) |
+			Statement::Return(Node { val: Some(ref mut expr), .. }) |
+			Statement::Expr(ref mut expr) => substitute_expr(expr, substitute),
+			Statement::If(ref mut conditions, ref mut blocks, ref mut else_block) => {
+				for condition in conditions {
+					substitute_expr(condition, substitute);
+				}
+				for block in blocks {
+					substitute_block(&mut block.val, substitute);
+				}
+				if let Some(ref mut block) = *else_block {
+					substitute_block(&mut block.val, substitute);
+				}
+			},
+			Statement::Loop(ref mut block) | Statement::Block(ref mut block) => {
+				substitute_block(&mut block.val, substitute);
+			},
+			Statement::StaticAssert(ref mut expr, _) | Statement::Assert(ref mut expr) => {
+				substitute_expr(&mut expr.val, substitute);
+			},
+			_ => (),
+		}
+	}
+}
+fn substitute_expr(expr: &mut Expr, substitute: &mut FnMut(&mut Node<Id>)) {
+	for node in expr {
+		match node.val.0 {
+			ExprToken::Closure(ref mut def) => substitute_fn(def, substitute),
+			ExprToken::TypeQuery(_, ref mut type_name) |
+			ExprToken::Cast(ref mut type_name) => substitute(type_name),
+			_ => (),
+		}
+	}
 }
 
 pub struct Scope {
 	// name: Option<String>,
 	parent: *mut Scope,
 	children: Vec<Box<Scope>>,
+	next_child: usize,
 	variables: HashMap<String, Variable>,
+	/// Where the variables were declared, to point at in errors.
+	tokens: HashMap<String, FullToken>,
+	functions: HashMap<String, Function>,
+	/// Type aliases, which only the root scope has.
+	types: HashMap<String, Type>,
+	ret_ty: Option<Type>,
 }
 impl Scope {
 	pub fn new_root() -> Scope {
 		Scope {
 			parent: ptr::null_mut(),
 			children: Vec::new(),
+			next_child: 0,
 			variables: HashMap::new(),
+			tokens: HashMap::new(),
+			functions: HashMap::new(),
+			types: HashMap::new(),
+			ret_ty: None,
 		}
 	}
 
@@ -53,6 +193,25 @@ impl Scope {
 		&mut self.children[len - 1]
 	}
 
+	/// Enters the next child scope, creating it if no earlier pass has.
+	/// Every pass visits blocks in the same order, so each block keeps its scope.
+	pub fn enter(&mut self) -> &mut Scope {
+		self.next_child += 1;
+		if self.next_child > self.children.len() {
+			self.new()
+		} else {
+			&mut self.children[self.next_child - 1]
+		}
+	}
+
+	/// Resets the traversal so that the next pass starts again from the first child.
+	pub fn rewind(&mut self) {
+		self.next_child = 0;
+		for child in &mut self.children {
+			child.rewind();
+		}
+	}
+
 	pub fn create_var(&mut self, name: String, var: Variable) -> Option<Variable> {
 		self.variables.insert(name, var)
 	}
@@ -67,74 +226,786 @@ impl Scope {
 			},
 		}
 	}
+
+	/// Whether the variable belongs to the outermost scope, rather than to a function.
+	pub fn is_global(&self, name: &str) -> bool {
+		if self.variables.contains_key(name) {
+			self.parent.is_null()
+		} else if self.parent.is_null() {
+			false
+		} else {
+			unsafe { (*self.parent).is_global(name) }
+		}
+	}
+
+	pub fn create_fn(&mut self, name: String, function: Function) -> Option<Function> {
+		self.functions.insert(name, function)
+	}
+
+	pub fn get_fn(&self, name: &str) -> Option<&Function> {
+		match self.functions.get(name) {
+			Some(function) => Some(function),
+			None => if self.parent.is_null() {
+				None
+			} else {
+				unsafe { (*self.parent).get_fn(name) }
+			},
+		}
+	}
+
+	pub fn get_fn_mut(&mut self, name: &str) -> Option<&mut Function> {
+		match self.functions.get_mut(name) {
+			Some(function) => Some(function),
+			None => if self.parent.is_null() {
+				None
+			} else {
+				unsafe { (*self.parent).get_fn_mut(name) }
+			},
+		}
+	}
+
+	pub fn create_type(&mut self, name: String, ty: Type) -> Option<Type> {
+		self.types.insert(name, ty)
+	}
+
+	pub fn get_type(&self, name: &str) -> Option<Type> {
+		match self.types.get(name) {
+			Some(&ty) => Some(ty),
+			None => if self.parent.is_null() {
+				None
+			} else {
+				unsafe { (*self.parent).get_type(name) }
+			},
+		}
+	}
+
+	/// The names of the variables and functions which can be used here.
+	pub fn names(&self) -> Vec<&str> {
+		let mut names: Vec<&str> = self.variables.keys().chain(self.functions.keys())
+			// Closures and instances of generic functions cannot be named.
+			.filter(|name| !name.contains('#') && !name.contains('<'))
+			.map(|name| &name[..])
+			.collect();
+		if !self.parent.is_null() {
+			names.extend(unsafe { (*self.parent).names() });
+		}
+		names
+	}
+
+	/// The names of the type aliases.
+	pub fn type_names(&self) -> Vec<&str> {
+		if !self.parent.is_null() {
+			return unsafe { (*self.parent).type_names() };
+		}
+		self.types.keys().map(|name| &name[..]).collect()
+	}
+
+	/// The names of the aliases of a type, in alphabetical order.
+	pub fn aliases_of(&self, ty: Type) -> Vec<String> {
+		if !self.parent.is_null() {
+			return unsafe { (*self.parent).aliases_of(ty) };
+		}
+		let mut names: Vec<String> = self.types.iter()
+			.filter(|&(_, &alias)| alias == ty)
+			.map(|(name, _)| name.clone())
+			.collect();
+		names.sort();
+		names
+	}
+
+	/// The return type of the function this scope is in.
+	/// `None` for functions without a return value.
+	pub fn ret_ty(&self) -> Option<Type> {
+		match self.ret_ty {
+			Some(ty) => Some(ty),
+			None => if self.parent.is_null() {
+				None
+			} else {
+				unsafe { (*self.parent).ret_ty() }
+			},
+		}
+	}
+}
+
+/// Checks every item of the program, then evaluates the initializers of globals.
+/// Globals are checked in order, so they can only refer to earlier constants.
+/// Checking goes on after an error so that every error is found, but those caused by
+/// earlier ones are left out.
+pub fn check_program(items: &mut ast::Program, program: &mut Program) -> result::Result<(), Vec<Error>> {
+	let mut errors = Vec::new();
+	check_items(items, program, &mut errors);
+	if errors.is_empty() { return Ok(()); }
+	// Only keep the errors caused by others if there is nothing else to show.
+	if errors.iter().any(|e| !e.is_caused()) {
+		errors.retain(|e| !e.is_caused());
+	}
+	Err(errors.into_iter().map(|e| e.with_aliases(&program.scope)).collect())
+}
+fn check_items(items: &mut ast::Program, program: &mut Program, errors: &mut Vec<Error>) {
+	declare_aliases(items, &mut program.scope, errors);
+	// Functions are declared first so that the initializers of globals can call them.
+	for (i, item) in items.iter().enumerate() {
+		if let Item::Function(ref def) = *item {
+			let function = declare_function(def, &program.scope, errors);
+			if program.scope.get_fn(&def.name.val).is_some() {
+				let first = items[..i].iter().filter_map(|item| match *item {
+					Item::Function(ref first) if first.name.val == def.name.val => Some(first),
+					_ => None,
+				}).next().unwrap();
+				errors.push(Error::already_exists(&def.name.token)
+					.label(&first.name.token, "first declared here"));
+				continue;
+			}
+			program.scope.create_fn(def.name.val.clone(), function);
+		}
+	}
+
+	// Closures and instances of generic functions are added to the end of the program
+	// as they are found.
+	let mut instances = Vec::new();
+	for item in items.iter_mut() {
+		let is_const = match *item {
+			Item::Const(..) => true,
+			_               => false,
+		};
+		match *item {
+			Item::Const( ref id, ref mut type_name, ref mut expr) |
+			Item::Static(ref id, ref mut type_name, ref mut expr) => {
+				// Globals which are not valid are still declared, so that using them is not an
+				// error too.
+				let ty = match check_global(id, type_name, expr, &mut program.scope, &mut instances) {
+					Ok(ty) => ty,
+					Err(e) => {
+						errors.push(e);
+						Type::Invalid
+					},
+				};
+				let var = if is_const { Variable::constant(ty) } else { Variable::new(ty) };
+				if let Err(e) = declare_var(&mut program.scope, id.val.clone(), var, &id.token) {
+					errors.push(e);
+				}
+			},
+			Item::Function(_) | Item::TypeAlias(..) | Item::Import(_) => (),
+		}
+	}
+
+	let mut i = 0;
+	loop {
+		for (def, captures) in instances {
+			let mut function = declare_function(&def, &program.scope, errors);
+			function.captures = captures;
+			program.scope.create_fn(def.name.val.clone(), function);
+			items.push(Item::Function(def));
+		}
+		if i == items.len() { break; }
+		instances = match items[i] {
+			Item::Function(ref mut def) if def.generics.is_empty() => {
+				check_function(def, &mut program.scope, errors)
+			},
+			_ => Vec::new(),
+		};
+		i += 1;
+	}
+	program.scope.rewind();
+	// Globals can only be evaluated once the whole program is valid.
+	if errors.is_empty() {
+		eval::fold_globals(items, errors);
+		// Static assertions using globals which failed would report their errors again.
+		if errors.is_empty() { eval::check_static_asserts(items, errors); }
+	}
 }
 
-pub fn passes(block: &mut Block, scope: &mut Scope) -> Result<()> {
-	try!(check_types(block, scope));
-	try!(complete_types(block, scope));
+/// Checks the initializer of a global against its type, and returns the type.
+fn check_global(id: &Node<Id>, type_name: &mut Option<TypeName>, expr: &mut Expr, scope: &mut Scope,
+                instances: &mut Vec<NewFn>) -> Result<Type> {
+	try!(check_constant(expr, scope));
+	let ty = match *type_name {
+		Some(ref mut ty) => try!(type_of_name(ty, scope)),
+		None         => Type::Unknown,
+	};
+	let expr_ty = try!(type_of(expr, scope));
+	if ty.merge(expr_ty) == Type::Invalid { return Err(Error::expected(&id.token, ty, expr_ty)); }
+	let ty = ty.merge(expr_ty).complete();
+	if ty == Type::Invalid { return Err(Error::type_not_determined(&id.token)); }
+	clt_expr(scope, expr, ty);
+	try!(instantiate_expr(expr, scope, &id.val, instances));
+	Ok(ty)
+}
+
+/// Adds the type aliases of the program to the root scope.
+/// Aliases may refer to each other in any order, so those whose target is not known yet
+/// are retried until no more can be resolved.
+fn declare_aliases(items: &ast::Program, scope: &mut Scope, errors: &mut Vec<Error>) {
+	let mut pending: Vec<(&Node<Id>, &Node<Id>)> = Vec::new();
+	for item in items {
+		if let Item::TypeAlias(ref id, ref type_name) = *item {
+			if let Some(&(first, _)) = pending.iter().find(|&&(first, _)| first.val == id.val) {
+				errors.push(Error::already_exists(&id.token).label(&first.token, "first declared here"));
+			} else if Type::from_str(&id.val) != Type::Invalid {
+				errors.push(Error::already_exists(&id.token));
+			} else {
+				pending.push((id, type_name));
+			}
+		}
+	}
+	while !pending.is_empty() {
+		let len = pending.len();
+		pending.retain(|&(id, type_name)| match type_from_name(type_name, scope) {
+			Ok(ty) => { scope.create_type(id.val.clone(), ty); false },
+			Err(_) => true,
+		});
+		if pending.len() == len {
+			// The rest are invalid, which makes their uses invalid rather than unknown.
+			for (id, type_name) in pending {
+				errors.push(Error::unknown_type(&type_name.token).suggest_type(&type_name.val, scope));
+				scope.create_type(id.val.clone(), Type::Invalid);
+			}
+			return;
+		}
+	}
+}
+
+/// Records the error of a result, returning the value or a default.
+fn recover<T>(res: Result<T>, default: T, errors: &mut Vec<Error>) -> T {
+	match res {
+		Ok(val) => val,
+		Err(e)  => {
+			errors.push(e);
+			default
+		},
+	}
+}
+
+/// The signature of a function. Types which are not valid are recorded as errors, and
+/// left as `Invalid`.
+fn declare_function(def: &FnDef, scope: &Scope, errors: &mut Vec<Error>) -> Function {
+	let mut constraints = Vec::new();
+	for &(_, ref constraint) in &def.generics {
+		constraints.push(match *constraint {
+			Some(ref name) => match Type::family_from_str(&name.val) {
+				Type::Invalid => {
+					errors.push(Error::unknown_type(&name.token)
+						.suggest(&name.val, FAMILY_NAMES.iter().cloned()));
+					Type::Invalid
+				},
+				ty => ty,
+			},
+			None => Type::Unknown,
+		});
+	}
+	let type_param = |type_name: &Node<Id>| {
+		def.generics.iter().position(|&(ref id, _)| id.val == type_name.val)
+	};
+	let mentions_type_param = |type_name: &Node<Id>| {
+		type_name.val.split(|c: char| !c.is_alphanumeric() && c != '_').any(|word| {
+			def.generics.iter().any(|&(ref id, _)| id.val == word)
+		})
+	};
+
+	let mut params = Vec::new();
+	let mut generic_params = Vec::new();
+	for &(ref param, ref type_name) in &def.params {
+		let ty = match type_param(type_name) {
+			Some(i) => constraints[i],
+			// Types containing type parameters, such as `fn(T) -> T`, are checked once the
+			// function is instantiated.
+			None if mentions_type_param(type_name) => Type::Unknown,
+			None => recover(type_from_name(type_name, scope), Type::Invalid, errors),
+		};
+		params.push((param.val.clone(), Variable::new(ty)));
+		generic_params.push(type_param(type_name));
+	}
+	let (ret_ty, generic_ret) = match def.ret {
+		Some(ref type_name) => match type_param(type_name) {
+			Some(i) => (Some(constraints[i]), Some(i)),
+			None    => (Some(recover(type_from_name(type_name, scope), Type::Invalid, errors)), None),
+		},
+		None => (None, None),
+	};
+
+	if def.export && !def.generics.is_empty() {
+		errors.push(Error::generic_export(&def.name.token));
+	} else if def.export {
+		let types = params.iter().map(|&(_, var)| var.ty).zip(def.params.iter().map(|&(_, ref name)| name));
+		for (ty, type_name) in types.chain(ret_ty.into_iter().zip(def.ret.iter())) {
+			if ty != Type::Invalid && builder::header::c_type(ty).is_none() {
+				errors.push(Error::export_type(&type_name.token, ty));
+			}
+		}
+	}
+	let generic = if def.generics.is_empty() {
+		None
+	} else {
+		Some(Generic {
+			def: def.clone(),
+			constraints: constraints,
+			params: generic_params,
+			ret: generic_ret,
+		})
+	};
+	Function {
+		params: params,
+		ret_ty: ret_ty,
+		export: def.export,
+		llvm: None,
+		generic: generic,
+		captures: None,
+	}
+}
+
+/// Checks the body of a function and completes its types.
+/// Returns the closures it contains and the instances of generic functions it needs
+/// which do not exist yet. Its types are only completed if it has no errors.
+fn check_function(def: &mut FnDef, root: &mut Scope, errors: &mut Vec<Error>) -> Vec<NewFn> {
+	let (vars, ret_ty) = {
+		let function = root.get_fn(&def.name.val).unwrap();
+		let mut vars = function.captures.clone().unwrap_or(Vec::new());
+		vars.extend(function.params.iter().cloned());
+		(vars, function.ret_ty)
+	};
+	let scope = root.enter();
+	scope.ret_ty = ret_ty;
+	// Captures come before the parameters, and are pointed at by the name of the closure.
+	let captures = vars.len() - def.params.len();
+	for (i, (name, var)) in vars.into_iter().enumerate() {
+		let token = if i < captures { &def.name.token } else { &def.params[i - captures].0.token };
+		recover(declare_var(scope, name, var, token), (), errors);
+	}
+
+	let len = errors.len();
+	check_types(&mut def.block.val, scope, errors);
+	scope.rewind();
+	if ret_ty.is_some() && !def.block.val.iter().any(|statement| diverges(statement).is_some()) {
+		errors.push(Error::missing_return(&def.name.token));
+	}
+	if errors.len() > len { return Vec::new(); }
+	complete_types(&mut def.block.val, scope, errors);
+	scope.rewind();
+	if errors.len() > len { return Vec::new(); }
+	let mut instances = Vec::new();
+	recover(instantiate(&mut def.block.val, scope, &def.name.val, &mut instances), (), errors);
+	scope.rewind();
+	instances
+}
+
+/// Makes sure that the expression only uses constants and calls.
+/// Whether the functions it calls are pure is found when it is evaluated.
+fn check_constant(expr: &Expr, scope: &mut Scope) -> Result<()> {
+	for node in expr {
+		match node.val.0 {
+			ExprToken::Id(ref id) => match scope.get_var(id) {
+				Some(var) if !var.mutable => (),
+				Some(_) => return Err(Error::not_constant(&node.token)),
+				None    => return Err(Error::doesnt_exist(&node.token).suggest(id, scope.names())),
+			},
+			ExprToken::Call(ref id, _) if scope.get_var(id).is_some() => {
+				return Err(Error::not_constant(&node.token));
+			},
+			ExprToken::Closure(_) => return Err(Error::not_constant(&node.token)),
+			_ => (),
+		}
+	}
+	Ok(())
+}
+
+/// Creates a variable, pointing at the earlier declaration if the scope already has one
+/// of the same name.
+fn declare_var(scope: &mut Scope, name: String, var: Variable, token: &FullToken) -> Result<()> {
+	if let Some(first) = scope.tokens.get(&name) {
+		return Err(Error::already_exists(token).label(first, "first declared here"));
+	}
+	scope.tokens.insert(name.clone(), token.clone());
+	scope.create_var(name, var);
 	Ok(())
 }
 
-pub fn check_types(block: &mut Block, scope: &mut Scope) -> Result<()> {
+/// The type of a closure, from its signature.
+fn closure_type(def: &FnDef, scope: &Scope) -> Result<Type> {
+	let mut params = Vec::new();
+	for &(_, ref type_name) in &def.params {
+		params.push(try!(type_from_name(type_name, scope)));
+	}
+	let ret = match def.ret {
+		Some(ref type_name) => Some(try!(type_from_name(type_name, scope))),
+		None                => None,
+	};
+	Ok(Type::Fn(FnType::new(params, ret)))
+}
+
+/// The type of a variable, or of a function used as a value.
+fn type_of_id(scope: &mut Scope, id: &str) -> Option<Type> {
+	if let Some(var) = scope.get_var(id) {
+		return Some(var.ty);
+	}
+	match scope.get_fn(id) {
+		Some(function) if function.generic.is_none() => Some(function.ty()),
+		_ => None,
+	}
+}
+
+/// If no statement after this one can run, the token of what ends it.
+/// Loops only end by returning.
+pub fn diverges(statement: &Statement) -> Option<&FullToken> {
+	fn block(block: &Node<Block>) -> Option<&FullToken> {
+		block.val.iter().filter_map(diverges).next()
+	}
+	match *statement {
+		Statement::Return(ref ret) => Some(&ret.token),
+		Statement::Loop(ref body) => Some(&body.token),
+		Statement::Block(ref body) => block(body),
+		Statement::If(_, ref blocks, Some(ref else_block)) => {
+			if blocks.iter().all(|body| block(body).is_some()) { block(else_block) } else { None }
+		},
+		_ => None,
+	}
+}
+
+/// Adds the variables a closure uses from outside of itself to `vars`.
+pub fn free_vars(def: &FnDef, vars: &mut Vec<Id>) {
+	let mut locals = vec![def.params.iter().map(|&(ref id, _)| id.val.clone()).collect()];
+	free_vars_block(&def.block.val, &mut locals, vars);
+}
+fn free_vars_block(block: &Block, locals: &mut Vec<Vec<Id>>, vars: &mut Vec<Id>) {
+	locals.push(Vec::new());
 	for statement in block {
 		match *statement {
-			Statement::Declaration(ref id, ref type_name, ref mut expr) => {
-				// Create new variable, assign type as merge of given type and assignment.
-				let mut ty = match *type_name {
-					Some(ref ty) => Type::from_str(&ty.val),
-					None         => Type::Unknown,
-				};
-				if expr.is_some() {
-					ty = ty.merge(try!(type_of(&mut expr.as_mut().unwrap(), scope)));
+			Statement::Declaration(ref id, ref type_name, ref expr) => {
+				if let Some(TypeName::TypeOf(ref expr)) = *type_name {
+					free_vars_expr(&expr.val, locals, vars);
 				}
-				if scope.create_var(id.val.clone(), Variable::new(ty)).is_some() {
-					return Err(Error::already_exists(&id.token));
+				if let Some(ref expr) = *expr {
+					free_vars_expr(expr, locals, vars);
 				}
+				locals.last_mut().unwrap().push(id.val.clone());
 			},
-			Statement::Assignment(ref id, ref mut expr) => {
-				// Merge type of given variable with that of assignment.
-				let ty  = try!(type_of(expr, scope));
-				let var = scope.get_var(&id.val);
-				let var = try!(var.ok_or(Error::doesnt_exist(&id.token)));
-				var.ty = var.ty.merge(ty);
+			Statement::Assignment(ref id, ref expr) => {
+				free_vars_expr(expr, locals, vars);
+				use_var(&id.val, locals, vars);
 			},
-			Statement::If(ref mut conditions, ref mut blocks, ref mut else_block) => {
+			Statement::If(ref conditions, ref blocks, ref else_block) => {
 				for condition in conditions {
-					// Assert all condition types are boolean.
-					let ty = try!(type_of(condition, scope));
-					if ty != Type::Bool {
-						return Err(Error::expected(&condition[0].token, ty, Type::Bool));
-					}
+					free_vars_expr(condition, locals, vars);
 				}
 				for block in blocks {
-					try!(check_types(&mut block.val, scope.new()));
+					free_vars_block(&block.val, locals, vars);
 				}
-				if else_block.is_some() {
-					try!(check_types(&mut else_block.as_mut().unwrap().val, scope.new()));
+				if let Some(ref block) = *else_block {
+					free_vars_block(&block.val, locals, vars);
 				}
 			},
-			Statement::Loop(ref mut block)  => try!(check_types(&mut block.val, scope.new())),
-			Statement::Block(ref mut block) => try!(check_types(&mut block.val, scope.new())),
-			Statement::Return(ref mut expr) => match *expr {
-				Some(ref mut expr) => { try!(type_of(expr, scope)); },
-				None => (),
+			Statement::Loop(ref block) | Statement::Block(ref block) => {
+				free_vars_block(&block.val, locals, vars);
+			},
+			Statement::Return(Node { val: Some(ref expr), .. }) |
+			Statement::Expr(ref expr) => free_vars_expr(expr, locals, vars),
+			Statement::Return(_) => (),
+			Statement::StaticAssert(ref expr, _) | Statement::Assert(ref expr) => {
+				free_vars_expr(&expr.val, locals, vars);
 			},
-			Statement::Print(_)  => (),
+			Statement::Print(ref id) => use_var(id, locals, vars),
+			Statement::Error(_) => (),
 		}
 	}
+	locals.pop();
+}
+fn free_vars_expr(expr: &Expr, locals: &mut Vec<Vec<Id>>, vars: &mut Vec<Id>) {
+	for node in expr {
+		match node.val.0 {
+			ExprToken::Id(ref id) | ExprToken::Call(ref id, _) => use_var(id, locals, vars),
+			ExprToken::Closure(ref def) => {
+				let mut inner = Vec::new();
+				free_vars(def, &mut inner);
+				for id in inner {
+					use_var(&id, locals, vars);
+				}
+			},
+			_ => (),
+		}
+	}
+}
+fn use_var(id: &str, locals: &Vec<Vec<Id>>, vars: &mut Vec<Id>) {
+	if !locals.iter().any(|vars| vars.iter().any(|var| var == id)) &&
+	   !vars.iter().any(|var| var == id) {
+		vars.push(id.to_string());
+	}
+}
+
+fn type_from_name(name: &Node<Id>, scope: &Scope) -> Result<Type> {
+	match Type::from_str_with(&name.val, &|alias| scope.get_type(alias)) {
+		Type::Invalid => Err(Error::unknown_type(&name.token).suggest_type(&name.val, scope)),
+		ty            => Ok(ty),
+	}
+}
+
+/// The type given to a declaration. `@typeof` types its expression without evaluating it.
+fn type_of_name(type_name: &mut TypeName, scope: &mut Scope) -> Result<Type> {
+	match *type_name {
+		TypeName::Name(ref name)       => type_from_name(name, scope),
+		TypeName::TypeOf(ref mut expr) => type_of(&mut expr.val, scope),
+	}
+}
+
+/// Replaces the type queries of an expression with the literals they stand for.
+/// Sizes and alignments are literals of any number type, bounds have the queried type.
+fn resolve_type_queries(expr: &mut Expr, scope: &Scope) -> Result<()> {
+	for node in expr {
+		let (query, ty) = match node.val.0 {
+			ExprToken::TypeQuery(query, ref type_name) => (query, try!(type_from_name(type_name, scope))),
+			_ => continue,
+		};
+		let number = Type::Num(NumType::Unknown);
+		node.val = match (query, ty.int_bounds(), ty.float_max()) {
+			(TypeQuery::SizeOf,  _, _) => (ExprToken::IntLit(builder::size_align(ty).0 as u128), number),
+			(TypeQuery::AlignOf, _, _) => (ExprToken::IntLit(builder::size_align(ty).1 as u128), number),
+			(TypeQuery::Min, Some((min, _)), _) => (ExprToken::IntLit(min as u128), ty),
+			(TypeQuery::Max, Some((_, max)), _) => (ExprToken::IntLit(max), ty),
+			(TypeQuery::Min, _, Some(max)) => (ExprToken::FloatLit(-max), ty),
+			(TypeQuery::Max, _, Some(max)) => (ExprToken::FloatLit( max), ty),
+			_ => return Err(Error::expected(&node.token, number, ty)),
+		};
+	}
+	Ok(())
+}
+
+/// Checks the statements of a block, recording their errors.
+pub fn check_types(block: &mut Block, scope: &mut Scope, errors: &mut Vec<Error>) {
+	for statement in block {
+		if let Err(e) = check_statement(statement, scope, errors) {
+			// The variable is declared anyway, so that using it is not an error too.
+			if let Statement::Declaration(ref id, _, _) = *statement {
+				if !scope.tokens.contains_key(&id.val) {
+					scope.tokens.insert(id.val.clone(), id.token.clone());
+					scope.create_var(id.val.clone(), Variable::new(Type::Invalid));
+				}
+			}
+			errors.push(e);
+		}
+	}
+}
+fn check_statement(statement: &mut Statement, scope: &mut Scope, errors: &mut Vec<Error>) -> Result<()> {
+	match *statement {
+		Statement::Declaration(ref id, ref mut type_name, ref mut expr) => {
+			// Create new variable, assign type as merge of given type and assignment.
+			let mut ty = match *type_name {
+				Some(ref mut ty) => try!(type_of_name(ty, scope)),
+				None         => Type::Unknown,
+			};
+			if let Some(ref mut expr) = *expr {
+				let expr_ty = try!(type_of(expr, scope));
+				if ty.merge(expr_ty) == Type::Invalid {
+					return Err(Error::expected(&id.token, ty, expr_ty));
+				}
+				ty = ty.merge(expr_ty);
+			}
+			try!(declare_var(scope, id.val.clone(), Variable::new(ty), &id.token));
+		},
+		Statement::Assignment(ref id, ref mut expr) => {
+			// Merge type of given variable with that of assignment.
+			let ty  = try!(type_of(expr, scope));
+			if scope.get_var(&id.val).is_none() {
+				return Err(Error::doesnt_exist(&id.token).suggest(&id.val, scope.names()));
+			}
+			let var = scope.get_var(&id.val).unwrap();
+			if !var.mutable { return Err(Error::assign_to_const(&id.token)); }
+			if var.ty.merge(ty) == Type::Invalid {
+				return Err(Error::expected(&id.token, var.ty, ty));
+			}
+			var.ty = var.ty.merge(ty);
+		},
+		Statement::If(ref mut conditions, ref mut blocks, ref mut else_block) => {
+			for condition in conditions {
+				recover(check_condition(condition, scope), (), errors);
+			}
+			for block in blocks {
+				check_types(&mut block.val, scope.enter(), errors);
+			}
+			if let Some(ref mut block) = *else_block {
+				check_types(&mut block.val, scope.enter(), errors);
+			}
+		},
+		Statement::Loop(ref mut block) | Statement::Block(ref mut block) => {
+			check_types(&mut block.val, scope.enter(), errors);
+		},
+		Statement::Return(ref mut ret) => match (ret.val.as_mut(), scope.ret_ty()) {
+			(Some(expr), Some(ret_ty)) => {
+				let ty = try!(type_of(expr, scope));
+				if ty.merge(ret_ty) == Type::Invalid {
+					return Err(Error::expected(&ret.token, ret_ty, ty));
+				}
+			},
+			(Some(_), None) => return Err(Error::unexpected_return(&ret.token)),
+			(None, Some(_)) => return Err(Error::missing_return(&ret.token)),
+			(None, None)    => (),
+		},
+		Statement::Expr(ref mut expr) => { try!(type_of_call(expr, scope)); },
+		Statement::StaticAssert(ref mut expr, _) => {
+			try!(check_constant(&expr.val, scope));
+			try!(check_condition(&mut expr.val, scope));
+		},
+		Statement::Assert(ref mut expr) => try!(check_condition(&mut expr.val, scope)),
+		Statement::Print(_)  => (),
+		Statement::Error(ref start) => if let Some(ref id) = start.val {
+			if scope.get_var(id).is_none() && scope.get_fn(id).is_none() {
+				try!(declare_var(scope, id.clone(), Variable::new(Type::Invalid), &start.token));
+			}
+		},
+	}
+	Ok(())
+}
+/// Makes sure that the type of a condition is boolean.
+fn check_condition(condition: &mut Expr, scope: &mut Scope) -> Result<()> {
+	let ty = try!(type_of(condition, scope));
+	if ty != Type::Bool {
+		return Err(Error::expected(&condition[0].token, ty, Type::Bool));
+	}
 	Ok(())
 }
 fn type_of(expr: &mut Expr, scope: &mut Scope) -> Result<Type> {
+	match try!(type_of_call(expr, scope)) {
+		Some(ty) => Ok(ty),
+		None     => Err(Error::no_value(&expr[expr.len() - 1].token)),
+	}
+}
+/// Like `type_of`, but the expression may also be a call of a function without a return type.
+fn type_of_call(expr: &mut Expr, scope: &mut Scope) -> Result<Option<Type>> {
+	try!(resolve_type_queries(expr, scope));
+	let len = expr.len();
 	let mut stack = Vec::new();
-	for &mut Node { ref token, val: (ref e, ref mut ty) } in expr {
+	for (i, &mut Node { ref token, val: (ref e, ref mut ty) }) in expr.iter_mut().enumerate() {
 		match *e {
-			ExprToken::Id(ref id) => match scope.get_var(id) {
-				Some(var) => stack.push(var.ty),
-				None      => return Err(Error::doesnt_exist(token)),
+			ExprToken::Call(ref id, num) if scope.get_var(id).is_some() => {
+				// A call of a function value.
+				let fn_type = match scope.get_var(id).unwrap().ty {
+					Type::Fn(fn_type) => fn_type,
+					_ => return Err(Error::not_a_function(token)),
+				};
+				let params = fn_type.params();
+				if num as usize != params.len() {
+					return Err(Error::wrong_arg_count(token, params.len() as u32, num));
+				}
+				if stack.len() < num as usize { return Err(Error::too_few_operands(token, num)); }
+				let args = stack.split_off(stack.len() - num as usize);
+				for (&param_ty, arg) in params.iter().zip(args.into_iter()) {
+					if param_ty.merge(arg) == Type::Invalid {
+						return Err(Error::expected(token, param_ty, arg));
+					}
+				}
+				match fn_type.ret() {
+					Some(ret_ty) => *ty = ret_ty,
+					None => {
+						if i != len - 1 { return Err(Error::no_value(token)); }
+						return Ok(None);
+					},
+				}
+				stack.push(*ty);
+			},
+			ExprToken::Call(ref id, num) => {
+				let function = match scope.get_fn(id) {
+					Some(function) => function,
+					None => return Err(Error::doesnt_exist(token).suggest(id, scope.names())),
+				};
+				if num as usize != function.params.len() {
+					return Err(Error::wrong_arg_count(token, function.params.len() as u32, num));
+				}
+				if stack.len() < num as usize { return Err(Error::too_few_operands(token, num)); }
+				let args = stack.split_off(stack.len() - num as usize);
+
+				// Arguments of the same type parameter must all have the same type.
+				let mut generics = match function.generic {
+					Some(ref generic) => generic.constraints.clone(),
+					None              => Vec::new(),
+				};
+				for (j, arg) in args.into_iter().enumerate() {
+					let param_ty = function.params[j].1.ty;
+					let generic_param = function.generic.as_ref().and_then(|g| g.params[j]);
+					let merged = match generic_param {
+						Some(g) => {
+							generics[g] = generics[g].merge(arg);
+							generics[g]
+						},
+						None => param_ty.merge(arg),
+					};
+					if merged == Type::Invalid { return Err(Error::expected(token, param_ty, arg)); }
+				}
+
+				match function.generic.as_ref().and_then(|g| g.ret) {
+					Some(g) => *ty = generics[g],
+					None    => match function.ret_ty {
+						Some(ret_ty) => *ty = ret_ty,
+						None => {
+							if i != len - 1 { return Err(Error::no_value(token)); }
+							return Ok(None);
+						},
+					},
+				}
+				stack.push(*ty);
+			},
+			ExprToken::Id(ref id) => match type_of_id(scope, id) {
+				Some(id_ty) => stack.push(id_ty),
+				None        => return Err(Error::doesnt_exist(token).suggest(id, scope.names())),
+			},
+			ExprToken::Closure(ref def) => {
+				// The body is checked once it is lifted out into a function of its own,
+				// but the variables it captures must exist here.
+				let mut vars = Vec::new();
+				free_vars(def, &mut vars);
+				for id in vars {
+					if scope.get_var(&id).is_none() && scope.get_fn(&id).is_none() {
+						let mut token = token.clone();
+						token.token = Token::Ident(id.clone());
+						return Err(Error::doesnt_exist(&token).suggest(&id, scope.names()));
+					}
+				}
+				*ty = try!(closure_type(def, scope));
+				stack.push(*ty);
+			},
+			ExprToken::CharLit(val) => {
+				if char::from_u32(val).is_none() { return Err(Error::invalid_char(token, val)); }
+				stack.push(*ty);
+			},
+			ExprToken::StringLit(_) => return Err(Error::string_value(token)),
+			ExprToken::Cast(ref type_name) => {
+				let to = try!(type_from_name(type_name, scope));
+				let from = try!(stack.pop().ok_or(Error::too_few_operands(token, 1)));
+				if !can_cast(from, to) { return Err(Error::invalid_cast(token, from, to)); }
+				*ty = to;
+				stack.push(*ty);
+			},
+			ExprToken::Vector(num) => {
+				let elem_ty = try!(merge_stack(&mut stack, num, Type::Num(NumType::Unknown), token));
+				if !Type::is_lane_count(num) { return Err(Error::lane_count(token, num)); }
+				*ty = match elem_ty {
+					Type::Num(elem) => Type::Vector(elem, num),
+					_ => unreachable!(),
+				};
+				stack.push(*ty);
+			},
+			ExprToken::Extract => {
+				if stack.len() < 2 { return Err(Error::too_few_operands(token, 2)); }
+				try!(check_lane_index(stack.pop().unwrap(), token));
+				*ty = match stack.pop().unwrap() {
+					Type::Vector(elem, _) => Type::Num(elem),
+					vector_ty => return Err(Error::not_a_vector(token, vector_ty)),
+				};
+				stack.push(*ty);
+			},
+			ExprToken::Insert => {
+				if stack.len() < 3 { return Err(Error::too_few_operands(token, 3)); }
+				let val_ty = stack.pop().unwrap();
+				try!(check_lane_index(stack.pop().unwrap(), token));
+				*ty = match stack.pop().unwrap() {
+					Type::Vector(elem, lanes) => match Type::Num(elem).merge(val_ty) {
+						Type::Num(elem) => Type::Vector(elem, lanes),
+						_ => return Err(Error::expected(token, Type::Num(elem), val_ty)),
+					},
+					vector_ty => return Err(Error::not_a_vector(token, vector_ty)),
+				};
+				stack.push(*ty);
 			},
 			ExprToken::Op(op) => {
 				match op {
-					Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod => {
+					Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod |
+					Op::WrapAdd | Op::WrapSub | Op::WrapMul => {
 						*ty = try!(merge_stack(&mut stack, 2, *ty, token));
 					},
 					Op::Pow => unimplemented!(),
@@ -142,7 +1013,12 @@ fn type_of(expr: &mut Expr, scope: &mut Scope) -> Result<Type> {
 						try!(merge_stack(&mut stack, 2, Type::Bool, token));
 					},
 					Op::Gt | Op::Lt | Op::Geq | Op::Leq => {
-						try!(merge_stack(&mut stack, 2, Type::Num(NumType::Unknown), token));
+						// Characters are ordered by their code points.
+						let operand_ty = try!(merge_stack(&mut stack, 2, Type::Unknown, token));
+						let number = Type::Num(NumType::Unknown);
+						if operand_ty != Type::Char && operand_ty.merge(number) == Type::Invalid {
+							return Err(Error::expected(token, operand_ty, number));
+						}
 					},
 					Op::Eq | Op::Neq => {
 						try!(merge_stack(&mut stack, 2, Type::Unknown, token));
@@ -152,7 +1028,7 @@ fn type_of(expr: &mut Expr, scope: &mut Scope) -> Result<Type> {
 					},
 					Op::Neg => {
 						*ty = try!(merge_stack(&mut stack, 1, *ty, token));
-						if let Type::Num(NumType::Unsigned(_)) = *ty {
+						if let Type::Num(NumType::Unsigned(_)) = ty.elem() {
 							return Err(Error::expected_sign(token));
 						}
 					},
@@ -167,7 +1043,25 @@ fn type_of(expr: &mut Expr, scope: &mut Scope) -> Result<Type> {
 		}
 	}
 	if stack.is_empty() { return Err(Error::empty_expr()); }
-	Ok(stack.pop().unwrap())
+	Ok(stack.pop())
+}
+/// Whether `@cast` converts values of the one type to the other.
+fn can_cast(from: Type, to: Type) -> bool {
+	let u32_type = Type::Num(NumType::Unsigned(UnsignedType::U32));
+	match (from, to) {
+		(Type::Num(_), Type::Num(_)) => true,
+		(Type::Char, ty) | (ty, Type::Char) => ty == Type::Char || u32_type.merge(ty) == u32_type,
+		_ => false,
+	}
+}
+/// Makes sure that the index of a lane is an integer.
+fn check_lane_index(index_ty: Type, token: &FullToken) -> Result<()> {
+	match index_ty.merge(Type::Num(NumType::Unknown)) {
+		Type::Num(NumType::Float(_)) | Type::Invalid => {
+			Err(Error::expected(token, Type::Num(NumType::Unsigned(UnsignedType::Unknown)), index_ty))
+		},
+		_ => Ok(()),
+	}
 }
 fn merge_stack(stack: &mut Vec<Type>, num: u32,
                req_ty: Type, token: &FullToken) -> Result<Type> {
@@ -179,43 +1073,47 @@ fn merge_stack(stack: &mut Vec<Type>, num: u32,
 		if n == Type::Invalid { return Err(Error::expected(token, ty, a)) };
 		ty = n;
 	}
-	ty = ty.merge(req_ty);
-	if ty == Type::Invalid { return Err(Error::expected(token, ty, req_ty)); }
-	Ok(ty)
+	// Vectors are operated on element-wise, so only their elements need the required type.
+	let merged = match (ty, req_ty) {
+		(Type::Vector(_, lanes), Type::Num(num)) => ty.merge(Type::Vector(num, lanes)),
+		_ => ty.merge(req_ty),
+	};
+	if merged == Type::Invalid { return Err(Error::expected(token, req_ty, ty)); }
+	Ok(merged)
 }
 
 /// Finishes type inference by reverting all unknown types to defaults, when possible.
-pub fn complete_types(block: &mut Block, scope: &mut Scope) -> Result<()> {
-	try!(complete_var_types(block, scope));
+pub fn complete_types(block: &mut Block, scope: &mut Scope, errors: &mut Vec<Error>) {
+	complete_var_types(block, scope, errors);
+	scope.rewind();
 	complete_lit_types(block, scope);
 	//try!(assert_valid_types(block, scope));
-	Ok(())
 }
 
-/// Sets the types of all incomplete variables to their defaults.
-pub fn complete_var_types(block: &mut Block, scope: &mut Scope) -> Result<()> {
+/// Sets the types of all incomplete variables to their defaults, recording those which
+/// have none.
+pub fn complete_var_types(block: &mut Block, scope: &mut Scope, errors: &mut Vec<Error>) {
 	for statement in block {
 		match *statement {
 			Statement::Declaration(ref id, _, _) => {
 				let var = scope.get_var(&id.val).unwrap();
 				var.ty = var.ty.complete();
-				if var.ty == Type::Invalid { return Err(Error::type_not_determined(&id.token)); }
+				if var.ty == Type::Invalid { errors.push(Error::type_not_determined(&id.token)); }
 			},
 			Statement::If(_, ref mut blocks, ref mut else_block) => {
 				for block in blocks {
-					try!(complete_var_types(&mut block.val, scope.new()));
+					complete_var_types(&mut block.val, scope.enter(), errors);
 				}
 				if else_block.is_some() {
-					try!(complete_var_types(&mut else_block.as_mut().unwrap().val, scope.new()));
+					complete_var_types(&mut else_block.as_mut().unwrap().val, scope.enter(), errors);
 				}
 			},
 			Statement::Loop(ref mut block) | Statement::Block(ref mut block) => {
-				try!(complete_var_types(&mut block.val, scope.new()));
+				complete_var_types(&mut block.val, scope.enter(), errors);
 			},
 			_ => (),
 		}
 	}
-	Ok(())
 }
 
 /// Reverse infers assignments from the variables to the literals assigned to them.
@@ -232,19 +1130,26 @@ pub fn complete_lit_types(block: &mut Block, scope: &mut Scope) {
 					clt_expr(scope, condition, Type::Bool);
 				}
 				for block in blocks {
-					complete_lit_types(&mut block.val, scope.new());
+					complete_lit_types(&mut block.val, scope.enter());
 				}
 				if else_block.is_some() {
-					complete_lit_types(&mut else_block.as_mut().unwrap().val, scope.new());
+					complete_lit_types(&mut else_block.as_mut().unwrap().val, scope.enter());
 				}
 			},
 			Statement::Loop(ref mut block) | Statement::Block(ref mut block) => {
-				complete_lit_types(&mut block.val, scope.new());
+				complete_lit_types(&mut block.val, scope.enter());
 			}
-			Statement::Return(ref mut expr) => match *expr { // TODO: return type
-				Some(ref mut expr) => clt_expr(scope, expr, Type::Unknown),
+			Statement::Return(ref mut ret) => match ret.val {
+				Some(ref mut expr) => {
+					let ty = scope.ret_ty().unwrap_or(Type::Unknown);
+					clt_expr(scope, expr, ty);
+				},
 				None => (),
 			},
+			Statement::Expr(ref mut expr) => clt_expr(scope, expr, Type::Unknown),
+			Statement::StaticAssert(ref mut expr, _) | Statement::Assert(ref mut expr) => {
+				clt_expr(scope, &mut expr.val, Type::Bool);
+			},
 			_ => (),
 		}
 	}
@@ -255,34 +1160,38 @@ pub fn clt_expr(scope: &mut Scope, expr: &mut Expr, final_type: Type) {
 	for &mut Node { token: _, val: (ref e, ref mut ty) } in expr {
 		match *e {
 			ExprToken::Id(ref id) => {
-				let var_ty = scope.get_var(id).unwrap().ty;
+				let var_ty = type_of_id(scope, id).unwrap();
 				assert!(var_ty.is_known());
+				*ty = var_ty;
 				stack.push((var_ty, Vec::new()));
 			},
 			ExprToken::Op(op) => {
 				match op {
 					Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod |
+					Op::WrapAdd | Op::WrapSub | Op::WrapMul |
 					Op::And | Op::Or => {
 						let (ty2,     tyref2) = stack.pop().unwrap();
 						let (ty1, mut tyref1) = stack.pop().unwrap();
 
+						// The operator itself has the type of its operands.
 						if ty1 == Type::Unknown {
 							if ty2 == Type::Unknown {
 								for blah in tyref2 {
 									tyref1.push(blah); // append() is unstable
 								}
+								tyref1.push(ty);
 								stack.push((Type::Unknown, tyref1));
 							} else {
-								for tyref in tyref1 { *tyref = ty2; }
+								for tyref in tyref1 { set_type(tyref, ty2); }
+								*ty = ty2;
 								stack.push((ty2, Vec::new()));
 							}
 						} else {
 							if ty2 == Type::Unknown {
-								for tyref in tyref2 { *tyref = ty1; }
-								stack.push((ty1, Vec::new()));
-							} else {
-								stack.push((ty1, Vec::new()));
+								for tyref in tyref2 { set_type(tyref, ty1); }
 							}
+							*ty = ty1;
+							stack.push((ty1, Vec::new()));
 						}
 					},
 					Op::Pow => unimplemented!(),
@@ -295,15 +1204,171 @@ pub fn clt_expr(scope: &mut Scope, expr: &mut Expr, final_type: Type) {
 								for tyref in tyref1 { *tyref = tyref.complete(); }
 								for tyref in tyref2 { *tyref = tyref.complete(); }
 							} else {
-								for tyref in tyref1 { *tyref = ty2; }
+								for tyref in tyref1 { set_type(tyref, ty2); }
 							}
 						} else if ty2 == Type::Unknown {
-							for tyref in tyref2 { *tyref = ty1; }
+							for tyref in tyref2 { set_type(tyref, ty1); }
 						}
 						stack.push((Type::Bool, Vec::new()));
 					},
-					Op::Not | Op::Neg => (),
+					Op::Not | Op::Neg | Op::Inv => {
+						let (ty1, mut tyref1) = stack.pop().unwrap();
+						if ty1 == Type::Unknown {
+							tyref1.push(ty);
+						} else {
+							*ty = ty1;
+						}
+						stack.push((ty1, tyref1));
+					},
+					_ => unreachable!(),
+				}
+			},
+			ExprToken::Cast(_) => {
+				// Little about the operand follows from the type it is cast to, except that
+				// only `U32`s become characters.
+				let (_, tyrefs) = stack.pop().unwrap();
+				for tyref in tyrefs {
+					*tyref = if *ty == Type::Char {
+						Type::Num(NumType::Unsigned(UnsignedType::U32))
+					} else {
+						tyref.complete()
+					};
+				}
+				stack.push((*ty, Vec::new()));
+			},
+			ExprToken::Vector(num) => {
+				// The elements and the vector are inferred together.
+				let elems = stack.split_off(stack.len() - num as usize);
+				let known = elems.iter().map(|&(elem_ty, _)| elem_ty).find(|&elem_ty| elem_ty != Type::Unknown);
+				let mut tyrefs = Vec::new();
+				for (_, elem_tyrefs) in elems {
+					tyrefs.extend(elem_tyrefs);
+				}
+				match known {
+					Some(elem_ty) => {
+						for tyref in tyrefs { set_type(tyref, elem_ty); }
+						set_type(ty, elem_ty);
+						stack.push((*ty, Vec::new()));
+					},
+					None => {
+						tyrefs.push(ty);
+						stack.push((Type::Unknown, tyrefs));
+					},
+				}
+			},
+			ExprToken::Extract => {
+				let (_, index_tyrefs) = stack.pop().unwrap();
+				for tyref in index_tyrefs { *tyref = tyref.complete(); }
+				let (vector_ty, mut tyrefs) = stack.pop().unwrap();
+				if vector_ty == Type::Unknown {
+					tyrefs.push(ty);
+					stack.push((Type::Unknown, tyrefs));
+				} else {
+					*ty = vector_ty.elem();
+					stack.push((*ty, Vec::new()));
+				}
+			},
+			ExprToken::Insert => {
+				let (val_ty, val_tyrefs) = stack.pop().unwrap();
+				let (_, index_tyrefs) = stack.pop().unwrap();
+				for tyref in index_tyrefs { *tyref = tyref.complete(); }
+				let (vector_ty, mut tyrefs) = stack.pop().unwrap();
+				tyrefs.extend(val_tyrefs);
+				let known = if vector_ty != Type::Unknown { vector_ty } else { val_ty };
+				if known == Type::Unknown {
+					tyrefs.push(ty);
+					stack.push((Type::Unknown, tyrefs));
+				} else {
+					for tyref in tyrefs { set_type(tyref, known); }
+					set_type(ty, known);
+					stack.push((*ty, Vec::new()));
+				}
+			},
+			ExprToken::Call(ref id, num) if scope.get_var(id).is_some() => {
+				let fn_type = match scope.get_var(id).unwrap().ty {
+					Type::Fn(fn_type) => fn_type,
 					_ => unreachable!(),
+				};
+				let params = fn_type.params();
+				let args = stack.split_off(stack.len() - num as usize);
+				for (j, (arg_ty, tyrefs)) in args.into_iter().enumerate() {
+					if arg_ty == Type::Unknown {
+						for tyref in tyrefs { set_type(tyref, params[j]); }
+					}
+				}
+				if let Some(ret_ty) = fn_type.ret() {
+					stack.push((ret_ty, Vec::new()));
+				}
+			},
+			ExprToken::Call(ref id, num) => {
+				let function = scope.get_fn(id).unwrap();
+				let args = stack.split_off(stack.len() - num as usize);
+				let generic = match function.generic {
+					Some(ref generic) => generic,
+					None => {
+						// Unknown arguments take the type of their parameter.
+						for (j, (arg_ty, tyrefs)) in args.into_iter().enumerate() {
+							if arg_ty == Type::Unknown {
+								for tyref in tyrefs { set_type(tyref, function.params[j].1.ty); }
+							}
+						}
+						if let Some(ret_ty) = function.ret_ty {
+							stack.push((ret_ty, Vec::new()));
+						}
+						continue;
+					},
+				};
+
+				// Group the arguments by their type parameter.
+				let mut groups: Vec<(Type, Vec<&mut Type>)> = generic.constraints.iter().map(|_| {
+					(Type::Unknown, Vec::new())
+				}).collect();
+				for (j, (arg_ty, tyrefs)) in args.into_iter().enumerate() {
+					match generic.params[j] {
+						Some(g) => {
+							if arg_ty != Type::Unknown { groups[g].0 = arg_ty; }
+							for tyref in tyrefs { groups[g].1.push(tyref); }
+						},
+						None => if arg_ty == Type::Unknown {
+							let param_ty = function.params[j].1.ty;
+							for tyref in tyrefs {
+								if param_ty == Type::Unknown {
+									*tyref = tyref.complete();
+								} else {
+									set_type(tyref, param_ty);
+								}
+							}
+						},
+					}
+				}
+
+				let mut ret = None;
+				for (g, (group_ty, tyrefs)) in groups.into_iter().enumerate() {
+					if group_ty != Type::Unknown {
+						for tyref in tyrefs { set_type(tyref, group_ty); }
+						if generic.ret == Some(g) { ret = Some((group_ty, Vec::new())); }
+					} else if generic.ret == Some(g) {
+						// The type may still be inferred from how the result is used.
+						ret = Some((Type::Unknown, tyrefs));
+					} else {
+						let mut group_ty = generic.constraints[g];
+						for tyref in &tyrefs { group_ty = group_ty.merge(**tyref); }
+						let group_ty = group_ty.complete();
+						for tyref in tyrefs { set_type(tyref, group_ty); }
+					}
+				}
+				match ret {
+					Some((Type::Unknown, mut tyrefs)) => {
+						tyrefs.push(ty);
+						stack.push((Type::Unknown, tyrefs));
+					},
+					Some((ret_ty, tyrefs)) => {
+						*ty = ret_ty;
+						stack.push((ret_ty, tyrefs));
+					},
+					None => if let Some(ret_ty) = function.ret_ty {
+						stack.push((ret_ty, Vec::new()));
+					},
 				}
 			},
 			_ => if ty.is_known() {
@@ -313,12 +1378,152 @@ pub fn clt_expr(scope: &mut Scope, expr: &mut Expr, final_type: Type) {
 			},
 		}
 	}
-	let (ty0, tyref0) = stack.pop().unwrap();
-	if ty0 == Type::Unknown {
-		for tyref in tyref0 {
-			*tyref = final_type;
+	if let Some((ty0, tyref0)) = stack.pop() {
+		if ty0 == Type::Unknown {
+			for tyref in tyref0 {
+				if final_type == Type::Unknown {
+					*tyref = tyref.complete();
+				} else {
+					set_type(tyref, final_type);
+				}
+			}
+		}
+	}
+}
+/// Sets the type inferred for a node. The elements of a vector are inferred along with it,
+/// so vectors take the number of lanes they already have and elements take the lane type.
+fn set_type(tyref: &mut Type, ty: Type) {
+	*tyref = match (*tyref, ty.elem()) {
+		(Type::Vector(_, lanes), Type::Num(elem)) => Type::Vector(elem, lanes),
+		_ => ty.elem(),
+	};
+}
+
+/// Renames the calls of generic functions to the instances for their argument types,
+/// and lifts closures out into functions named after the function they are in.
+/// Collects the closures and the instances which do not exist yet.
+fn instantiate(block: &mut Block, scope: &mut Scope, parent: &str,
+               found: &mut Vec<NewFn>) -> Result<()> {
+	for statement in block {
+		match *statement {
+			Statement::Declaration(_, _, Some(ref mut expr)) |
+			Statement::Assignment(_, ref mut expr) |
+			Statement::Expr(ref mut expr) => try!(instantiate_expr(expr, scope, parent, found)),
+			Statement::Return(Node { val: Some(ref mut expr), .. }) |
+			Statement::StaticAssert(Node { val: ref mut expr, .. }, _) |
+			Statement::Assert(Node { val: ref mut expr, .. }) => {
+				try!(instantiate_expr(expr, scope, parent, found));
+			},
+			Statement::If(ref mut conditions, ref mut blocks, ref mut else_block) => {
+				for condition in conditions {
+					try!(instantiate_expr(condition, scope, parent, found));
+				}
+				for block in blocks {
+					try!(instantiate(&mut block.val, scope.enter(), parent, found));
+				}
+				if let Some(ref mut block) = *else_block {
+					try!(instantiate(&mut block.val, scope.enter(), parent, found));
+				}
+			},
+			Statement::Loop(ref mut block) | Statement::Block(ref mut block) => {
+				try!(instantiate(&mut block.val, scope.enter(), parent, found));
+			},
+			_ => (),
+		}
+	}
+	Ok(())
+}
+
+fn instantiate_expr(expr: &mut Expr, scope: &mut Scope, parent: &str,
+                    found: &mut Vec<NewFn>) -> Result<()> {
+	// The types of the values on the stack, as completed by the earlier passes.
+	let mut stack = Vec::new();
+	for &mut Node { ref token, val: (ref mut e, ty) } in expr {
+		match *e {
+			ExprToken::Call(ref id, num) if scope.get_var(id).is_some() => {
+				let len = stack.len() - num as usize;
+				stack.truncate(len);
+				match scope.get_var(id).unwrap().ty {
+					Type::Fn(fn_type) if fn_type.ret().is_some() => stack.push(ty),
+					_ => (),
+				}
+			},
+			ExprToken::Call(ref mut id, num) => {
+				let args = stack.split_off(stack.len() - num as usize);
+				let function = scope.get_fn(id).unwrap();
+				if function.ret_ty.is_some() { stack.push(ty); }
+				let generic = match function.generic {
+					Some(ref generic) => generic,
+					None              => continue,
+				};
+
+				let mut types: Vec<Type> = generic.constraints.iter().map(|ty| ty.complete()).collect();
+				for (j, &arg_ty) in args.iter().enumerate() {
+					if let Some(g) = generic.params[j] { types[g] = arg_ty; }
+				}
+				if let Some(g) = generic.ret { types[g] = ty; }
+				if let Some(&ty) = types.iter().find(|ty| !ty.is_known()) {
+					return Err(Error::expected(token, ty, Type::Unknown));
+				}
+
+				let names: Vec<String> = types.iter().map(|ty| ty.to_string()).collect();
+				let name = format!("{}<{}>", id, names.join(", "));
+				let instance = generic.instance(&name, &types);
+				for (&(_, ref type_name), &arg_ty) in instance.params.iter().zip(args.iter()) {
+					let param_ty = try!(type_from_name(type_name, scope));
+					if param_ty != arg_ty { return Err(Error::expected(token, param_ty, arg_ty)); }
+				}
+				if scope.get_fn(&name).is_none() && !found.iter().any(|f| f.0.name.val == name) {
+					found.push((instance, None));
+				}
+				*id = name;
+			},
+			ExprToken::Closure(_) => {
+				let closures = found.iter().filter(|f| f.1.is_some()).count();
+				let name = format!("{}#closure{}", parent, closures);
+				let mut def = match mem::replace(e, ExprToken::Id(name.clone())) {
+					ExprToken::Closure(def) => *def,
+					_ => unreachable!(),
+				};
+				def.name.val = name;
+
+				// Variables of the enclosing functions are captured, items are not.
+				let mut vars = Vec::new();
+				free_vars(&def, &mut vars);
+				let mut captures = Vec::new();
+				for id in vars {
+					if scope.is_global(&id) { continue; }
+					if let Some(var) = scope.get_var(&id) {
+						captures.push((id, Variable::new(var.ty)));
+					}
+				}
+				found.push((def, Some(captures)));
+				stack.push(ty);
+			},
+			ExprToken::Op(op) if op.is_binary() => {
+				stack.pop();
+				stack.pop();
+				stack.push(ty);
+			},
+			ExprToken::Op(_) | ExprToken::Cast(_) => {
+				stack.pop();
+				stack.push(ty);
+			},
+			ExprToken::Vector(num) => {
+				let len = stack.len() - num as usize;
+				stack.truncate(len);
+				stack.push(ty);
+			},
+			ExprToken::Extract | ExprToken::Insert => {
+				let operands = if *e == ExprToken::Extract { 2 } else { 3 };
+				let len = stack.len() - operands;
+				stack.truncate(len);
+				stack.push(ty);
+			},
+			_ => stack.push(ty),
 		}
 	}
+	Ok(())
 }
 
 /*pub fn assert_valid_types(block: &mut Block, scope: &mut Scope) -> Result<()> {
@@ -329,6 +1534,14 @@ pub fn clt_expr(scope: &mut Scope, expr: &mut Expr, final_type: Type) {
 pub struct Error {
 	pub token: FullToken,
 	pub info: ErrorType,
+	/// The aliases of the types the error mentions, which are shown along with them.
+	pub aliases: Vec<(Type, Vec<String>)>,
+	/// Other places the error concerns, with what they are.
+	pub labels: Vec<(FullToken, String)>,
+	/// How the error may be fixed, such as with a similar name for names which are not known.
+	pub help: Option<String>,
+	/// Edits of the source which fix the error, when they surely do.
+	pub fixes: Vec<Fix>,
 }
 #[derive(Clone, Debug, PartialEq)]
 pub enum ErrorType {
@@ -339,35 +1552,268 @@ pub enum ErrorType {
 	ExpectedSign,         // expected to be negativable
 	EmptyExpr,
 	TypeNotDetermined,
+	UnknownType,
+	NotAFunction,
+	NotConstant,
+	NotParsed,            // evaluated a statement which could not be parsed
+	FailedConstant,       // used a constant which could not be evaluated
+	DivByZero,
+	Overflow(Type),
+	EvalLimit,            // evaluating a global took too many steps or calls
+	StaticAssertFailed(String),
+	AssertFailed,         // an `@assert` failed while evaluating at compile time
+	AssignToConst,
+	UnexpectedReturn,     // returned a value from a function without a return type
+	MissingReturn,        // returned nothing from a function with a return type
+	WrongArgCount(u32, u32), // expected, given
+	NoValue,              // used the result of a function without a return type
+	GenericExport,
+	ExportType(Type),     // a type without a C type in the signature of an exported function
+	InvalidCast(Type, Type), // from, to
+	InvalidChar(u32),     // a surrogate or beyond the last code point
+	NotAVector(Type),
+	LaneCount(u32),       // a vector of a number of lanes which is not supported
+	StringValue,          // a string used as a value, which has no type
 }
 impl Error {
+	fn new(token: FullToken, info: ErrorType) -> Error {
+		Error { token: token, info: info, aliases: Vec::new(), labels: Vec::new(), help: None,
+		        fixes: Vec::new() }
+	}
+	/// Adds an edit to the fix of the error.
+	pub fn fix(mut self, token: &FullToken, text: &str) -> Error {
+		self.fixes.push(Fix { token: token.clone(), text: text.to_string() });
+		self
+	}
+	fn label(mut self, token: &FullToken, message: &str) -> Error {
+		self.labels.push((token.clone(), message.to_string()));
+		self
+	}
+	/// Suggests the most similar of the names for the one the error is about.
+	fn suggest<'a, I>(mut self, name: &str, candidates: I) -> Error
+	                 where I: IntoIterator<Item = &'a str> {
+		self.help = diagnostic::suggest(name, candidates)
+			.map(|suggestion| diagnostic::did_you_mean(name, suggestion));
+		self
+	}
+	/// Suggests a name for a type which is not known, with each of its unknown parts
+	/// replaced by the most similar type, as in `Vec4<F32>` for `Vec4<f32>`.
+	fn suggest_type(mut self, name: &str, scope: &Scope) -> Error {
+		let mut candidates = TYPE_NAMES.to_vec();
+		candidates.extend(scope.type_names());
+		let mut fixed = name.to_string();
+		for part in name.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '.') {
+			let is_vector = part.starts_with("Vec") && part[3..].parse::<u32>().is_ok();
+			if part.is_empty() || part == "fn" || is_vector ||
+			   Type::from_str_with(part, &|alias| scope.get_type(alias)) != Type::Invalid {
+				continue;
+			}
+			match diagnostic::suggest(part, candidates.iter().cloned()) {
+				Some(suggestion) => fixed = fixed.replacen(part, suggestion, 1),
+				None             => return self,
+			}
+		}
+		if fixed != name { self.help = Some(diagnostic::did_you_mean(name, &fixed)); }
+		self
+	}
+	/// Whether the error is caused by an earlier one, which left a type invalid, a statement
+	/// unparsed or a constant without a value.
+	pub fn is_caused(&self) -> bool {
+		match self.info {
+			ErrorType::Expected(a, b) | ErrorType::InvalidCast(a, b) => {
+				a == Type::Invalid || b == Type::Invalid
+			},
+			ErrorType::NotAVector(ty) => ty == Type::Invalid,
+			ErrorType::NotParsed | ErrorType::FailedConstant => true,
+			_ => false,
+		}
+	}
+	/// Finds the aliases of the types the error mentions.
+	fn with_aliases(mut self, scope: &Scope) -> Error {
+		let types = match self.info {
+			ErrorType::Expected(a, b) | ErrorType::InvalidCast(a, b) => vec![a, b],
+			ErrorType::Overflow(ty) | ErrorType::NotAVector(ty) | ErrorType::ExportType(ty) => vec![ty],
+			_ => Vec::new(),
+		};
+		for ty in types {
+			let names = scope.aliases_of(ty);
+			if !names.is_empty() { self.aliases.push((ty, names)); }
+		}
+		self
+	}
+	pub fn diagnostic(&self) -> Diagnostic {
+		let code = match self.info {
+			ErrorType::AlreadyExists      => "E0201",
+			ErrorType::DoesntExist        => "E0202",
+			ErrorType::TooFewOperands(_)  => "E0203",
+			ErrorType::Expected(..)       => "E0204",
+			ErrorType::ExpectedSign       => "E0205",
+			ErrorType::EmptyExpr          => "E0206",
+			ErrorType::TypeNotDetermined  => "E0207",
+			ErrorType::UnknownType        => "E0208",
+			ErrorType::NotAFunction       => "E0209",
+			ErrorType::NotConstant | ErrorType::NotParsed | ErrorType::FailedConstant => "E0210",
+			ErrorType::DivByZero          => "E0211",
+			ErrorType::Overflow(_)        => "E0212",
+			ErrorType::EvalLimit          => "E0213",
+			ErrorType::StaticAssertFailed(_) => "E0214",
+			ErrorType::AssertFailed       => "E0215",
+			ErrorType::AssignToConst      => "E0216",
+			ErrorType::UnexpectedReturn   => "E0217",
+			ErrorType::MissingReturn      => "E0218",
+			ErrorType::WrongArgCount(..)  => "E0219",
+			ErrorType::NoValue            => "E0220",
+			ErrorType::GenericExport      => "E0221",
+			ErrorType::ExportType(_)      => "E0226",
+			ErrorType::InvalidCast(..)    => "E0222",
+			ErrorType::InvalidChar(_)     => "E0223",
+			ErrorType::NotAVector(_)      => "E0224",
+			ErrorType::LaneCount(_)       => "E0225",
+			ErrorType::StringValue        => "E0227",
+		};
+		let mut diagnostic = Diagnostic::error(code, &self.token, Message(self).to_string());
+		diagnostic.labels = self.labels.clone();
+		diagnostic.fixes = self.fixes.clone();
+		match self.info {
+			ErrorType::ExpectedSign => diagnostic.help("cast it to a signed type with `@cast`"),
+			ErrorType::NotConstant | ErrorType::EvalLimit => {
+				diagnostic.note("The initializers of globals are evaluated at compile time.")
+			},
+			ErrorType::AssignToConst => diagnostic.help("declare it with `static` to assign to it"),
+			ErrorType::UnexpectedReturn => {
+				diagnostic.help("give the function a return type, as in `fn f() -> I32`")
+			},
+			ErrorType::MissingReturn => {
+				diagnostic.note("Every path through a function with a return type must return a value.")
+			},
+			ErrorType::GenericExport => {
+				diagnostic.note("C calls exported functions, so they need a single signature.")
+			},
+			ErrorType::ExportType(_) => {
+				diagnostic.note("C calls exported functions, so their parameters and results need C types.")
+			},
+			ErrorType::InvalidChar(_) => {
+				diagnostic.note("Surrogates, from '\\u{D800}' to '\\u{DFFF}', are not characters.")
+			},
+			ErrorType::LaneCount(_) => diagnostic.note("Vectors have 2, 4, 8, 16, 32 or 64 lanes."),
+			ErrorType::StringValue => {
+				diagnostic.note("Strings are only the messages of `@static_assert`, and have no type.")
+			},
+			ErrorType::DoesntExist | ErrorType::UnknownType | ErrorType::Overflow(_) => match self.help {
+				Some(ref help) => diagnostic.help(help),
+				None           => diagnostic,
+			},
+			_ => diagnostic,
+		}
+	}
+	/// Writes a type followed by its aliases, as in `'F64' (aka 'Meters')`.
+	fn write_type(&self, f: &mut fmt::Formatter, ty: Type) -> result::Result<(), fmt::Error> {
+		try!(f.write_fmt(format_args!("'{}'", ty)));
+		match self.aliases.iter().find(|&&(alias, _)| alias == ty) {
+			Some(&(_, ref names)) => f.write_fmt(format_args!(" (aka '{}')", names.join("', '"))),
+			None => Ok(()),
+		}
+	}
 	pub fn already_exists(  token: &FullToken) -> Error {
-		Error { token: token.clone(), info: ErrorType::AlreadyExists }
+		Error::new(token.clone(), ErrorType::AlreadyExists)
 	}
 	pub fn doesnt_exist(    token: &FullToken) -> Error {
-		Error { token: token.clone(), info: ErrorType::DoesntExist }
+		Error::new(token.clone(), ErrorType::DoesntExist)
 	}
 	pub fn too_few_operands(token: &FullToken, num: u32) -> Error {
-		Error { token: token.clone(), info: ErrorType::TooFewOperands(num) }
+		Error::new(token.clone(), ErrorType::TooFewOperands(num))
 	}
 	pub fn expected(       token: &FullToken, a: Type, b: Type) -> Error {
-		Error { token: token.clone(), info: ErrorType::Expected(a, b) }
+		Error::new(token.clone(), ErrorType::Expected(a, b))
 	}
 	pub fn expected_sign(  token: &FullToken) -> Error {
-		Error { token: token.clone(), info: ErrorType::ExpectedSign }
+		Error::new(token.clone(), ErrorType::ExpectedSign)
 	}
 	pub fn empty_expr() -> Error {
-		Error { token: FullToken::none(0, 0), info: ErrorType::EmptyExpr }
+		Error::new(FullToken::none(0, 0), ErrorType::EmptyExpr)
 	}
 	pub fn type_not_determined(token: &FullToken) -> Error {
-		Error { token: token.clone(), info: ErrorType::TypeNotDetermined }
+		Error::new(token.clone(), ErrorType::TypeNotDetermined)
+	}
+	pub fn unknown_type(   token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::UnknownType)
+	}
+	pub fn not_a_function(token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::NotAFunction)
+	}
+	pub fn not_constant(   token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::NotConstant)
+	}
+	pub fn not_parsed(     token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::NotParsed)
+	}
+	pub fn failed_constant(token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::FailedConstant)
+	}
+	pub fn div_by_zero(    token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::DivByZero)
+	}
+	pub fn overflow(       token: &FullToken, ty: Type) -> Error {
+		Error::new(token.clone(), ErrorType::Overflow(ty))
+	}
+	pub fn eval_limit(     token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::EvalLimit)
+	}
+	pub fn static_assert_failed(token: &FullToken, message: String) -> Error {
+		Error::new(token.clone(), ErrorType::StaticAssertFailed(message))
+	}
+	pub fn assert_failed(  token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::AssertFailed)
+	}
+	pub fn assign_to_const(token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::AssignToConst)
+	}
+	pub fn unexpected_return(token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::UnexpectedReturn)
+	}
+	pub fn missing_return( token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::MissingReturn)
+	}
+	pub fn wrong_arg_count(token: &FullToken, expected: u32, given: u32) -> Error {
+		Error::new(token.clone(), ErrorType::WrongArgCount(expected, given))
+	}
+	pub fn no_value(       token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::NoValue)
+	}
+	pub fn generic_export( token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::GenericExport)
+	}
+	pub fn export_type(    token: &FullToken, ty: Type) -> Error {
+		Error::new(token.clone(), ErrorType::ExportType(ty))
+	}
+	pub fn invalid_cast(   token: &FullToken, from: Type, to: Type) -> Error {
+		Error::new(token.clone(), ErrorType::InvalidCast(from, to))
+	}
+	pub fn invalid_char(   token: &FullToken, val: u32) -> Error {
+		Error::new(token.clone(), ErrorType::InvalidChar(val))
+	}
+	pub fn not_a_vector(   token: &FullToken, ty: Type) -> Error {
+		Error::new(token.clone(), ErrorType::NotAVector(ty))
+	}
+	pub fn lane_count(     token: &FullToken, lanes: u32) -> Error {
+		Error::new(token.clone(), ErrorType::LaneCount(lanes))
+	}
+	pub fn string_value(   token: &FullToken) -> Error {
+		Error::new(token.clone(), ErrorType::StringValue)
 	}
 }
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
-		try!(f.write_fmt(format_args!("At {}:{} -- ", self.token.line, self.token.column)));
-		let my_token = &self.token.token;
-		match self.info {
+		fmt::Display::fmt(&self.diagnostic(), f)
+	}
+}
+
+/// The description of an error, without its position.
+struct Message<'a>(&'a Error);
+impl<'a> fmt::Display for Message<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+		let my_token = &self.0.token.token;
+		match self.0.info {
 			ErrorType::AlreadyExists => {
 				try!(f.write_fmt(format_args!("Redeclaration of '{}'.", my_token)))
 			},
@@ -377,14 +1823,88 @@ impl fmt::Display for Error {
 			ErrorType::TooFewOperands(_) => {
 				try!(f.write_fmt(format_args!("Misused operator '{}'", my_token)))
 			},
-			ErrorType::Expected(ref type1, ref type2) => {
-				try!(f.write_fmt(format_args!("Types do not match: '{}' & '{}'", type1, type2)))
+			ErrorType::Expected(type1, type2) => {
+				try!(f.write_str("Types do not match: "));
+				try!(self.0.write_type(f, type1));
+				try!(f.write_str(" & "));
+				try!(self.0.write_type(f, type2))
 			},
 			ErrorType::ExpectedSign => try!(f.write_str("Expected signed type.")),
 			ErrorType::EmptyExpr    => try!(f.write_str("Expected expression.")),
 			ErrorType::TypeNotDetermined => {
 				try!(f.write_fmt(format_args!("Could not determine type: '{}'.", my_token)))
 			},
+			ErrorType::UnknownType => {
+				try!(f.write_fmt(format_args!("Unknown type '{}'.", my_token)))
+			},
+			ErrorType::NotAFunction => {
+				try!(f.write_fmt(format_args!("'{}' is not a function.", my_token)))
+			},
+			ErrorType::NotConstant => {
+				try!(f.write_fmt(format_args!("'{}' cannot be evaluated at compile time.", my_token)))
+			},
+			ErrorType::NotParsed => {
+				try!(f.write_str("A statement which could not be parsed cannot be evaluated."))
+			},
+			ErrorType::FailedConstant => {
+				try!(f.write_fmt(format_args!("'{}' could not be evaluated.", my_token)))
+			},
+			ErrorType::DivByZero => {
+				try!(f.write_str("Division by zero at compile time."))
+			},
+			ErrorType::Overflow(ty) => {
+				try!(f.write_fmt(format_args!("'{}' overflows ", my_token)));
+				try!(self.0.write_type(f, ty));
+				try!(f.write_str(" at compile time."))
+			},
+			ErrorType::EvalLimit => {
+				try!(f.write_str("Took too long to evaluate at compile time."))
+			},
+			ErrorType::StaticAssertFailed(ref message) => {
+				try!(f.write_fmt(format_args!("Static assertion failed: {}", message)))
+			},
+			ErrorType::AssertFailed => {
+				try!(f.write_str("Assertion failed at compile time."))
+			},
+			ErrorType::AssignToConst => {
+				try!(f.write_fmt(format_args!("Cannot assign to constant '{}'.", my_token)))
+			},
+			ErrorType::UnexpectedReturn => {
+				try!(f.write_str("Function without a return type cannot return a value."))
+			},
+			ErrorType::MissingReturn => try!(f.write_str("Expected return value.")),
+			ErrorType::WrongArgCount(expected, given) => {
+				try!(f.write_fmt(format_args!("'{}' takes {} arguments but was given {}.",
+				                              my_token, expected, given)))
+			},
+			ErrorType::NoValue => {
+				try!(f.write_fmt(format_args!("'{}' does not return a value.", my_token)))
+			},
+			ErrorType::GenericExport => {
+				try!(f.write_fmt(format_args!("Generic function '{}' cannot be exported.", my_token)))
+			},
+			ErrorType::ExportType(ty) => {
+				try!(self.0.write_type(f, ty));
+				try!(f.write_str(" has no C type, so exported functions cannot use it."))
+			},
+			ErrorType::InvalidCast(from, to) => {
+				try!(f.write_str("Cannot cast "));
+				try!(self.0.write_type(f, from));
+				try!(f.write_str(" to "));
+				try!(self.0.write_type(f, to));
+				try!(f.write_str("."))
+			},
+			ErrorType::InvalidChar(val) => {
+				try!(f.write_fmt(format_args!("'\\u{{{:X}}}' is not a Unicode scalar value.", val)))
+			},
+			ErrorType::NotAVector(ty) => {
+				try!(self.0.write_type(f, ty));
+				try!(f.write_str(" is not a vector."))
+			},
+			ErrorType::LaneCount(lanes) => {
+				try!(f.write_fmt(format_args!("Vectors cannot have {} lanes.", lanes)))
+			},
+			ErrorType::StringValue => try!(f.write_str("Strings cannot be used as values.")),
 		}
 		Ok(())
 	}
@@ -392,3 +1912,422 @@ impl fmt::Display for Error {
 pub type Result<T> = result::Result<T, Error>;
 
 // type_of
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parser;
+	use types::{FloatType, SignedType};
+
+	/// Checks a program, giving its first error.
+	fn check(code: &str) -> (ast::Program, Result<()>) {
+		let (items, res) = check_all(code);
+		(items, res.map_err(|mut e| e.remove(0)))
+	}
+	fn check_all(code: &str) -> (ast::Program, result::Result<(), Vec<Error>>) {
+		let (mut items, e) = parser::parse(code);
+		assert_eq!(e, Vec::new());
+		let mut program = Program::new();
+		let res = check_program(&mut items, &mut program);
+		(items, res)
+	}
+	/// Checks a valid program, giving the values its globals were folded to, with their types.
+	fn const_values(code: &str) -> Vec<(ExprToken, Type)> {
+		let (items, res) = check(code);
+		assert_eq!(res, Ok(()));
+		items.iter().filter_map(|item| match *item {
+			Item::Const(_, _, ref expr) | Item::Static(_, _, ref expr) => Some(expr[0].val.clone()),
+			_ => None,
+		}).collect()
+	}
+
+	#[test]
+	fn generic_test() {
+		let (items, res) = check(r#"
+			fn max<T: Number>(a: T, b: T) -> T {
+				if a > b { return a }
+				return b
+			}
+			fn main() {
+				x: U8 = max(1, 2)
+				y := max(1.5, 2)
+				z := max(x, 3)
+			}"#);
+		assert_eq!(res, Ok(()));
+		let names: Vec<&str> = items.iter().filter_map(|item| match *item {
+			Item::Function(ref def) => Some(&def.name.val[..]),
+			_ => None,
+		}).collect();
+		assert_eq!(names, vec!["max", "main", "max<U8>", "max<F64>"]);
+
+		let (_, res) = check("fn f<T: Float>(a: T) {}\nfn main() { f(true) }");
+		assert!(res.is_err());
+		let (_, res) = check("fn f(a: I32) {}\nfn main() { f(1, 2) }");
+		assert_eq!(res.unwrap_err().info, ErrorType::WrongArgCount(1, 2));
+		let (_, res) = check("fn f() {}\nfn main() { x := f() }");
+		assert_eq!(res.unwrap_err().info, ErrorType::NoValue);
+		let (_, res) = check("@export fn f<T>(a: T) {}");
+		assert_eq!(res.unwrap_err().info, ErrorType::GenericExport);
+		let (_, errors) = check_all("@export fn f(a: Vec4<I32>, b: I32) -> fn(I32) { return fn(x: I32) {} }");
+		let errors: Vec<(ErrorType, u32)> = errors.unwrap_err().iter()
+			.map(|e| (e.info.clone(), e.token.column)).collect();
+		assert_eq!(errors, vec![(ErrorType::ExportType(Type::from_str("Vec4<I32>")), 17),
+		                        (ErrorType::ExportType(Type::from_str("fn(I32)")), 39)]);
+	}
+
+	#[test]
+	fn return_test() {
+		let (_, res) = check(r#"
+			fn sign(x: I32) -> I32 {
+				if x > 0 { return 1 } else if x < 0 { return -1 } else { return 0 }
+			}
+			fn forever() -> I32 { loop {} }
+			fn main() {
+				f := fn(x: I32) -> I32 { { return x } }
+			}"#);
+		assert_eq!(res, Ok(()));
+		let (_, res) = check("fn f(x: I32) -> I32 { if x > 0 { return 1 } }");
+		let e = res.unwrap_err();
+		assert_eq!((e.info, e.token.token), (ErrorType::MissingReturn, Token::Ident("f".to_string())));
+		let (_, res) = check("fn f() -> I32 { x := 1 }");
+		assert_eq!(res.unwrap_err().info, ErrorType::MissingReturn);
+		let (_, res) = check("fn main() { f := fn() -> I32 { x := 1\n@print x } }");
+		assert_eq!(res.unwrap_err().info, ErrorType::MissingReturn);
+		let (_, res) = check("fn f() -> I32 { return }");
+		assert_eq!(res.unwrap_err().info, ErrorType::MissingReturn);
+	}
+
+	#[test]
+	fn closure_test() {
+		let ty = Type::from_str("fn(I32, fn(Bool)) -> F64");
+		assert_eq!(ty.to_string(), "fn(I32, fn(Bool)) -> F64");
+		assert_eq!(Type::from_str("fn(I32"), Type::Invalid);
+
+		let (items, res) = check(r#"
+			fn inc(x: I32) -> I32 { return x + 1 }
+			fn main() {
+				n := 2
+				f := fn(x: I32) -> I32 { return x + n }
+				g: fn(I32) -> I32 = inc
+				y := f(g(1))
+			}"#);
+		assert_eq!(res, Ok(()));
+		match items[2] {
+			Item::Function(ref def) => assert_eq!(def.name.val, "main#closure0"),
+			_ => panic!(),
+		}
+
+		let (_, res) = check("fn main() { f := fn() -> I32 { return m } }");
+		assert_eq!(res.unwrap_err().info, ErrorType::DoesntExist);
+		let (_, res) = check("fn main() { x := 1\ny := x(2) }");
+		assert_eq!(res.unwrap_err().info, ErrorType::NotAFunction);
+		let (_, res) = check("fn main() { f := fn(x: Bool) {}\nf(1) }");
+		assert!(res.is_err());
+	}
+	#[test]
+	fn static_test() {
+		let (_, res) = check(r#"
+			const LIMIT := 10
+			static count := LIMIT * 2
+			fn main() {
+				count = count + 1
+				f := fn() { count = 0 }
+			}"#);
+		assert_eq!(res, Ok(()));
+
+		let (_, res) = check("static x := 1\nstatic y := f()\nfn f() -> I32 { return x }");
+		assert_eq!(res.unwrap_err().info, ErrorType::NotConstant);
+		let (_, res) = check("static x := 1\nstatic y := x\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::NotConstant);
+		let (_, res) = check("const X := 1\nfn main() { X = 2 }");
+		assert_eq!(res.unwrap_err().info, ErrorType::AssignToConst);
+	}
+
+	#[test]
+	fn eval_test() {
+		assert_eq!(const_values(r#"
+			const STEMS := fact(4) - 20
+			const HALF: F32 = 1 / 2.0
+			static big := STEMS > 3 & true
+			fn fact(n: I32) -> I32 {
+				r := 1
+				loop {
+					if n < 2 { return r }
+					r = r * n
+					n = n - 1
+				}
+			}
+			fn main() {}"#), vec![
+			(ExprToken::IntLit(4), Type::from_str("I32")),
+			(ExprToken::FloatLit(0.5), Type::from_str("F32")),
+			(ExprToken::BoolLit(true), Type::Bool),
+		]);
+
+		let (_, res) = check("const X := 1 / (2 - 2)\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::DivByZero);
+		let (_, res) = check("const X: U8 = 200 + 100\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::Overflow(Type::from_str("U8")));
+		// Every global which fails is reported, but not those which only use one which did.
+		let (_, res) = check_all("const Z := 10 / (5 - 5)\nconst W := Z + 1\nconst Y: U8 = 200 + 100\n\
+		                          static V := W * 2\nconst X := 3\n");
+		let errors: Vec<(ErrorType, u32)> = res.unwrap_err().iter().map(|e| (e.info.clone(), e.token.line)).collect();
+		assert_eq!(errors, vec![(ErrorType::DivByZero, 1), (ErrorType::Overflow(Type::from_str("U8")), 3)]);
+		assert_eq!(const_values("const X: U8 = @wrapping_add(200, 100)\nconst Y: I8 = @wrapping_sub(-128, 1)\n"), vec![
+			(ExprToken::IntLit(44), Type::from_str("U8")),
+			(ExprToken::IntLit(127), Type::from_str("I8")),
+		]);
+		assert_eq!(const_values("const X := @max(U128) / 3\nconst Y: I128 = @min(I128) / -2\n"), vec![
+			(ExprToken::IntLit(u128::max_value() / 3), Type::from_str("U128")),
+			(ExprToken::IntLit(1 << 126), Type::from_str("I128")),
+		]);
+		assert_eq!(const_values("const M := -170141183460469231731687303715884105728i128
+const N: I8 = -0
+"), vec![
+			(ExprToken::IntLit(1 << 127), Type::from_str("I128")),
+			(ExprToken::IntLit(0), Type::from_str("I8")),
+		]);
+		let (_, res) = check("const M := -170141183460469231731687303715884105729i128
+");
+		assert_eq!(res.unwrap_err().info, ErrorType::Overflow(Type::from_str("I128")));
+		let (_, res) = check("const R := -2147483648 % -1
+");
+		assert_eq!(res.unwrap_err().info, ErrorType::Overflow(Type::from_str("I32")));
+		let (_, res) = check("const R: I8 = -128 / -1
+");
+		assert_eq!(res.unwrap_err().info, ErrorType::Overflow(Type::from_str("I8")));
+		let (_, res) = check("const X := f()\nfn f() -> I32 { @print X\nreturn 1 }");
+		assert_eq!(res.unwrap_err().info, ErrorType::NotConstant);
+		let (_, res) = check("const X := f()\nfn f() -> I32 { loop {} }");
+		assert_eq!(res.unwrap_err().info, ErrorType::EvalLimit);
+
+		let (_, res) = check(r#"
+			const X := half(8)
+			fn half(n: I32) -> I32 {
+				@assert(n % 2 == 0)
+				return n / 2
+			}
+			fn main() {
+				@static_assert(X == 4, "half")
+				@assert(X > 0)
+			}"#);
+		assert_eq!(res, Ok(()));
+		let (_, res) = check("fn main() { @static_assert(1 > 2, \"no\") }");
+		assert_eq!(res.unwrap_err().info, ErrorType::StaticAssertFailed("no".to_string()));
+		let (_, res) = check("fn main() { x := 1\n@static_assert(x > 0, \"no\") }");
+		assert_eq!(res.unwrap_err().info, ErrorType::NotConstant);
+		let (_, res) = check("const X := f(1)\nfn f(n: I32) -> I32 {\n@assert(n == 0)\nreturn n }");
+		assert_eq!(res.unwrap_err().info, ErrorType::AssertFailed);
+
+		// Globals are not evaluated through statements which could not be parsed.
+		let (mut items, e) = parser::parse("const X := f()\nfn f() -> I32 {\nx := )\nreturn 1\n}");
+		assert!(!e.is_empty());
+		let errors = check_program(&mut items, &mut Program::new()).unwrap_err();
+		assert_eq!(errors.iter().map(|e| e.info.clone()).collect::<Vec<_>>(), vec![ErrorType::NotParsed]);
+		assert!(errors[0].is_caused());
+	}
+
+	#[test]
+	fn type_query_test() {
+		assert_eq!(const_values(r#"
+			const SIZES := @sizeof(I64) * 10 + @alignof(I16)
+			const LOW := @min(I8)
+			const HIGH: @typeof(@max(U16)) = @max(U16)
+			const FLOAT := @min(F32)
+			fn main() {
+				x: @typeof(LOW + 1) = 3
+			}"#), vec![
+			(ExprToken::IntLit(82), Type::from_str("I32")),
+			(ExprToken::IntLit(-128i128 as u128), Type::from_str("I8")),
+			(ExprToken::IntLit(65535), Type::from_str("U16")),
+			(ExprToken::FloatLit(-3.4028234663852886e38), Type::from_str("F32")),
+		]);
+		// A closure is a pointer to its function and one to its environment.
+		let (_, res) = check(&format!("fn main() {{ @static_assert(@sizeof(fn(I32)) == {}, \"fn\") }}",
+		                              2 * builder::pointer_width() / 8));
+		assert_eq!(res, Ok(()));
+
+		let (_, res) = check("const X := @max(Bool)\n");
+		assert!(res.is_err());
+		let (_, res) = check("const X := @sizeof(Foo)\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::UnknownType);
+	}
+
+	#[test]
+	fn cast_test() {
+		assert_eq!(const_values(r#"
+			const WRAPPED := @cast(U8, -1)
+			const SATURATED := @cast(I8, 1e9)
+			const HALF := @cast(F16, 0.1)
+			const BACK := @cast(I64, HALF * 1000)
+			fn widen<T: Number>(x: T) -> F128 { return @cast(F128, x) }
+			fn main() {
+				x := widen(@cast(I16, 2.5))
+			}"#), vec![
+			(ExprToken::IntLit(255), Type::from_str("U8")),
+			(ExprToken::IntLit(127), Type::from_str("I8")),
+			(ExprToken::FloatLit(0.0999755859375), Type::from_str("F16")),
+			(ExprToken::IntLit(100), Type::from_str("I64")),
+		]);
+
+		let (_, res) = check("const X := @cast(I32, true)\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::InvalidCast(Type::Bool, Type::from_str("I32")));
+	}
+
+	#[test]
+	fn char_test() {
+		assert_eq!(const_values(r#"
+			const CODE := @cast(U32, 'a')
+			const SMILE := @cast(Char, 0x1F600)
+			const LESS := 'a' < '\u{e9}'
+			fn main() {
+				c := 'x'
+				n: U32 = @cast(U32, c)
+			}"#), vec![
+			(ExprToken::IntLit(97), Type::from_str("U32")),
+			(ExprToken::CharLit(0x1F600), Type::Char),
+			(ExprToken::BoolLit(true), Type::Bool),
+		]);
+
+		let (_, res) = check("const X := '\\u{D800}'\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::InvalidChar(0xD800));
+		let (_, res) = check("const X := @cast(Char, 0xD800)\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::InvalidChar(0xD800));
+		let (_, res) = check("const X := @cast(Char, true)\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::InvalidCast(Type::Bool, Type::Char));
+		let (_, res) = check("const X := \"thang\"\n");
+		assert_eq!(res.unwrap_err().info, ErrorType::StringValue);
+		let (_, res) = check("fn main() { x := 1\nx = \"one\" }");
+		assert_eq!(res.unwrap_err().info, ErrorType::StringValue);
+	}
+
+	#[test]
+	fn merge_test() {
+		// Known types only merge with themselves, and partially known ones take the known type.
+		let (i32_type, i64_type) = (Type::from_str("I32"), Type::from_str("I64"));
+		assert_eq!(i32_type.merge(i64_type), Type::Invalid);
+		assert_eq!(i64_type.merge(i32_type), Type::Invalid);
+		assert_eq!(i32_type.merge(Type::Num(NumType::Signed(SignedType::Unknown))), i32_type);
+		assert_eq!(Type::Num(NumType::Unknown).merge(i64_type), i64_type);
+		let f32_type = Type::from_str("F32");
+		assert_eq!(Type::Num(NumType::Float(FloatType::Unknown)).merge(f32_type), f32_type);
+		assert_eq!(f32_type.merge(Type::Num(NumType::Float(FloatType::Unknown))), f32_type);
+		assert_eq!(Type::Num(NumType::Unsigned(UnsignedType::Unknown)).merge(i32_type), Type::Invalid);
+
+		assert_eq!(const_values("const X: I64 = 3 * 2
+const Y: F32 = 1 / 4.0
+"), vec![
+			(ExprToken::IntLit(6), i64_type),
+			(ExprToken::FloatLit(0.25), f32_type),
+		]);
+		let (_, res) = check("fn main() {\nx: I32 = 1\ny: I64 = 2\nz := x + y\n}");
+		assert!(res.is_err());
+		let (_, res) = check("fn main() {\nx: I32 = 1\ny: I64 = x\n}");
+		assert_eq!(res.unwrap_err().info, ErrorType::Expected(i64_type, i32_type));
+	}
+
+	#[test]
+	fn vector_test() {
+		let (items, res) = check(r#"
+			fn sum(v: Vec4<F32>) -> F32 {
+				return @extract(v, 0) + @extract(v, 1) + @extract(v, 2) + @extract(v, 3)
+			}
+			fn main() {
+				a: Vec4<F32> = @vec(1, 2, 3, 4) * @vec(0.5, 1, 1, 1)
+				b := @insert(a, 2, 8)
+				c := sum(b)
+			}"#);
+		assert_eq!(res, Ok(()));
+		let vec4 = Type::from_str("Vec4<F32>");
+		assert_eq!(vec4, Type::Vector(NumType::Float(FloatType::F32), 4));
+		let types: Vec<Type> = match items[1] {
+			Item::Function(ref def) => match def.block.val[0] {
+				Statement::Declaration(_, _, Some(ref expr)) => expr.iter().map(|node| node.val.1).collect(),
+				_ => panic!(),
+			},
+			_ => panic!(),
+		};
+		let f32_type = Type::from_str("F32");
+		assert_eq!(types, vec![f32_type, f32_type, f32_type, f32_type, vec4,
+		                       f32_type, f32_type, f32_type, f32_type, vec4, vec4]);
+
+		let (_, res) = check("fn main() { x := @vec(1, 2, 3) }");
+		assert_eq!(res.unwrap_err().info, ErrorType::LaneCount(3));
+		let (_, res) = check("fn main() { x := @extract(1, 0) }");
+		assert_eq!(res.unwrap_err().info, ErrorType::NotAVector(Type::Num(NumType::Unknown)));
+		let (_, res) = check("fn main() { x := @extract(@vec(1, 2), 0.5) }");
+		assert!(res.is_err());
+		let (_, res) = check("fn main() { x := @vec(1, 2) < @vec(3, 4) }");
+		assert!(res.is_err());
+		assert_eq!(Type::from_str("Vec3<F32>"), Type::Invalid);
+	}
+
+	#[test]
+	fn alias_test() {
+		let (_, res) = check(r#"
+			type Pair = Vec2<Meters>
+			type Meters = F64
+			fn scale(p: Pair, by: Meters) -> Pair {
+				return p * @vec(by, by)
+			}
+			fn main() {
+				a: Meters = 0.5
+				b := scale(@vec(a, 2), a)
+				c := @sizeof(Pair)
+			}"#);
+		assert_eq!(res, Ok(()));
+
+		let (_, res) = check("type A = B\ntype B = A");
+		assert_eq!(res.unwrap_err().info, ErrorType::UnknownType);
+		let (_, res) = check("type U8 = I8");
+		assert_eq!(res.unwrap_err().info, ErrorType::AlreadyExists);
+		let (_, res) = check("type Meters = F64\nfn main() { a: Meters = 1\nb: I32 = 1\nb = a }");
+		assert_eq!(res.unwrap_err().to_string(),
+		           "At 4:1 -- Types do not match: 'I32' & 'F64' (aka 'Meters')");
+	}
+
+	#[test]
+	fn report_all_test() {
+		let (_, res) = check_all(r#"
+			static g := h
+			fn f(a: Foo) -> I32 { return a }
+			fn main() {
+				a := b
+				c: I32 = a + 1
+				d := true + 1
+				if 1 {
+					e := -a
+				}
+				a = 2
+			}"#);
+		let errors: Vec<String> = res.unwrap_err().iter().map(|e| e.to_string()).collect();
+		// Using `a`, `b` and `h` afterwards is not reported again.
+		assert_eq!(errors, vec![
+			"At 3:12 -- Unknown type 'Foo'.",
+			"At 2:16 -- Use of undeclared variable 'h'.",
+			"At 5:10 -- Use of undeclared variable 'b'.",
+			"At 7:15 -- Types do not match: 'Number' & 'Bool'",
+			"At 8:8 -- Types do not match: 'Number' & 'Bool'",
+		]);
+	}
+	#[test]
+	fn suggest_test() {
+		let (_, res) = check_all(r#"
+			type Meters = F64
+			fn total(a: i32, b: Vec4<f32>, c: Meter) {}
+			fn main() {
+				count := 1
+				x := cuont + 1
+				y := totl(1)
+				z := @sizeof(Int64)
+			}"#);
+		let help: Vec<Option<String>> = res.unwrap_err().iter().map(|e| e.diagnostic().help).collect();
+		assert_eq!(help, vec![
+			Some("names are case-sensitive: did you mean `I32`?".to_string()),
+			Some("names are case-sensitive: did you mean `Vec4<F32>`?".to_string()),
+			Some("did you mean `Meters`?".to_string()),
+			Some("did you mean `count`?".to_string()),
+			Some("did you mean `total`?".to_string()),
+			None,
+		]);
+	}
+}
@@ -0,0 +1,454 @@
+use std::{cmp, fmt, iter, result};
+use std::path::PathBuf;
+
+use parser::FullToken;
+
+/// A problem with a program, found by any phase of the compiler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	/// Identifies the kind of problem, and does not change between versions.
+	pub code: &'static str,
+	pub message: String,
+	/// Where the problem is.
+	pub token: FullToken,
+	/// Other places the problem concerns, with what they are.
+	pub labels: Vec<(FullToken, String)>,
+	pub notes: Vec<String>,
+	/// How the problem may be fixed.
+	pub help: Option<String>,
+	/// Edits of the source which fix the problem, when they surely do. They are applied
+	/// together.
+	pub fixes: Vec<Fix>,
+}
+/// An edit of the source, which replaces the span of the token with the text.
+/// An empty span inserts the text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+	pub token: FullToken,
+	pub text: String,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+impl Diagnostic {
+	pub fn error(code: &'static str, token: &FullToken, message: String) -> Diagnostic {
+		Diagnostic {
+			severity: Severity::Error,
+			code: code,
+			message: message,
+			token: token.clone(),
+			labels: Vec::new(),
+			notes: Vec::new(),
+			help: None,
+			fixes: Vec::new(),
+		}
+	}
+	pub fn warning(code: &'static str, token: &FullToken, message: String) -> Diagnostic {
+		Diagnostic { severity: Severity::Warning, .. Diagnostic::error(code, token, message) }
+	}
+	pub fn label(mut self, token: &FullToken, message: &str) -> Diagnostic {
+		self.labels.push((token.clone(), message.to_string()));
+		self
+	}
+	pub fn note(mut self, note: &str) -> Diagnostic {
+		self.notes.push(note.to_string());
+		self
+	}
+	pub fn help(mut self, help: &str) -> Diagnostic {
+		self.help = Some(help.to_string());
+		self
+	}
+	pub fn fix(mut self, token: &FullToken, text: &str) -> Diagnostic {
+		self.fixes.push(Fix { token: token.clone(), text: text.to_string() });
+		self
+	}
+}
+/// The short form, with only the position and the message.
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+		f.write_fmt(format_args!("At {}:{} -- {}", self.token.line, self.token.column, self.message))
+	}
+}
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+		f.write_str(match *self {
+			Severity::Error   => "error",
+			Severity::Warning => "warning",
+		})
+	}
+}
+
+/// Applies the fixes of diagnostics to the code of a file. The fixes of a diagnostic are
+/// left out if they overlap those of an earlier one, or if some are in other files.
+/// Returns the fixed code, and which diagnostics were fixed.
+pub fn apply_fixes(code: &str, file: u32, diagnostics: &[Diagnostic]) -> (String, Vec<bool>) {
+	let mut fixes: Vec<&Fix> = Vec::new();
+	let fixed: Vec<bool> = diagnostics.iter().map(|diagnostic| {
+		let applies = !diagnostic.fixes.is_empty() && diagnostic.fixes.iter().all(|fix| {
+			// Two insertions at the same place overlap too, as their order is not known.
+			fix.token.file == file && !fixes.iter().any(|other| {
+				(fix.token.start < other.token.end && other.token.start < fix.token.end) ||
+				fix.token.start == other.token.start
+			})
+		});
+		if applies { fixes.extend(&diagnostic.fixes); }
+		applies
+	}).collect();
+
+	// Later edits first, so that the offsets of the earlier ones stay the same.
+	fixes.sort_by_key(|fix| cmp::Reverse(fix.token.start));
+	let mut code = code.to_string();
+	for fix in fixes {
+		code.replace_range(fix.token.start..fix.token.end, &fix.text);
+	}
+	(code, fixed)
+}
+
+/// The candidate most like a name which is not known, if any is close enough to be what
+/// was meant. One differing only in case is always close enough.
+pub fn suggest<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+                     where I: IntoIterator<Item = &'a str> {
+	let lower = name.to_lowercase();
+	let max = cmp::max(1, name.chars().count() / 3);
+	let mut best: Option<(usize, &str)> = None;
+	for candidate in candidates {
+		if candidate == name { continue; }
+		let distance = if candidate.to_lowercase() == lower { 0 } else { distance(name, candidate) };
+		if distance > max { continue; }
+		// Ties go to the first in alphabetical order, so that the choice does not depend on
+		// the order of the candidates.
+		if best.map_or(true, |(best, other)| distance < best || (distance == best && candidate < other)) {
+			best = Some((distance, candidate));
+		}
+	}
+	best.map(|(_, candidate)| candidate)
+}
+
+/// The help for a name which is not known, given the name suggested for it.
+pub fn did_you_mean(name: &str, suggestion: &str) -> String {
+	if name.to_lowercase() == suggestion.to_lowercase() {
+		format!("names are case-sensitive: did you mean `{}`?", suggestion)
+	} else {
+		format!("did you mean `{}`?", suggestion)
+	}
+}
+
+/// The number of characters which must be inserted, removed or replaced, or pairs of
+/// them swapped, to turn one string into the other.
+fn distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+	// `d[i][j]` is the distance between the first `i` characters of `a` and the first `j`
+	// of `b`.
+	let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+	for i in 0..a.len() + 1 { d[i][0] = i; }
+	for j in 0..b.len() + 1 { d[0][j] = j; }
+	for i in 1..a.len() + 1 {
+		for j in 1..b.len() + 1 {
+			let replace = d[i - 1][j - 1] + if a[i - 1] == b[j - 1] { 0 } else { 1 };
+			d[i][j] = cmp::min(replace, cmp::min(d[i - 1][j], d[i][j - 1]) + 1);
+			if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+				d[i][j] = cmp::min(d[i][j], d[i - 2][j - 2] + 1);
+			}
+		}
+	}
+	d[a.len()][b.len()]
+}
+
+const RED:    &'static str = "\x1b[1;31m";
+const YELLOW: &'static str = "\x1b[1;33m";
+const BLUE:   &'static str = "\x1b[1;34m";
+const BOLD:   &'static str = "\x1b[1m";
+const RESET:  &'static str = "\x1b[0m";
+
+/// Shows diagnostics along with the lines of source they point to.
+/// Tokens index `files` and `code` by their `file`.
+pub struct Renderer<'a> {
+	pub files: &'a [PathBuf],
+	pub code: &'a [String],
+	pub format: Format,
+	/// Whether to color the output with terminal escape codes.
+	pub color: bool,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+	/// Shows the source along with the message.
+	Human,
+	/// A JSON object on a single line, for tools to read.
+	Json,
+}
+impl<'a> Renderer<'a> {
+	pub fn render(&self, diagnostic: &Diagnostic) -> String {
+		match self.format {
+			Format::Human => self.render_human(diagnostic),
+			Format::Json  => self.render_json(diagnostic),
+		}
+	}
+
+	/// Shows an error which is not in any file, such as one with the command line.
+	pub fn render_message(&self, message: &str, help: Option<&str>) -> String {
+		match self.format {
+			Format::Human => {
+				let mut out = format!("{}: {}\n", self.paint("error", RED), self.paint(message, BOLD));
+				if let Some(help) = help {
+					out.push_str(&format!("{} {}\n", self.paint("= help:", BOLD), help));
+				}
+				out
+			},
+			Format::Json => {
+				format!("{{\"code\":null,\"severity\":\"error\",\"message\":{},\"file\":null,\"line\":null,\
+				         \"column\":null,\"start\":null,\"end\":null,\"labels\":[],\"notes\":[],\"help\":{},\
+				         \"fixes\":[]}}",
+				        json_string(message),
+				        help.map_or("null".to_string(), json_string))
+			},
+		}
+	}
+
+	fn render_human(&self, diagnostic: &Diagnostic) -> String {
+		let token = &diagnostic.token;
+		let width = iter::once(token).chain(diagnostic.labels.iter().map(|label| &label.0))
+			.map(|token| token.line.to_string().len())
+			.max().unwrap();
+		let color = match diagnostic.severity {
+			Severity::Error   => RED,
+			Severity::Warning => YELLOW,
+		};
+		let mut out = format!("{}: {}\n",
+		                      self.paint(&format!("{}[{}]", diagnostic.severity, diagnostic.code), color),
+		                      self.paint(&diagnostic.message, BOLD));
+		self.snippet(&mut out, token, '^', "", color, width, true);
+		for &(ref label, ref text) in &diagnostic.labels {
+			self.snippet(&mut out, label, '-', text, BLUE, width, label.file != token.file);
+		}
+		let pad = " ".repeat(width);
+		for note in &diagnostic.notes {
+			out.push_str(&format!("{} {} {}\n", pad, self.paint("= note:", BOLD), note));
+		}
+		if let Some(ref help) = diagnostic.help {
+			out.push_str(&format!("{} {} {}\n", pad, self.paint("= help:", BOLD), help));
+		}
+		out
+	}
+
+	/// The fields of a diagnostic, with each position given as its file, line, column and
+	/// byte span.
+	fn render_json(&self, diagnostic: &Diagnostic) -> String {
+		let labels: Vec<String> = diagnostic.labels.iter().map(|&(ref token, ref message)| {
+			format!("{{{},\"message\":{}}}", self.json_position(token), json_string(message))
+		}).collect();
+		let notes: Vec<String> = diagnostic.notes.iter().map(|note| json_string(note)).collect();
+		let fixes: Vec<String> = diagnostic.fixes.iter().map(|fix| {
+			format!("{{{},\"text\":{}}}", self.json_position(&fix.token), json_string(&fix.text))
+		}).collect();
+		format!("{{\"code\":{},\"severity\":{},\"message\":{},{},\"labels\":[{}],\"notes\":[{}],\"help\":{},\
+		         \"fixes\":[{}]}}",
+		        json_string(diagnostic.code),
+		        json_string(&diagnostic.severity.to_string()),
+		        json_string(&diagnostic.message),
+		        self.json_position(&diagnostic.token),
+		        labels.join(","),
+		        notes.join(","),
+		        diagnostic.help.as_ref().map_or("null".to_string(), |help| json_string(help)),
+		        fixes.join(","))
+	}
+	fn json_position(&self, token: &FullToken) -> String {
+		format!("\"file\":{},\"line\":{},\"column\":{},\"start\":{},\"end\":{}",
+		        json_string(&self.files[token.file as usize].to_string_lossy()),
+		        token.line, token.column, token.start, token.end)
+	}
+
+	/// Writes the line of a token with its characters marked, followed by the label.
+	fn snippet(&self, out: &mut String, token: &FullToken, mark: char, label: &str, color: &str,
+	           width: usize, path: bool) {
+		let pad = " ".repeat(width);
+		if path {
+			out.push_str(&format!("{}{} {}:{}:{}\n", pad, self.paint("-->", BLUE),
+			                      self.files[token.file as usize].display(), token.line, token.column));
+		}
+		let code = &self.code[token.file as usize];
+		// Tokens made up by the parser may not be on any line.
+		let line = match code.lines().nth(token.line.saturating_sub(1) as usize) {
+			Some(line) if token.line > 0 => line,
+			_ => return,
+		};
+		out.push_str(&format!("{} {}\n", pad, self.paint("|", BLUE)));
+		let number = format!("{:>1$} |", token.line, width);
+		out.push_str(&format!("{} {}\n", self.paint(&number, BLUE), line));
+
+		// Tabs are kept so that the marks line up with the code.
+		let indent: String = line.chars().take(token.column.saturating_sub(1) as usize)
+			.map(|c| if c == '\t' { '\t' } else { ' ' })
+			.collect();
+		let len = code.get(token.start..token.end)
+			.and_then(|text| text.lines().next())
+			.map_or(1, |text| text.chars().count().max(1));
+		let marks: String = iter::repeat(mark).take(len).collect();
+		let marks = if label.is_empty() { marks } else { format!("{} {}", marks, label) };
+		out.push_str(&format!("{} {} {}{}\n", pad, self.paint("|", BLUE), indent, self.paint(&marks, color)));
+	}
+
+	fn paint(&self, text: &str, color: &str) -> String {
+		if self.color {
+			format!("{}{}{}", color, text, RESET)
+		} else {
+			text.to_string()
+		}
+	}
+}
+
+/// Quotes a string for JSON.
+fn json_string(string: &str) -> String {
+	let mut out = String::from("\"");
+	for c in string.chars() {
+		match c {
+			'"'  => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use checker;
+	use parser;
+
+	#[test]
+	fn render_test() {
+		let code = "fn main() {\n\tvalue := 1\n\tx := 2\n\tvalue := 3\n}".to_string();
+		let (mut items, e) = parser::parse(&code);
+		assert_eq!(e, Vec::new());
+		let err = checker::check_program(&mut items, &mut checker::Program::new()).unwrap_err().remove(0);
+		let files = [PathBuf::from("main.eb")];
+		let code = [code];
+		let mut renderer = Renderer { files: &files, code: &code, format: Format::Human, color: false };
+		assert_eq!(renderer.render(&err.diagnostic()),
+		           "error[E0201]: Redeclaration of 'value'.\n \
+		            --> main.eb:4:2\n  \
+		              |\n\
+		            4 | \tvalue := 3\n  \
+		              | \t^^^^^\n  \
+		              |\n\
+		            2 | \tvalue := 1\n  \
+		              | \t----- first declared here\n");
+
+		let diagnostic = Diagnostic::error("E0216", &err.token, "Cannot assign.".to_string())
+			.note("A note.")
+			.help("a fix");
+		assert!(renderer.render(&diagnostic).ends_with("^^^^^\n  = note: A note.\n  = help: a fix\n"));
+		assert_eq!(diagnostic.to_string(), "At 4:2 -- Cannot assign.");
+		assert_eq!(renderer.render_message("Unknown lint 'unused'.", Some("did you mean `unused_variables`?")),
+		           "error: Unknown lint 'unused'.\n= help: did you mean `unused_variables`?\n");
+
+		renderer.format = Format::Json;
+		assert_eq!(renderer.render(&err.diagnostic().help("a \"fix\"")),
+		           "{\"code\":\"E0201\",\"severity\":\"error\",\"message\":\"Redeclaration of 'value'.\",\
+		            \"file\":\"main.eb\",\"line\":4,\"column\":2,\"start\":33,\"end\":38,\
+		            \"labels\":[{\"file\":\"main.eb\",\"line\":2,\"column\":2,\"start\":13,\"end\":18,\
+		            \"message\":\"first declared here\"}],\"notes\":[],\"help\":\"a \\\"fix\\\"\",\"fixes\":[]}");
+		assert_eq!(renderer.render_message("Unknown crate type 'exe'.", None),
+		           "{\"code\":null,\"severity\":\"error\",\"message\":\"Unknown crate type 'exe'.\",\
+		            \"file\":null,\"line\":null,\"column\":null,\"start\":null,\"end\":null,\
+		            \"labels\":[],\"notes\":[],\"help\":null,\"fixes\":[]}");
+		let insert = err.token.after();
+		assert!(renderer.render(&diagnostic.fix(&insert, "_")).ends_with(
+			"\"fixes\":[{\"file\":\"main.eb\",\"line\":4,\"column\":7,\"start\":38,\"end\":38,\"text\":\"_\"}]}"));
+	}
+	#[test]
+	fn json_test() {
+		let files = [PathBuf::from("main.eb")];
+		let render = |code: &str| -> Vec<String> {
+			let (_, e) = parser::parse(code);
+			let code = [code.to_string()];
+			let renderer = Renderer { files: &files, code: &code, format: Format::Json, color: false };
+			e.iter().map(|e| renderer.render(&e.diagnostic())).collect()
+		};
+		// Errors of the tokenizer.
+		assert_eq!(render("fn main() {\n\tx := 08z\n\ty := \"open\n}"), vec![
+			"{\"code\":\"E0111\",\"severity\":\"error\",\"message\":\"Unknown number suffix 'z'.\",\
+			 \"file\":\"main.eb\",\"line\":2,\"column\":7,\"start\":18,\"end\":21,\"labels\":[],\
+			 \"notes\":[\"The suffixes are the names of number types in lowercase, such as `u8` or `f32`, \
+			 or `i`, `u` and `f` alone.\"],\"help\":null,\"fixes\":[]}",
+			"{\"code\":\"E0113\",\"severity\":\"error\",\"message\":\"Unterminated string.\",\
+			 \"file\":\"main.eb\",\"line\":3,\"column\":7,\"start\":28,\"end\":29,\"labels\":[],\
+			 \"notes\":[\"It starts here and goes on to the end of the file.\"],\"help\":null,\"fixes\":[]}",
+		]);
+		// Errors of the parser, with their help and fixes.
+		assert_eq!(render("fn main() {\n\tx := (1 + 2\n\t@prnt x\n}"), vec![
+			"{\"code\":\"E0102\",\"severity\":\"error\",\"message\":\"Expected ')', found ','.\",\
+			 \"file\":\"main.eb\",\"line\":2,\"column\":13,\"start\":24,\"end\":25,\"labels\":[],\"notes\":[],\
+			 \"help\":\"insert `)`\",\"fixes\":[{\"file\":\"main.eb\",\"line\":2,\"column\":13,\"start\":24,\
+			 \"end\":24,\"text\":\")\"}]}",
+			"{\"code\":\"E0104\",\"severity\":\"error\",\"message\":\"Not a valid special: '@prnt'\",\
+			 \"file\":\"main.eb\",\"line\":3,\"column\":2,\"start\":26,\"end\":31,\"labels\":[],\"notes\":[],\
+			 \"help\":\"did you mean `@print`?\",\"fixes\":[]}",
+		]);
+		// Columns count characters, so fixes after text which is not ASCII line up.
+		assert_eq!(render("fn main() {\n\tx := (\"é\"\n}"), vec![
+			"{\"code\":\"E0102\",\"severity\":\"error\",\"message\":\"Expected ')', found ','.\",\
+			 \"file\":\"main.eb\",\"line\":2,\"column\":11,\"start\":23,\"end\":24,\"labels\":[],\"notes\":[],\
+			 \"help\":\"insert `)`\",\"fixes\":[{\"file\":\"main.eb\",\"line\":2,\"column\":11,\"start\":23,\
+			 \"end\":23,\"text\":\")\"}]}",
+		]);
+	}
+	#[test]
+	fn fix_test() {
+		let code = "const BIG := -3000000000\ntype Id I32\nconst X = 1\nfn main() {\n\t\
+		            x: I32 5\n\ty: 2\n\tz := (x + y  // sum\n\t@assert(z == 8\n}";
+		let (mut items, e) = parser::parse(code);
+		let mut diagnostics: Vec<Diagnostic> = e.iter().map(|e| e.diagnostic()).collect();
+		assert_eq!(diagnostics.len(), 6);
+		assert_eq!(diagnostics[0].help, Some("insert `=`".to_string()));
+		assert_eq!(diagnostics[1].help, Some("write `:=` in place of `=`".to_string()));
+		let errors = checker::check_program(&mut items, &mut checker::Program::new()).unwrap_err();
+		diagnostics.extend(errors.iter().filter(|e| !e.is_caused()).map(|e| e.diagnostic()));
+		let (fixed, applied) = apply_fixes(code, 0, &diagnostics);
+		assert!(applied.iter().all(|&applied| applied));
+		assert_eq!(fixed, "const BIG := -3000000000i64\ntype Id = I32\nconst X := 1\nfn main() {\n\t\
+		                   x: I32 = 5\n\ty:= 2\n\tz := (x + y)  // sum\n\t@assert(z == 8)\n}");
+		let (mut items, e) = parser::parse(&fixed);
+		assert_eq!(e, Vec::new());
+		checker::check_program(&mut items, &mut checker::Program::new()).unwrap();
+		// A `)` is not surely missing before a `,`.
+		let (_, e) = parser::parse("fn main() {\n\tx := (1, 2)\n}");
+		assert_eq!(e[0].fixes, Vec::new());
+
+		// Only the first of fixes which overlap is applied, and those in other files are not.
+		let token = |start, end, file| FullToken { start: start, end: end, file: file, .. FullToken::none(1, 1) };
+		let diagnostics = [
+			Diagnostic::error("E0102", &token(0, 0, 0), String::new()).fix(&token(4, 5, 0), "x"),
+			Diagnostic::error("E0102", &token(0, 0, 0), String::new()).fix(&token(0, 0, 0), "(")
+				.fix(&token(3, 3, 0), ")"),
+			Diagnostic::error("E0102", &token(0, 0, 0), String::new()).fix(&token(3, 3, 0), "]"),
+			Diagnostic::error("E0102", &token(0, 0, 0), String::new()).fix(&token(4, 6, 0), "y"),
+			Diagnostic::error("E0102", &token(0, 0, 0), String::new()).fix(&token(1, 2, 1), "z"),
+			Diagnostic::error("E0102", &token(0, 0, 0), String::new()),
+		];
+		assert_eq!(apply_fixes("a+b c", 0, &diagnostics),
+		           ("(a+b) x".to_string(), vec![true, true, false, false, false, false]));
+	}
+	#[test]
+	fn suggest_test() {
+		let names = ["count", "counter", "total", "I32"];
+		assert_eq!(suggest("cuont", names.iter().cloned()), Some("count"));
+		assert_eq!(suggest("countr", names.iter().cloned()), Some("count"));
+		assert_eq!(suggest("i32", names.iter().cloned()), Some("I32"));
+		assert_eq!(suggest("x", names.iter().cloned()), None);
+		assert_eq!(suggest("sum", names.iter().cloned()), None);
+		assert_eq!(did_you_mean("i32", "I32"), "names are case-sensitive: did you mean `I32`?");
+
+		let code = "fn main() {\n\t@prnt x\n}".to_string();
+		let (_, e) = parser::parse(&code);
+		assert_eq!(e[0].diagnostic().help, Some("did you mean `@print`?".to_string()));
+	}
+}
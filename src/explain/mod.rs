@@ -0,0 +1,145 @@
+use lint::{self, LINTS};
+
+/// Long descriptions of the error codes, for `--explain`.
+/// Each has an erroneous example, which fails with its code, followed by corrected ones,
+/// which compile. An example of several files starts each with a `// name.eb` line.
+pub const EXPLANATIONS: [(&'static str, &'static str); 44] = [
+	("E0101", include_str!("E0101.md")),
+	("E0102", include_str!("E0102.md")),
+	("E0103", include_str!("E0103.md")),
+	("E0104", include_str!("E0104.md")),
+	("E0105", include_str!("E0105.md")),
+	("E0106", include_str!("E0106.md")),
+	("E0107", include_str!("E0107.md")),
+	("E0108", include_str!("E0108.md")),
+	("E0109", include_str!("E0109.md")),
+	("E0110", include_str!("E0110.md")),
+	("E0111", include_str!("E0111.md")),
+	("E0112", include_str!("E0112.md")),
+	("E0113", include_str!("E0113.md")),
+	("E0114", include_str!("E0114.md")),
+	("E0115", include_str!("E0115.md")),
+	("E0116", include_str!("E0116.md")),
+	("E0117", include_str!("E0117.md")),
+	("E0201", include_str!("E0201.md")),
+	("E0202", include_str!("E0202.md")),
+	("E0203", include_str!("E0203.md")),
+	("E0204", include_str!("E0204.md")),
+	("E0205", include_str!("E0205.md")),
+	("E0206", include_str!("E0206.md")),
+	("E0207", include_str!("E0207.md")),
+	("E0208", include_str!("E0208.md")),
+	("E0209", include_str!("E0209.md")),
+	("E0210", include_str!("E0210.md")),
+	("E0211", include_str!("E0211.md")),
+	("E0212", include_str!("E0212.md")),
+	("E0213", include_str!("E0213.md")),
+	("E0214", include_str!("E0214.md")),
+	("E0215", include_str!("E0215.md")),
+	("E0216", include_str!("E0216.md")),
+	("E0217", include_str!("E0217.md")),
+	("E0218", include_str!("E0218.md")),
+	("E0219", include_str!("E0219.md")),
+	("E0220", include_str!("E0220.md")),
+	("E0221", include_str!("E0221.md")),
+	("E0222", include_str!("E0222.md")),
+	("E0223", include_str!("E0223.md")),
+	("E0224", include_str!("E0224.md")),
+	("E0225", include_str!("E0225.md")),
+	("E0226", include_str!("E0226.md")),
+	("E0227", include_str!("E0227.md")),
+];
+
+/// The description of an error code, or of a lint. Codes are not case-sensitive.
+pub fn explain(code: &str) -> Option<String> {
+	if let Some(&(_, text)) = EXPLANATIONS.iter().find(|&&(other, _)| other.eq_ignore_ascii_case(code)) {
+		return Some(text.to_string());
+	}
+	LINTS.iter().find(|lint| lint.name == code).map(|lint| {
+		let level = match lint.default {
+			lint::Level::Allow => "allowed",
+			lint::Level::Warn  => "a warning",
+			lint::Level::Deny  => "an error",
+		};
+		format!("The `{}` lint finds {}.\n\nIt is {} by default. Set its level with `-A`, `-W` or `-D`, \
+		         or allow it in a function with `@allow({})`.\n", lint.name, lint.description, level, lint.name)
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::env;
+	use std::fs::{self, File};
+	use std::io::Write;
+	use checker;
+	use parser;
+
+	/// The code blocks of an explanation.
+	fn examples(text: &str) -> Vec<String> {
+		let mut examples = Vec::new();
+		let mut example: Option<String> = None;
+		for line in text.lines() {
+			if line == "```" {
+				match example.take() {
+					Some(code) => examples.push(code),
+					None       => example = Some(String::new()),
+				}
+			} else if let Some(ref mut code) = example {
+				code.push_str(line);
+				code.push('\n');
+			}
+		}
+		assert!(example.is_none());
+		examples
+	}
+
+	/// Writes the files of an example to a directory of its own, and returns the codes of
+	/// the errors of compiling the first.
+	fn compile(dir: &str, example: &str) -> Vec<&'static str> {
+		let dir = env::temp_dir().join(dir);
+		fs::create_dir_all(&dir).unwrap();
+		let mut files: Vec<(String, String)> = Vec::new();
+		for line in example.lines() {
+			if line.starts_with("// ") && line.ends_with(".eb") {
+				files.push((line[3..].to_string(), String::new()));
+			} else {
+				if files.is_empty() { files.push(("main.eb".to_string(), String::new())); }
+				let code = &mut files.last_mut().unwrap().1;
+				code.push_str(line);
+				code.push('\n');
+			}
+		}
+		for &(ref name, ref code) in &files {
+			fs::create_dir_all(dir.join(name).parent().unwrap()).unwrap();
+			File::create(dir.join(name)).unwrap().write_all(code.as_bytes()).unwrap();
+		}
+
+		let (mut items, _, e) = parser::load(&dir.join(&files[0].0), &files[0].1);
+		let mut codes: Vec<&'static str> = e.iter().map(|e| e.diagnostic().code).collect();
+		if let Err(errors) = checker::check_program(&mut items, &mut checker::Program::new()) {
+			codes.extend(errors.iter().map(|error| error.diagnostic().code));
+		}
+		codes
+	}
+
+	#[test]
+	fn explanations_test() {
+		for &(code, text) in EXPLANATIONS.iter() {
+			let examples = examples(text);
+			assert!(examples.len() >= 2, "{} needs an erroneous and a corrected example", code);
+			for (i, example) in examples.iter().enumerate() {
+				let codes = compile(&format!("thang_explain_{}_{}", code, i), example);
+				if i == 0 {
+					assert!(codes.contains(&code), "the example of {} fails with {:?}", code, codes);
+				} else {
+					assert!(codes.is_empty(), "the corrected example of {} fails with {:?}", code, codes);
+				}
+			}
+		}
+
+		assert!(explain("e0204").unwrap().starts_with("A value has a type"));
+		assert!(explain("unused_variables").unwrap().contains("a warning by default"));
+		assert_eq!(explain("E0999"), None);
+	}
+}
@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+use std::i128;
+
+use checker;
+use diagnostic::{self, Diagnostic};
+use parser::FullToken;
+use parser::ast::*;
+
+/// How the problems a lint finds are reported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+	Allow,
+	Warn,
+	/// Reported as errors, which stop the program from being built.
+	Deny,
+}
+
+/// A check for code which is valid, but likely a mistake.
+pub struct Lint {
+	pub name: &'static str,
+	pub default: Level,
+	pub description: &'static str,
+}
+
+pub const LINTS: [Lint; 7] = [
+	Lint {
+		name: "unused_variables",
+		default: Level::Warn,
+		description: "variables and parameters which are never read",
+	},
+	Lint {
+		name: "unused_assignments",
+		default: Level::Warn,
+		description: "values which are overwritten or go out of scope before they are read",
+	},
+	Lint {
+		name: "unreachable_code",
+		default: Level::Warn,
+		description: "statements after a `return`, or after a `loop`, which only ends by returning",
+	},
+	Lint {
+		name: "shadowing",
+		default: Level::Allow,
+		description: "declarations of a name which a variable of an enclosing block already has",
+	},
+	Lint {
+		name: "constant_conditions",
+		default: Level::Warn,
+		description: "conditions of `if` which are made up of literals only",
+	},
+	Lint {
+		name: "type_limits",
+		default: Level::Warn,
+		description: "comparisons which are always true or always false for the range of the type",
+	},
+	Lint {
+		name: "unknown_lints",
+		default: Level::Warn,
+		description: "names in `@allow` which are not lints",
+	},
+];
+
+/// The level of each lint, which starts out as its default.
+pub struct Levels {
+	levels: HashMap<&'static str, Level>,
+}
+impl Levels {
+	pub fn new() -> Levels {
+		Levels { levels: LINTS.iter().map(|lint| (lint.name, lint.default)).collect() }
+	}
+	/// Sets the level of a lint. Returns false if there is no lint of that name.
+	pub fn set(&mut self, name: &str, level: Level) -> bool {
+		match LINTS.iter().find(|lint| lint.name == name) {
+			Some(lint) => {
+				self.levels.insert(lint.name, level);
+				true
+			},
+			None => false,
+		}
+	}
+	pub fn get(&self, name: &str) -> Level {
+		self.levels[name]
+	}
+}
+
+/// Runs the lints over a checked program.
+pub fn lint(items: &Program, levels: &Levels) -> Vec<Diagnostic> {
+	let mut linter = Linter {
+		items: items,
+		levels: levels,
+		allow: &[],
+		diagnostics: Vec::new(),
+		vars: Vec::new(),
+		scopes: Vec::new(),
+		writes: Vec::new(),
+	};
+	for item in items {
+		match *item {
+			// Instances of generic functions have the body of the function itself.
+			Item::Function(ref def) if !def.name.val.contains('<') => linter.function(def),
+			_ => (),
+		}
+	}
+	let mut diagnostics = linter.diagnostics;
+	diagnostics.sort_by_key(|diagnostic| (diagnostic.token.file, diagnostic.token.start));
+	diagnostics
+}
+
+struct Var<'a> {
+	name: &'a str,
+	token: &'a FullToken,
+	read: bool,
+	global: bool,
+}
+
+/// The statements which run after a statement, in one of the blocks around it.
+#[derive(Clone, Copy)]
+struct Rest<'a> {
+	statements: &'a [Statement],
+	/// Whether the block is the body of a loop, which may run the statements before the
+	/// statement again.
+	is_loop: bool,
+}
+
+struct Linter<'a> {
+	items: &'a Program,
+	levels: &'a Levels,
+	/// The lints allowed in the function being linted.
+	allow: &'a [Node<Id>],
+	diagnostics: Vec<Diagnostic>,
+	/// The variables of the function being linted, and the globals.
+	vars: Vec<Var<'a>>,
+	/// The indices in `vars` of the variables of each block, innermost last.
+	scopes: Vec<Vec<usize>>,
+	/// Assignments whose values are never read, by the index of their variable.
+	writes: Vec<(usize, &'a FullToken)>,
+}
+impl<'a> Linter<'a> {
+	fn function(&mut self, def: &'a FnDef) {
+		// Closures are named after the function they are in, whose attributes they share.
+		let parent = def.name.val.split('#').next().unwrap();
+		self.allow = match self.find_fn(parent) {
+			Some(parent) => &parent.allow,
+			None         => &def.allow,
+		};
+		if !def.name.val.contains('#') {
+			for name in &def.allow {
+				if LINTS.iter().any(|lint| lint.name == name.val) { continue; }
+				let help = diagnostic::suggest(&name.val, LINTS.iter().map(|lint| lint.name))
+					.map(|lint| diagnostic::did_you_mean(&name.val, lint));
+				self.report("unknown_lints", &name.token, format!("Unknown lint '{}'.", name.val))
+					.map(|diagnostic| diagnostic.help = help);
+			}
+		}
+
+		self.vars.clear();
+		self.scopes.clear();
+		self.writes.clear();
+		self.scopes.push(Vec::new());
+		for item in self.items {
+			match *item {
+				Item::Const(ref id, _, _) | Item::Static(ref id, _, _) => {
+					self.vars.push(Var { name: &id.val, token: &id.token, read: false, global: true });
+					let len = self.vars.len();
+					self.scopes[0].push(len - 1);
+				},
+				_ => (),
+			}
+		}
+		self.scopes.push(Vec::new());
+		for &(ref id, _) in &def.params {
+			self.declare(id);
+		}
+		self.block(&def.block.val, &[]);
+
+		for i in 0..self.vars.len() {
+			let (name, token) = (self.vars[i].name, self.vars[i].token);
+			if self.vars[i].read || self.vars[i].global || name.starts_with('_') { continue; }
+			let help = format!("prefix it with an underscore, as in `_{}`, if it is meant to be unused", name);
+			self.report("unused_variables", token, format!("Unused variable '{}'.", name))
+				.map(|diagnostic| diagnostic.help = Some(help));
+		}
+		// The assignments of variables which are never read are not reported on their own.
+		let writes = self.writes.clone();
+		for (var, token) in writes {
+			if !self.vars[var].read { continue; }
+			let message = format!("The value assigned to '{}' is never read.", self.vars[var].name);
+			self.report("unused_assignments", token, message)
+				.map(|diagnostic| diagnostic.notes.push("It is overwritten first, or never read again.".to_string()));
+		}
+	}
+
+	fn find_fn(&self, name: &str) -> Option<&'a FnDef> {
+		self.items.iter().filter_map(|item| match *item {
+			Item::Function(ref def) if def.name.val == name => Some(def),
+			_ => None,
+		}).next()
+	}
+
+	/// Reports a problem found by a lint, unless it is allowed.
+	fn report(&mut self, lint: &'static str, token: &FullToken, message: String) -> Option<&mut Diagnostic> {
+		if self.allow.iter().any(|name| name.val == lint) { return None; }
+		let level = self.levels.get(lint);
+		let diagnostic = match level {
+			Level::Allow => return None,
+			Level::Warn  => Diagnostic::warning(lint, token, message),
+			Level::Deny  => Diagnostic::error(lint, token, message),
+		};
+		let default = LINTS.iter().any(|other| other.name == lint && other.default == level);
+		let diagnostic = if default {
+			diagnostic.note(&format!("`{}` is on by default.", lint))
+		} else {
+			diagnostic
+		};
+		self.diagnostics.push(diagnostic);
+		self.diagnostics.last_mut()
+	}
+
+	/// Lints the statements of a block. `rest` are the statements which run after the
+	/// block, innermost last.
+	fn block(&mut self, block: &'a [Statement], rest: &[Rest<'a>]) {
+		self.scopes.push(Vec::new());
+		let mut diverged: Option<&FullToken> = None;
+		let mut reported = false;
+		for (i, statement) in block.iter().enumerate() {
+			if let Some(end) = diverged {
+				if !reported {
+					let token = statement_token(statement).unwrap_or(end);
+					self.report("unreachable_code", token, "Unreachable statement.".to_string())
+						.map(|diagnostic| diagnostic.labels.push((end.clone(),
+							"any code following this is unreachable".to_string())));
+					reported = true;
+				}
+			}
+			let mut after = rest.to_vec();
+			after.push(Rest { statements: &block[i + 1..], is_loop: false });
+			self.statement(statement, &after);
+			if diverged.is_none() { diverged = checker::diverges(statement); }
+		}
+		self.scopes.pop();
+	}
+
+	fn statement(&mut self, statement: &'a Statement, rest: &[Rest<'a>]) {
+		match *statement {
+			Statement::Declaration(ref id, ref type_name, ref expr) => {
+				if let Some(TypeName::TypeOf(ref expr)) = *type_name {
+					self.expr(&expr.val);
+				}
+				if let Some(ref expr) = *expr {
+					self.expr(expr);
+				}
+				let var = self.declare(id);
+				if expr.is_some() { self.write(var, &id.token, rest); }
+			},
+			Statement::Assignment(ref id, ref expr) => {
+				self.expr(expr);
+				if let Some(var) = self.find_var(&id.val) {
+					self.write(var, &id.token, rest);
+				}
+			},
+			Statement::If(ref conditions, ref blocks, ref else_block) => {
+				for condition in conditions {
+					self.condition(condition);
+					self.expr(condition);
+				}
+				for block in blocks.iter().chain(else_block.iter()) {
+					self.block(&block.val, rest);
+				}
+			},
+			Statement::Loop(ref block) => {
+				let mut rest = rest.to_vec();
+				rest.push(Rest { statements: &[], is_loop: true });
+				self.block(&block.val, &rest);
+			},
+			Statement::Block(ref block) => self.block(&block.val, rest),
+			Statement::Return(Node { val: Some(ref expr), .. }) |
+			Statement::Expr(ref expr) => self.expr(expr),
+			Statement::Return(_) => (),
+			Statement::StaticAssert(ref expr, _) | Statement::Assert(ref expr) => self.expr(&expr.val),
+			Statement::Print(ref id) => self.read(id),
+			Statement::Error(_) => (),
+		}
+	}
+
+	fn declare(&mut self, id: &'a Node<Id>) -> usize {
+		let shadowed = self.find_var(&id.val).map(|var| self.vars[var].token);
+		if let Some(shadowed) = shadowed {
+			self.report("shadowing", &id.token, format!("'{}' shadows another variable.", id.val))
+				.map(|diagnostic| diagnostic.labels.push((shadowed.clone(), "first declared here".to_string())));
+		}
+		self.vars.push(Var { name: &id.val, token: &id.token, read: false, global: false });
+		let var = self.vars.len() - 1;
+		self.scopes.last_mut().unwrap().push(var);
+		var
+	}
+
+	fn find_var(&self, name: &str) -> Option<usize> {
+		self.scopes.iter().rev()
+			.filter_map(|scope| scope.iter().cloned().find(|&var| self.vars[var].name == name))
+			.next()
+	}
+
+	fn read(&mut self, name: &str) {
+		if let Some(var) = self.find_var(name) {
+			self.vars[var].read = true;
+		}
+	}
+
+	/// Records an assignment if the value is overwritten before it is read, or never read
+	/// again.
+	fn write(&mut self, var: usize, token: &'a FullToken, rest: &[Rest<'a>]) {
+		if self.vars[var].global { return; }
+		let name = self.vars[var].name;
+		for after in rest.iter().rev() {
+			// Anything may come next in a loop.
+			if after.is_loop { return; }
+			if let Some(next) = after.statements.iter().find(|statement| self.mentions(statement, name)) {
+				match *next {
+					Statement::Assignment(ref id, ref expr) if id.val == name && !self.reads(expr, name) => {
+						self.writes.push((var, token));
+					},
+					_ => (),
+				}
+				return;
+			}
+		}
+		self.writes.push((var, token));
+	}
+
+	/// Whether a statement reads or assigns a variable of the given name.
+	fn mentions(&self, statement: &Statement, name: &str) -> bool {
+		let block = |block: &Node<Block>| block.val.iter().any(|statement| self.mentions(statement, name));
+		match *statement {
+			Statement::Declaration(ref id, ref type_name, ref expr) => {
+				id.val == name ||
+				expr.as_ref().map_or(false, |expr| self.reads(expr, name)) ||
+				match *type_name {
+					Some(TypeName::TypeOf(ref expr)) => self.reads(&expr.val, name),
+					_ => false,
+				}
+			},
+			Statement::Assignment(ref id, ref expr) => id.val == name || self.reads(expr, name),
+			Statement::If(ref conditions, ref blocks, ref else_block) => {
+				conditions.iter().any(|condition| self.reads(condition, name)) ||
+				blocks.iter().chain(else_block.iter()).any(block)
+			},
+			Statement::Loop(ref body) | Statement::Block(ref body) => block(body),
+			Statement::Return(Node { val: Some(ref expr), .. }) |
+			Statement::Expr(ref expr) => self.reads(expr, name),
+			Statement::Return(_) => false,
+			Statement::StaticAssert(ref expr, _) | Statement::Assert(ref expr) => self.reads(&expr.val, name),
+			Statement::Print(ref id) => id == name,
+			// What the statement did is not known.
+			Statement::Error(_) => true,
+		}
+	}
+
+	/// Whether an expression reads a variable of the given name.
+	fn reads(&self, expr: &Expr, name: &str) -> bool {
+		let mut names = Vec::new();
+		self.names_read(expr, &mut names);
+		names.iter().any(|read| read == name)
+	}
+
+	/// Adds the names of the variables an expression reads, including those read by the
+	/// closures it makes.
+	fn names_read(&self, expr: &Expr, names: &mut Vec<Id>) {
+		for node in expr {
+			match node.val.0 {
+				ExprToken::Id(ref id) if id.contains("#closure") => match self.find_fn(id) {
+					Some(def) => {
+						let mut vars = Vec::new();
+						checker::free_vars(def, &mut vars);
+						names.extend(vars);
+					},
+					None => (),
+				},
+				ExprToken::Closure(ref def) => checker::free_vars(def, names),
+				ExprToken::Id(ref id) | ExprToken::Call(ref id, _) => names.push(id.clone()),
+				_ => (),
+			}
+		}
+	}
+
+	fn expr(&mut self, expr: &'a Expr) {
+		let mut names = Vec::new();
+		self.names_read(expr, &mut names);
+		for name in names {
+			self.read(&name);
+		}
+		for (i, node) in expr.iter().enumerate() {
+			if let ExprToken::Op(op) = node.val.0 {
+				self.type_limits(expr, i, op);
+			}
+		}
+	}
+
+	fn condition(&mut self, condition: &Expr) {
+		let constant = condition.iter().all(|node| match node.val.0 {
+			ExprToken::IntLit(_) | ExprToken::FloatLit(_) | ExprToken::BoolLit(_) |
+			ExprToken::CharLit(_) | ExprToken::Op(_) => true,
+			_ => false,
+		});
+		if !constant || condition.is_empty() { return; }
+		let message = match condition[..] {
+			[Node { val: (ExprToken::BoolLit(val), _), .. }] => format!("This condition is always {}.", val),
+			_ => "This condition is constant.".to_string(),
+		};
+		self.report("constant_conditions", &condition[0].token, message);
+	}
+
+	/// Reports a comparison of a number with a literal at or past the end of its range.
+	fn type_limits(&mut self, expr: &Expr, i: usize, op: Op) {
+		match op {
+			Op::Gt | Op::Lt | Op::Geq | Op::Leq => (),
+			_ => return,
+		}
+		let right = operand_start(expr, i - 1);
+		let left = operand_start(expr, right - 1);
+		// The comparison is turned around so that the literal is on the right.
+		let (operand, literal, op) = match (int_literal(&expr[left..right]), int_literal(&expr[right..i])) {
+			(None, Some(literal)) => (&expr[right - 1], literal, op),
+			(Some(literal), None) => (&expr[i - 1], literal, match op {
+				Op::Gt  => Op::Lt,
+				Op::Lt  => Op::Gt,
+				Op::Geq => Op::Leq,
+				_       => Op::Geq,
+			}),
+			_ => return,
+		};
+		let ty = operand.val.1;
+		let (min, max) = match ty.int_bounds() {
+			Some(bounds) => bounds,
+			None         => return,
+		};
+		let at_max = max <= i128::MAX as u128 && literal >= max as i128;
+		let always = match op {
+			Op::Geq if literal <= min => true,
+			Op::Lt  if literal <= min => false,
+			Op::Leq if at_max => true,
+			Op::Gt  if at_max => false,
+			_ => return,
+		};
+		let message = format!("This comparison is always {} for values of type '{}'.", always, ty);
+		self.report("type_limits", &expr[i].token, message)
+			.map(|diagnostic| diagnostic.notes.push(format!("'{}' ranges from {} to {}.", ty, min, max)));
+	}
+}
+
+/// The index of the first node of the operand which ends at `end`.
+fn operand_start(expr: &Expr, end: usize) -> usize {
+	let operands = match expr[end].val.0 {
+		ExprToken::Op(op) if op.is_binary() => 2,
+		ExprToken::Op(_) | ExprToken::Cast(_) => 1,
+		ExprToken::Call(_, num) | ExprToken::Vector(num) => num,
+		ExprToken::Extract => 2,
+		ExprToken::Insert  => 3,
+		_ => 0,
+	};
+	let mut start = end;
+	for _ in 0..operands {
+		start = operand_start(expr, start - 1);
+	}
+	start
+}
+
+/// The value of an operand which is an integer literal, which may be negated.
+fn int_literal(operand: &[Node<(ExprToken, ::types::Type)>]) -> Option<i128> {
+	match operand.iter().map(|node| &node.val.0).collect::<Vec<_>>()[..] {
+		[&ExprToken::IntLit(val)] if val <= i128::MAX as u128 => Some(val as i128),
+		[&ExprToken::IntLit(val), &ExprToken::Op(Op::Neg)] if val <= i128::MAX as u128 => Some(-(val as i128)),
+		_ => None,
+	}
+}
+
+/// The token of a statement, if it has one.
+fn statement_token(statement: &Statement) -> Option<&FullToken> {
+	match *statement {
+		Statement::Declaration(ref id, _, _) | Statement::Assignment(ref id, _) => Some(&id.token),
+		Statement::If(ref conditions, _, _) => conditions[0].first().map(|node| &node.token),
+		Statement::Loop(ref block) | Statement::Block(ref block) => Some(&block.token),
+		Statement::Return(ref ret) => Some(&ret.token),
+		Statement::Expr(ref expr) => expr.last().map(|node| &node.token),
+		Statement::StaticAssert(ref expr, _) | Statement::Assert(ref expr) => Some(&expr.token),
+		Statement::Print(_) => None,
+		Statement::Error(ref start) => Some(&start.token),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use checker;
+	use parser;
+
+	/// The lints reported for a program, with the lines they are on.
+	fn lint_code(code: &str, levels: &Levels) -> Vec<(&'static str, u32)> {
+		let (mut items, e) = parser::parse(code);
+		assert_eq!(e, Vec::new());
+		checker::check_program(&mut items, &mut checker::Program::new()).unwrap();
+		lint(&items, levels).iter().map(|diagnostic| (diagnostic.code, diagnostic.token.line)).collect()
+	}
+
+	#[test]
+	fn lint_test() {
+		let code = r#"fn f(a: I32, _b: I32) -> I32 {
+			x := 1
+			y := 2
+			y = 3
+			if true { z := y }
+			u: U8 = 4
+			if u >= 0 | u > 255 | 1 < u {
+				x := 5
+				@print x
+			}
+			g := fn() -> I32 { return y }
+			return g()
+			x = 6
+		}"#;
+		assert_eq!(lint_code(code, &Levels::new()), vec![
+			("unused_variables", 1),
+			("unused_variables", 2),
+			("unused_assignments", 3),
+			("constant_conditions", 5),
+			("unused_variables", 5),
+			("type_limits", 7),
+			("type_limits", 7),
+			("unreachable_code", 13),
+		]);
+
+		let mut levels = Levels::new();
+		assert!(levels.set("shadowing", Level::Deny));
+		assert!(levels.set("unused_variables", Level::Allow));
+		assert!(!levels.set("unused", Level::Allow));
+		let lints = lint_code(code, &levels);
+		assert!(lints.contains(&("shadowing", 8)));
+		assert!(!lints.iter().any(|&(lint, _)| lint == "unused_variables"));
+
+		let code = "@allow(unused_variables, unreachable)\nfn f() { x := 1 }";
+		assert_eq!(lint_code(code, &Levels::new()), vec![("unknown_lints", 1)]);
+	}
+}
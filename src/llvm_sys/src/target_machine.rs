@@ -95,6 +95,8 @@ extern "C" {
                                                    *mut LLVMMemoryBufferRef)
      -> LLVMBool;
     pub fn LLVMGetDefaultTargetTriple() -> *mut i8;
+    pub fn LLVMCreateTargetDataLayout(T: LLVMTargetMachineRef)
+     -> LLVMTargetDataRef;
     pub fn LLVMAddAnalysisPasses(T: LLVMTargetMachineRef,
                                  PM: LLVMPassManagerRef) -> ();
 }
@@ -4,6 +4,9 @@ mod types;
 mod parser;
 mod checker;
 mod builder;
+mod diagnostic;
+mod lint;
+mod explain;
 
 #[macro_use] extern crate bitflags;
 #[path = "llvm_sys/src/lib.rs"]
@@ -16,8 +19,11 @@ use std::env;
 
 use std::path::PathBuf;
 use std::fs::File;
-use std::io::Read;
-use std::process::Command;
+use std::io::{Read, Write};
+use std::process::{self, Command};
+
+use builder::Overflow;
+use diagnostic::{Diagnostic, Format, Renderer, Severity};
 
 fn print_usage(program: &str, opts: Options) {
 	let brief = format!("Useage: {} [options]", program);
@@ -31,12 +37,89 @@ fn main() {
 	// Get input and output files from command line.
 	let mut opts = Options::new();
 	opts.optopt("o", "", "set output file name", "NAME");
+	opts.optopt("", "crate-type", "kind of output: bin (default), staticlib or cdylib", "TYPE");
+	opts.optflag("", "release", "leave out the checks of @assert");
+	opts.optopt("", "overflow", "on integer overflow: trap (default), wrap (default with \
+	                             --release) or unchecked", "MODE");
+	opts.optflag("", "no-div-checks", "leave out the checks of integer division (left out with \
+	                                   --release)");
+	opts.optflag("", "color", "color the error messages");
+	opts.optopt("", "error-format", "how to print errors: human (default) or json, one object \
+	                                 per line", "FORMAT");
+	opts.optmulti("A", "", "allow a lint", "LINT");
+	opts.optmulti("W", "", "warn about a lint", "LINT");
+	opts.optmulti("D", "", "deny a lint, making it an error", "LINT");
+	opts.optflag("", "apply-fixes", "apply the fixes of the problems found to the source files, \
+	                                 instead of compiling");
+	opts.optopt("", "explain", "describe an error code, or a lint, at length", "CODE");
 	opts.optflag("h", "help", "print this help menu");
-	let matches = opts.parse(&args[1..]).unwrap();
+	let matches = match opts.parse(&args[1..]) {
+		Ok(matches) => matches,
+		Err(e) => {
+			// The options are not read yet, but errors are still shown in the format asked for.
+			let json = args.iter().any(|arg| arg == "--error-format=json") ||
+			           args.windows(2).any(|pair| pair[0] == "--error-format" && pair[1] == "json");
+			let format = if json { Format::Json } else { Format::Human };
+			fail(&Renderer { files: &[], code: &[], format: format, color: false }, &e.to_string(), None);
+		},
+	};
 	if matches.opt_present("h") {
 		print_usage(&program, opts);
 		return;
 	}
+	let color = matches.opt_present("color");
+	let format = match matches.opt_str("error-format") {
+		Some(ref format) if format == "human" => Format::Human,
+		Some(ref format) if format == "json"  => Format::Json,
+		Some(format) => {
+			fail(&Renderer { files: &[], code: &[], format: Format::Human, color: color },
+			     &format!("Unknown error format '{}'.", format), Some("the formats are `human` and `json`"));
+		},
+		None         => Format::Human,
+	};
+	// Shows the errors with the command line.
+	let cli = Renderer { files: &[], code: &[], format: format, color: color };
+	if let Some(code) = matches.opt_str("explain") {
+		match explain::explain(&code) {
+			Some(text) => print!("{}", text),
+			None => {
+				let codes = explain::EXPLANATIONS.iter().map(|&(code, _)| code);
+				let suggestion = diagnostic::suggest(&code.to_uppercase(), codes)
+					.or(diagnostic::suggest(&code, lint::LINTS.iter().map(|lint| lint.name)));
+				let help = suggestion.map(|suggestion| diagnostic::did_you_mean(&code, suggestion));
+				fail(&cli, &format!("Unknown error code '{}'.", code), help.as_ref().map(|help| &help[..]));
+			},
+		}
+		return;
+	}
+	let crate_type = match matches.opt_str("crate-type") {
+		Some(ref ty) if ty == "bin"       => CrateType::Bin,
+		Some(ref ty) if ty == "staticlib" => CrateType::StaticLib,
+		Some(ref ty) if ty == "cdylib"    => CrateType::CDylib,
+		Some(ty) => fail(&cli, &format!("Unknown crate type '{}'.", ty),
+		                 Some("the crate types are `bin`, `staticlib` and `cdylib`")),
+		None     => CrateType::Bin,
+	};
+	let release = matches.opt_present("release");
+	let overflow = match matches.opt_str("overflow") {
+		Some(ref mode) if mode == "trap"      => Overflow::Trap,
+		Some(ref mode) if mode == "wrap"      => Overflow::Wrap,
+		Some(ref mode) if mode == "unchecked" => Overflow::Unchecked,
+		Some(mode) => fail(&cli, &format!("Unknown overflow mode '{}'.", mode),
+		                   Some("the modes are `trap`, `wrap` and `unchecked`")),
+		None if release => Overflow::Wrap,
+		None            => Overflow::Trap,
+	};
+	let mut levels = lint::Levels::new();
+	for &(flag, level) in &[("A", lint::Level::Allow), ("W", lint::Level::Warn), ("D", lint::Level::Deny)] {
+		for name in matches.opt_strs(flag) {
+			if !levels.set(&name, level) {
+				let help = diagnostic::suggest(&name, lint::LINTS.iter().map(|lint| lint.name))
+					.map(|suggestion| diagnostic::did_you_mean(&name, suggestion));
+				fail(&cli, &format!("Unknown lint '{}'.", name), help.as_ref().map(|help| &help[..]));
+			}
+		}
+	}
 	let output = matches.opt_str("o");
 	let input = if !matches.free.is_empty() {
 		matches.free[0].clone()
@@ -44,54 +127,155 @@ fn main() {
 		print_usage(&program, opts);
 		return;
 	};
+	let input_path = PathBuf::from(input.clone());
+	let stem = input_path.file_stem().unwrap().to_str().unwrap().to_string();
 	let output: String = match output {
 		Some(output) => output,
 		None => {
-			let input_path = PathBuf::from(input.clone());
 			let mut output_path = PathBuf::new();
 			output_path.push(input_path.parent().unwrap());
-			output_path.push(input_path.file_stem().unwrap());
+			output_path.push(match crate_type {
+				CrateType::Bin       => stem.clone(),
+				CrateType::StaticLib => format!("lib{}.a",  stem),
+				CrateType::CDylib    => format!("lib{}.so", stem),
+			});
 			output_path.to_str().unwrap().to_string()
 		},
 	};
 
 	// Load input file.
 	let mut file = File::open(&input).unwrap_or_else(|e| {
-		panic!("Failed to open file '{}': {}", input, e);
+		fail(&cli, &format!("Failed to open file '{}': {}", input, e), None);
 	});
 	let mut code = String::new();
 	file.read_to_string(&mut code).unwrap_or_else(|_| {
-		panic!("Given file '{}' is not proper unicode.", input);
+		fail(&cli, &format!("Given file '{}' is not proper unicode.", input), None);
 	});
 
 	// Compile program to llvm.
-	let (mut block, e) = parser::parse(&code);
-	if !e.is_empty() {
-		for error in e {
-			println!("{}", error);
-		}
-		panic!();
+	let (mut items, files, e) = parser::load(&input_path, &code);
+	// Errors show the code of imported files too.
+	let sources: Vec<String> = files.iter().enumerate().map(|(i, path)| if i == 0 {
+		code.clone()
+	} else {
+		let mut code = String::new();
+		File::open(path).and_then(|mut file| file.read_to_string(&mut code)).ok();
+		code
+	}).collect();
+	let renderer = Renderer {
+		files: &files,
+		code: &sources,
+		format: format,
+		color: matches.opt_present("color"),
+	};
+	// The parser recovers from syntax errors, so the program is checked even if there are
+	// some, leaving out the errors they cause.
+	let mut diagnostics: Vec<Diagnostic> = e.iter().map(|e| e.diagnostic()).collect();
+	let mut checked = checker::Program::new();
+	match checker::check_program(&mut items, &mut checked) {
+		Ok(()) if e.is_empty() => diagnostics.extend(lint::lint(&items, &levels)),
+		Ok(()) => (),
+		Err(errors) => diagnostics.extend(errors.iter()
+			.filter(|error| e.is_empty() || !error.is_caused())
+			.map(|error| error.diagnostic())),
 	}
-	let mut scope = checker::Scope::new_root();
-	match checker::passes(&mut block, &mut scope) {
-		Ok(_)  => (),
-		Err(e) => panic!("Err: {}", e),
+	if matches.opt_present("apply-fixes") {
+		apply_fixes(&renderer, diagnostics);
+		return;
+	}
+	report(&renderer, &diagnostics);
+	let options = builder::Options {
+		files: &files,
+		release: release,
+		overflow: overflow,
+		div_checks: !release && !matches.opt_present("no-div-checks"),
+	};
+	if !builder::build(&items, &mut checked, &options, "temp.ll") {
+		panic!("Generated invalid LLVM IR.");
 	}
-	builder::build(&block, &mut scope, "temp.ll");
 
-	// Compile llvm to executable.
-	let res = Command::new("llc").arg("temp.ll").output().unwrap_or_else(|e| {
-		panic!("Failed to run llc: {}", e);
-	});
-	print!("{}", String::from_utf8(res.stdout).unwrap());
-	print!("{}", String::from_utf8(res.stderr).unwrap());
-	let res = Command::new("clang").arg("-o").
-	                                arg(output).
-	                                arg("temp.s").
-	                                arg("shim.a").output().unwrap_or_else(|e| {
-		panic!("Failed to run clang: {}", e);
+	// Compile llvm to the requested kind of output.
+	match crate_type {
+		CrateType::Bin => {
+			run(Command::new("llc").arg("temp.ll"));
+			run(Command::new("clang").arg("-o").
+			                          arg(&output).
+			                          arg("temp.s").
+			                          arg("shim.a"));
+		},
+		CrateType::StaticLib | CrateType::CDylib => {
+			let mut llc = Command::new("llc");
+			llc.arg("-filetype=obj").arg("-o").arg("temp.o").arg("temp.ll");
+			if crate_type == CrateType::CDylib {
+				llc.arg("-relocation-model=pic");
+			}
+			run(&mut llc);
+			if crate_type == CrateType::StaticLib {
+				run(Command::new("ar").arg("rcs").arg(&output).arg("temp.o"));
+			} else {
+				run(Command::new("clang").arg("-shared").arg("-o").arg(&output).arg("temp.o"));
+			}
+
+			// Describe the exported functions for C.
+			let header_path = PathBuf::from(&output).with_extension("h");
+			let mut file = File::create(&header_path).unwrap_or_else(|e| {
+				panic!("Failed to create header '{}': {}", header_path.display(), e);
+			});
+			file.write_all(builder::header::header(&items, &checked, &stem).as_bytes()).unwrap();
+		},
+	}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CrateType {
+	Bin,
+	StaticLib,
+	CDylib,
+}
+
+/// Prints an error which is not in the program, and exits.
+fn fail(renderer: &Renderer, message: &str, help: Option<&str>) -> ! {
+	eprintln!("{}", renderer.render_message(message, help));
+	process::exit(1);
+}
+
+/// Prints diagnostics, and exits if any of them is an error.
+fn report(renderer: &Renderer, diagnostics: &[Diagnostic]) {
+	for diagnostic in diagnostics {
+		eprintln!("{}", renderer.render(diagnostic));
+	}
+	if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+		process::exit(1);
+	}
+}
+
+/// Fixes the source files in place, and reports the diagnostics which were not fixed.
+fn apply_fixes(renderer: &Renderer, diagnostics: Vec<Diagnostic>) {
+	let mut fixed = vec![false; diagnostics.len()];
+	for (i, path) in renderer.files.iter().enumerate() {
+		let (code, fixed_here) = diagnostic::apply_fixes(&renderer.code[i], i as u32, &diagnostics);
+		let count = fixed_here.iter().filter(|&&fixed| fixed).count();
+		if count == 0 { continue; }
+		File::create(path).and_then(|mut file| file.write_all(code.as_bytes())).unwrap_or_else(|e| {
+			panic!("Failed to write '{}': {}", path.display(), e);
+		});
+		eprintln!("Fixed {} problem{} in '{}'.", count, if count == 1 { "" } else { "s" }, path.display());
+		for (fixed, fixed_here) in fixed.iter_mut().zip(fixed_here) {
+			*fixed |= fixed_here;
+		}
+	}
+	let rest: Vec<Diagnostic> = diagnostics.into_iter().zip(fixed)
+		.filter(|&(_, fixed)| !fixed)
+		.map(|(diagnostic, _)| diagnostic)
+		.collect();
+	report(renderer, &rest);
+}
+
+/// Runs an external tool, passing its output through.
+fn run(command: &mut Command) {
+	let res = command.output().unwrap_or_else(|e| {
+		panic!("Failed to run {:?}: {}", command, e);
 	});
 	print!("{}", String::from_utf8(res.stdout).unwrap());
 	print!("{}", String::from_utf8(res.stderr).unwrap());
 }
-
@@ -13,32 +13,58 @@ impl<T> Node<T> {
 	pub fn new(val: T, token: FullToken) -> Node<T> {
 		Node { token: token, val: val }
 	}
-	pub fn newn(val: T, line: u32, column: u32) -> Node<T> {
-		Node { token: FullToken::none(line, column), val: val }
-	}
 }
 
 /// Items are the outmost structure of a program.
-/// A program is basically a Vec<Item>.
+pub type Program = Vec<Item>;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Item {
 	/// Constant global declaration and initialization.
-	/// `const Id: [Id] = Expr`
-	Const(Node<Id>, Option<Node<Id>>, Expr),
+	/// `const Id: [TypeName] = Expr`
+	Const(Node<Id>, Option<TypeName>, Expr),
+
+	/// Mutable global variable, initialized with a constant expression.
+	/// `static Id: [TypeName] = Expr`
+	Static(Node<Id>, Option<TypeName>, Expr),
+
+	/// Import of another file, by its path relative to this one. Its items are accessed
+	/// through the last segment, as in `util.name`.
+	/// `import Id [. Id]...`
+	Import(Node<Vec<Id>>),
+
+	/// Another name for a type, which can be used wherever the type can.
+	/// `type Id = Type`
+	TypeAlias(Node<Id>, Node<Id>),
 
 	/// Function global declaration and definition.
-	/// `fn Id ( [Id: Id,]... ) [-> Id] { Block }
-	Function(Node<Id>, Option<Node<Id>>, Block),
+	/// The attributes are `@export` and `@allow(Id, ...)`.
+	/// `[@Attr]... fn Id [< [Id [: Id],]... >] ( [Id: Id,]... ) [-> Id] { Block }`
+	Function(FnDef),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FnDef {
+	pub name: Node<Id>,
+	/// Type parameters, each with an optional constraint such as `Signed`.
+	pub generics: Vec<(Node<Id>, Option<Node<Id>>)>,
+	pub params: Vec<(Node<Id>, Node<Id>)>,
+	pub ret: Option<Node<Id>>,
+	pub block: Node<Block>,
+	/// `@export`: unmangled symbol with the C calling convention.
+	pub export: bool,
+	/// `@allow(Id, ...)`: the lints which are not reported in the function.
+	pub allow: Vec<Node<Id>>,
 }
 
-/// Variable identifier.
+/// Variable identifier. Items of other files are named `module.name`.
 pub type Id    = String;
 pub type Block = Vec<Statement>;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Statement {
-	/// `Id: [Id] = Expr`
-	Declaration(Node<Id>, Option<Node<Id>>, Option<Expr>),
+	/// `Id: [TypeName] = Expr`
+	Declaration(Node<Id>, Option<TypeName>, Option<Expr>),
 
 	/// `Id = Expr`
 	Assignment(Node<Id>, Expr),
@@ -53,32 +79,104 @@ pub enum Statement {
 	Block(Node<Block>),
 
 	/// `return [Expr]`
-	Return(Option<Expr>),
+	Return(Node<Option<Expr>>),
+
+	/// `Id ( [Expr,]... )`
+	Expr(Expr),
 
 	/// `@print id`
 	Print(Id),
+
+	/// `@static_assert(Expr, String)`, checked by the compiler.
+	/// The token is the special's.
+	StaticAssert(Node<Expr>, String),
+
+	/// `@assert(Expr)`, which aborts the program when the expression is false.
+	/// The token is the special's, for reporting where the assertion failed.
+	Assert(Node<Expr>),
+
+	/// A statement which could not be parsed, with the identifier it starts with, if any.
+	/// The checker declares that variable if it does not exist, so that its uses are not
+	/// reported too.
+	Error(Node<Option<Id>>),
+}
+
+/// The type given to a declaration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeName {
+	/// `Id`
+	Name(Node<Id>),
+	/// `@typeof(Expr)`, the type of the expression, which is not evaluated.
+	TypeOf(Node<Expr>),
 }
 
 pub type Expr = Vec<Node<(ExprToken, Type)>>;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ExprToken {
-	IntLit(  u64),
+	IntLit(u128),
 	FloatLit(f64),
 	BoolLit(bool),
+	/// A code point, which the checker makes sure is a Unicode scalar value.
+	CharLit(u32),
 	StringLit(String),
 	Id(Id),
 	Op(Op),
+	/// Call of the function with the given number of arguments.
+	/// The function is either an item or a variable holding a function value.
+	Call(Id, u32),
+	/// `fn ( [Id: Id,]... ) [-> Id] { Block }`
+	/// A function value which captures the variables it uses by value.
+	Closure(Box<FnDef>),
+	/// `@sizeof(Id)`, `@alignof(Id)`, `@min(Id)` or `@max(Id)`.
+	/// The checker replaces it with a literal.
+	TypeQuery(TypeQuery, Node<Id>),
+	/// `@cast(Id, Expr)`, after the expression: conversion of its value to the named type.
+	Cast(Node<Id>),
+	/// `@vec(Expr, ...)`: a vector of the given number of elements.
+	Vector(u32),
+	/// `@extract(Expr, Expr)`: the lane of a vector at an index.
+	Extract,
+	/// `@insert(Expr, Expr, Expr)`: a copy of a vector with the lane at an index replaced.
+	Insert,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TypeQuery {
+	SizeOf, AlignOf, Min, Max
+}
+impl TypeQuery {
+	pub fn from_str(name: &str) -> Option<TypeQuery> {
+		match name {
+			"sizeof"  => Some(TypeQuery::SizeOf),
+			"alignof" => Some(TypeQuery::AlignOf),
+			"min"     => Some(TypeQuery::Min),
+			"max"     => Some(TypeQuery::Max),
+			_         => None,
+		}
+	}
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Op {
-	Neg, Inv, Not, Add, Sub, Mul, Div, Mod, Pow, And, Or, Gt, Lt, Geq, Leq, Eq, Neq, TempParen
+	Neg, Inv, Not, Add, Sub, Mul, Div, Mod, Pow, And, Or, Gt, Lt, Geq, Leq, Eq, Neq, TempParen,
+	/// `@wrapping_add(Expr, Expr)` and so on, which wrap around on overflow.
+	WrapAdd, WrapSub, WrapMul,
 }
 impl Op {
+	/// The operator written as a special which takes its operands like a call.
+	pub fn from_special(name: &str) -> Option<Op> {
+		match name {
+			"wrapping_add" => Some(Op::WrapAdd),
+			"wrapping_sub" => Some(Op::WrapSub),
+			"wrapping_mul" => Some(Op::WrapMul),
+			_              => None,
+		}
+	}
 	pub fn return_type(&self) -> Type {
 		match *self {
 			Op::Neg | Op::Add | Op::Sub | Op::Mul | Op::Inv |
+			Op::WrapAdd | Op::WrapSub | Op::WrapMul |
 			Op::Not | Op::Div | Op::Mod | Op::Pow => Type::Num(NumType::Unknown),
 			Op::And | Op::Or | Op::Gt | Op::Lt |
 			Op::Geq | Op::Leq | Op::Eq | Op::Neq  => Type::Bool,
@@ -97,6 +195,7 @@ impl Op {
 			Op::Pow                     => 6,
 			Op::Mul | Op::Div | Op::Mod => 5,
 			Op::Add | Op::Sub           => 4,
+			Op::WrapAdd | Op::WrapSub | Op::WrapMul => 4,
 			Op::Eq | Op::Neq | Op::Lt |
 			Op::Gt | Op::Leq | Op::Geq  => 3,
 			Op::And                     => 2,
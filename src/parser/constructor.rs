@@ -7,11 +7,12 @@ use parser::{FullToken, Token};
 use parser::error::{Error, ErrorKind, Result};
 
 pub fn construct(mut tokens: Vec<FullToken>) -> (Block, Vec<Error>) {
-	let mut con = Constructor { errors: Vec::new(), line: 0, column: 0 };
+	let mut con = Constructor { errors: Vec::new(), pos: FullToken::none(1, 1), end: FullToken::none(1, 1) };
 
 	// place extra block close at the end of the tokens
-	let ln = tokens[tokens.len() - 1].line;
-	tokens.push(FullToken::new(Token::Symbol('}'), ln + 1, 0));
+	let mut close = tokens[tokens.len() - 1].after();
+	close.token = Token::Symbol('}');
+	tokens.push(close);
 
 	let mut iter  = tokens.into_iter().peekable();
 
@@ -21,21 +22,50 @@ pub fn construct(mut tokens: Vec<FullToken>) -> (Block, Vec<Error>) {
 	(block, con.errors)
 }
 
+pub fn construct_program(mut tokens: Vec<FullToken>) -> (Program, Vec<Error>) {
+	let mut con = Constructor { errors: Vec::new(), pos: FullToken::none(1, 1), end: FullToken::none(1, 1) };
+
+	// place an end marker at the end of the tokens
+	let end = tokens.last().map_or(FullToken::none(1, 1), |token| token.after());
+	tokens.push(end);
+
+	let mut iter = tokens.into_iter().peekable();
+
+	let mut program = Vec::new();
+	loop {
+		match con.do_item(&mut iter) {
+			Ok(item) => program.push(item),
+			Err(e) => match e.kind {
+				ErrorKind::Done => break,
+				ErrorKind::UnclosedBlock => {
+					con.error(e);
+					break;
+				},
+				_ => {
+					con.error(e);
+					con.skip_item(&mut iter);
+				},
+			},
+		}
+	}
+
+	(program, con.errors)
+}
+
 macro_rules! next {
 	($slf: expr, $iter: expr) => { {
 		let token = try!($iter.next().ok_or(
-			Error::unclosed_block(FullToken::none($slf.line, $slf.column))));
-		$slf.line   = token.line;
-		$slf.column = token.column;
+			Error::unclosed_block($slf.pos.clone())));
+		$slf.pos = token.before();
+		$slf.end = token.after();
 		token
 	} }
 }
 macro_rules! peek {
 	($slf: expr, $iter:expr) => { {
 		let token = try!($iter.peek().ok_or(
-			Error::unclosed_block(FullToken::none($slf.line, $slf.column))));
-		$slf.line   = token.line;
-		$slf.column = token.column;
+			Error::unclosed_block($slf.pos.clone())));
+		$slf.pos = token.before();
 		token
 	} }
 }
@@ -46,45 +76,395 @@ macro_rules! sym {
 }
 
 type Iter = Peekable<IntoIter<FullToken>>;
+
+/// The specials which build and take apart vectors, whose operands are passed like arguments.
+const LANE_SPECIALS: [&'static str; 3] = ["vec", "extract", "insert"];
+/// Every special, for suggesting one in place of a special which does not exist.
+pub const SPECIALS: [&'static str; 17] = [
+	"export", "allow", "print", "static_assert", "assert", "typeof", "cast",
+	"sizeof", "alignof", "min", "max", "vec", "extract", "insert",
+	"wrapping_add", "wrapping_sub", "wrapping_mul",
+];
+
 struct Constructor {
 	errors: Vec<Error>,
-	line: u32,
-	column: u32,
+	/// Where the last token seen starts, for the nodes and errors made up by the parser.
+	pos: FullToken,
+	/// Just after the last token taken, where fixes insert what is missing.
+	end: FullToken,
 }
 impl Constructor {
 
+	fn do_item(&mut self, iter: &mut Iter) -> Result<Item> {
+		let mut export = false;
+		let mut allow = Vec::new();
+		loop {
+			self.trim_commas(iter);
+			let token = next!(self, iter);
+			// Only functions have attributes.
+			let attrs = export || !allow.is_empty();
+			match token.token {
+				Token::Special(ref attr) if attr == "export" => export = true,
+				Token::Special(ref attr) if attr == "allow" => {
+					allow.extend(try!(self.do_parenthesized(iter, |slf, iter| {
+						let mut lints = Vec::new();
+						loop {
+							slf.trim_commas(iter);
+							if peek!(slf, iter).token == Token::Symbol(')') { return Ok(lints); }
+							lints.push(try!(slf.do_ident(iter)));
+						}
+					})));
+				},
+				Token::Special(_) => return Err(Error::invalid_special(token.clone())),
+				Token::KwFn => return self.do_function(iter, export, allow),
+				Token::KwConst | Token::KwStatic => return if attrs {
+					Err(Error::expected(token, vec![Token::KwFn]))
+				} else {
+					self.do_global(iter, token.token == Token::KwStatic)
+				},
+				Token::KwImport => return if attrs {
+					Err(Error::expected(token, vec![Token::KwFn]))
+				} else {
+					self.do_import(iter)
+				},
+				Token::KwType => return if attrs {
+					Err(Error::expected(token, vec![Token::KwFn]))
+				} else {
+					self.do_type_alias(iter)
+				},
+				Token::None => return Err(Error::done()),
+				_ => return Err(Error::expected(token, vec![Token::KwFn, Token::KwConst, Token::KwStatic,
+				                                            Token::KwImport, Token::KwType])),
+			}
+		}
+	}
+
+	// Throw away tokens until the start of the next item.
+	fn skip_item(&mut self, iter: &mut Iter) {
+		let mut depth = 0;
+		loop {
+			let token = match iter.peek() {
+				Some(token) => token.token.clone(),
+				None => return,
+			};
+			match token {
+				Token::None => return,
+				Token::KwFn | Token::KwConst | Token::KwStatic | Token::KwImport | Token::KwType |
+				Token::Special(_) if depth == 0 => return,
+				Token::Symbol('{') => depth += 1,
+				Token::Symbol('}') => if depth > 0 { depth -= 1 },
+				_ => (),
+			}
+			iter.next();
+		}
+	}
+
+	fn do_import(&mut self, iter: &mut Iter) -> Result<Item> {
+		let first = try!(self.do_ident(iter));
+		let mut path = vec![first.val];
+		while peek!(self, iter).token == Token::Symbol('.') {
+			iter.next();
+			path.push(try!(self.do_ident(iter)).val);
+		}
+		Ok(Item::Import(Node::new(path, first.token)))
+	}
+
+	fn do_type_alias(&mut self, iter: &mut Iter) -> Result<Item> {
+		let id = try!(self.do_ident(iter));
+		let token = next!(self, iter);
+		if token.token != Token::Symbol('=') {
+			let fix = starts_type(&token.token);
+			let e = Error::expected(token.clone(), sym!['=']);
+			return Err(if fix { e.fix(token.before(), "= ") } else { e });
+		}
+		Ok(Item::TypeAlias(id, try!(self.do_type(iter))))
+	}
+
+	/// Joins an identifier with any following `.Ident`s, as in `module.name`.
+	fn do_path(&mut self, iter: &mut Iter, mut token: FullToken) -> Result<FullToken> {
+		while peek!(self, iter).token == Token::Symbol('.') {
+			iter.next();
+			let next = try!(self.do_ident(iter));
+			if let Token::Ident(ref mut id) = token.token {
+				id.push('.');
+				id.push_str(&next.val);
+			}
+		}
+		Ok(token)
+	}
+
+	// A `const` or `static` item.
+	fn do_global(&mut self, iter: &mut Iter, is_static: bool) -> Result<Item> {
+		let id = try!(self.do_ident(iter));
+		let token = next!(self, iter);
+		if token.token != Token::Symbol(':') {
+			let e = Error::expected(token.clone(), sym![':']);
+			// `const X = 1` is missing the `:` of `:=`.
+			return Err(match token.token {
+				Token::Symbol('=') => e.fix(token, ":="),
+				_ if starts_type(&token.token) => e.fix(token.before(), ": "),
+				_ => e,
+			});
+		}
+		match try!(self.do_declare(iter, id)) {
+			Statement::Declaration(id, type_id, Some(expr)) => Ok(if is_static {
+				Item::Static(id, type_id, expr)
+			} else {
+				Item::Const(id, type_id, expr)
+			}),
+			_ => Err(Error::expected(self.pos.clone(), sym!['='])),
+		}
+	}
+
+	fn do_function(&mut self, iter: &mut Iter, export: bool, allow: Vec<Node<Id>>) -> Result<Item> {
+		let name = try!(self.do_ident(iter));
+
+		let mut generics = Vec::new();
+		if peek!(self, iter).token == Token::Symbol('<') {
+			iter.next();
+			loop {
+				self.trim_commas(iter);
+				if peek!(self, iter).token == Token::Symbol('>') {
+					iter.next();
+					break;
+				}
+				let param = try!(self.do_ident(iter));
+				let constraint = if peek!(self, iter).token == Token::Symbol(':') {
+					iter.next();
+					Some(try!(self.do_ident(iter)))
+				} else { None };
+				generics.push((param, constraint));
+			}
+		}
+
+		let (params, ret, block) = try!(self.do_fn_body(iter));
+		Ok(Item::Function(FnDef {
+			name: name,
+			generics: generics,
+			params: params,
+			ret: ret,
+			block: block,
+			export: export,
+			allow: allow,
+		}))
+	}
+
+	/// A closure, after its `fn`. It is named after the `fn` token.
+	fn do_closure(&mut self, iter: &mut Iter, token: FullToken) -> Result<FnDef> {
+		let (params, ret, block) = try!(self.do_fn_body(iter));
+		Ok(FnDef {
+			name: Node::new("closure".to_string(), token),
+			generics: Vec::new(),
+			params: params,
+			ret: ret,
+			block: block,
+			export: false,
+			allow: Vec::new(),
+		})
+	}
+
+	/// The parameters, return type and block of a function.
+	fn do_fn_body(&mut self, iter: &mut Iter)
+	             -> Result<(Vec<(Node<Id>, Node<Id>)>, Option<Node<Id>>, Node<Block>)> {
+		let token = next!(self, iter);
+		if token.token != Token::Symbol('(') {
+			return Err(Error::expected(token, sym!['(']));
+		}
+
+		let mut params = Vec::new();
+		loop {
+			self.trim_commas(iter);
+			if peek!(self, iter).token == Token::Symbol(')') {
+				iter.next();
+				break;
+			}
+			let param = try!(self.do_ident(iter));
+			let token = next!(self, iter);
+			if token.token != Token::Symbol(':') {
+				return Err(Error::expected(token, sym![':']));
+			}
+			params.push((param, try!(self.do_type(iter))));
+		}
+		let ret = try!(self.do_ret_type(iter));
+
+		self.trim_commas(iter);
+		let token = next!(self, iter);
+		if token.token != Token::Symbol('{') {
+			return Err(Error::expected(token, sym!['{']));
+		}
+		let mut block = Vec::new();
+		self.do_block(iter, &mut block);
+		Ok((params, ret, Node::new(block, token)))
+	}
+
+	// `[-> Type]`
+	fn do_ret_type(&mut self, iter: &mut Iter) -> Result<Option<Node<Id>>> {
+		if peek!(self, iter).token != Token::Symbol('-') {
+			return Ok(None);
+		}
+		iter.next();
+		let token = next!(self, iter);
+		if token.token != Token::Symbol('>') {
+			return Err(Error::expected(token, sym!['>']));
+		}
+		Ok(Some(try!(self.do_type(iter))))
+	}
+
+	/// A type name. Function types are written out in full, as in `fn(I32, Bool) -> I32`.
+	fn do_type(&mut self, iter: &mut Iter) -> Result<Node<Id>> {
+		if peek!(self, iter).token != Token::KwFn {
+			let mut name = try!(self.do_ident(iter));
+			// Aliases of other files are named `module.Name`.
+			while peek!(self, iter).token == Token::Symbol('.') {
+				iter.next();
+				name.val = format!("{}.{}", name.val, try!(self.do_ident(iter)).val);
+			}
+			// A type argument, as in `Vec4<F32>`.
+			if peek!(self, iter).token == Token::Symbol('<') {
+				iter.next();
+				let arg = try!(self.do_type(iter));
+				let close = next!(self, iter);
+				if close.token != Token::Symbol('>') {
+					return Err(Error::expected(close, sym!['>']));
+				}
+				name.val = format!("{}<{}>", name.val, arg.val);
+			}
+			return Ok(name);
+		}
+		let token = next!(self, iter);
+		let paren = next!(self, iter);
+		if paren.token != Token::Symbol('(') {
+			return Err(Error::expected(paren, sym!['(']));
+		}
+		let mut params = Vec::new();
+		loop {
+			self.trim_commas(iter);
+			if peek!(self, iter).token == Token::Symbol(')') {
+				iter.next();
+				break;
+			}
+			params.push(try!(self.do_type(iter)).val);
+		}
+		let name = match try!(self.do_ret_type(iter)) {
+			Some(ret) => format!("fn({}) -> {}", params.join(", "), ret.val),
+			None      => format!("fn({})", params.join(", ")),
+		};
+		Ok(Node::new(name, token))
+	}
+
+	/// A type, or `@typeof(Expr)`.
+	fn do_type_name(&mut self, iter: &mut Iter) -> Result<TypeName> {
+		let is_typeof = match peek!(self, iter).token {
+			Token::Special(ref name) => name == "typeof",
+			_ => return Ok(TypeName::Name(try!(self.do_type(iter)))),
+		};
+		let token = next!(self, iter);
+		if !is_typeof {
+			return Err(Error::invalid_special(token));
+		}
+		let mut expr = Vec::new();
+		try!(self.do_parenthesized(iter, |slf, iter| slf.do_expr(iter, ')', &mut expr)));
+		Ok(TypeName::TypeOf(Node::new(expr, token)))
+	}
+
+	/// Parses what `parse` does between parentheses.
+	fn do_parenthesized<T, F>(&mut self, iter: &mut Iter, parse: F) -> Result<T>
+	                         where F: FnOnce(&mut Self, &mut Iter) -> Result<T> {
+		let token = next!(self, iter);
+		if token.token != Token::Symbol('(') {
+			return Err(Error::expected(token, sym!['(']));
+		}
+		let res = try!(parse(self, iter));
+		let (end, fix) = (self.end.clone(), ends_line(iter));
+		let token = next!(self, iter);
+		if token.token != Token::Symbol(')') {
+			let e = Error::expected(token, sym![')']);
+			return Err(if fix { e.fix(end, ")") } else { e });
+		}
+		Ok(res)
+	}
+
+	fn do_ident(&mut self, iter: &mut Iter) -> Result<Node<Id>> {
+		let token = next!(self, iter);
+		match token.token {
+			Token::Ident(ref id) => Ok(Node::new(id.clone(), token.clone())),
+			_ => Err(Error::expected(token, vec![Token::Ident("".to_string())])),
+		}
+	}
+
 	// Do each statement in the block.
+	// A statement which cannot be parsed is replaced by an error node, and parsing goes on
+	// after it.
 	fn do_block(&mut self, iter: &mut Iter, block: &mut Block) {
 		loop {
+			self.trim_commas(iter);
+			let start = match iter.peek() {
+				Some(token) => token.clone(),
+				None        => FullToken::none(0, 0),
+			};
 			match self.do_statement(iter) {
 				Ok(s)  => block.push(s),
 				Err(e) => match e.kind {
 					ErrorKind::Done => break,
 					ErrorKind::UnclosedBlock => {
-						self.errors.push(e);
+						self.error(e);
 						break;
 					},
 					_ => {
-						self.errors.push(e);
-						match self.close_block(iter) {
-							Ok(()) => (),
-							Err(e) => self.errors.push(e),
-						}
+						let closed = self.synchronize(iter, &e.token);
+						self.error(e);
+						let id = match start.token {
+							Token::Ident(ref id) => Some(id.clone()),
+							_ => None,
+						};
+						block.push(Statement::Error(Node::new(id, start)));
+						if closed { break; }
 					},
 				},
 			}
 		}
 	}
 
-	// Throw away tokens until ending '}' is found.
-	fn close_block(&mut self, iter: &mut Iter) -> Result<()> {
+	/// Throws away the rest of a statement which could not be parsed, up to the next
+	/// newline or keyword which starts a statement. `error` is where parsing failed.
+	/// Returns whether the end of the block was thrown away too.
+	fn synchronize(&mut self, iter: &mut Iter, error: &FullToken) -> bool {
+		// The token the error is at may have been taken already.
+		if iter.peek().map_or(true, |token| token.start != error.start || token.file != error.file) {
+			match error.token {
+				Token::Comma       => return false,
+				Token::Symbol('}') => return true,
+				_ => (),
+			}
+		}
+		let mut depth = 0;
 		loop {
-			match next!(self, iter).token {
-				Token::Symbol('{') => return self.close_block(iter),
-				Token::Symbol('}') => return Ok(()),
+			match iter.peek().map(|token| token.token.clone()) {
+				None | Some(Token::None) => return false,
+				Some(Token::Comma) if depth == 0 => {
+					iter.next();
+					return false;
+				},
+				Some(Token::KwIf) | Some(Token::KwLoop) | Some(Token::KwReturn) if depth == 0 => {
+					return false;
+				},
+				Some(Token::Symbol('}')) if depth == 0 => return false,
+				Some(Token::Symbol('{')) => depth += 1,
+				Some(Token::Symbol('}')) => depth -= 1,
 				_ => (),
 			}
+			iter.next();
+		}
+	}
+
+	/// Records an error. An unclosed block is only reported once, as every enclosing
+	/// block is unclosed too.
+	fn error(&mut self, e: Error) {
+		if e.kind == ErrorKind::UnclosedBlock &&
+		   self.errors.iter().any(|e| e.kind == ErrorKind::UnclosedBlock) {
+			return;
 		}
+		self.errors.push(e);
 	}
 
 	fn do_statement(&mut self, iter: &mut Iter) -> Result<Statement> {
@@ -92,8 +472,15 @@ impl Constructor {
 		let token = next!(self, iter);
 		match token.token {
 			Token::Ident(ref id) => {
-				// Statements beginning with an identifier are either assignments or declarations.
+				// Statements beginning with an identifier are either assignments, declarations
+				// or calls.
 				let id = id.to_string();
+				let next = peek!(self, iter).token.clone();
+				if next == Token::Symbol('(') || next == Token::Symbol('.') {
+					let mut expr = Vec::new();
+					try!(self.do_expr_from(iter, '}', &mut expr, Some(token.clone())));
+					return Ok(Statement::Expr(expr));
+				}
 				let token2 = next!(self, iter);
 				match token2.token {
 					Token::Symbol(':') => self.do_declare(iter, Node::new(id, token.clone())),
@@ -126,14 +513,15 @@ impl Constructor {
 			},
 			Token::KwIf     => self.do_if(    iter),
 			Token::KwLoop   => self.do_loop(  iter),
-			Token::KwReturn => self.do_return(iter),
+			Token::KwReturn => self.do_return(iter, token),
 			Token::Symbol('{') => {
 				let mut block = Vec::new();
 				self.do_block(iter, &mut block);
 				Ok(Statement::Block(Node::new(block, token)))
 			},
 			Token::Symbol('}') => Err(Error::done()),
-			Token::Special(command) => self.do_special(&command[..], iter),
+			Token::None => Err(Error::unclosed_block(token)),
+			Token::Special(ref command) => self.do_special(&command[..], iter, token.clone()),
 			_ => Err(Error::expected(token, vec![Token::Ident("".to_string()),
 			                                     Token::KwIf,
 			                                     Token::KwLoop,
@@ -144,23 +532,33 @@ impl Constructor {
 	}
 
 	fn do_declare(&mut self, iter: &mut Iter, id: Node<String>) -> Result<Statement> {
-		let token = next!(self, iter);
-		let type_id = match token.token {
-			Token::Symbol('=') => None, // var := val
-			Token::Ident(_) => {        // var: type
+		let type_id = match peek!(self, iter).token {
+			Token::Symbol('=') => { // var := val
+				iter.next();
+				None
+			},
+			Token::Ident(_) | Token::KwFn | Token::Special(_) => { // var: type
+				let type_id = Some(try!(self.do_type_name(iter)));
 				let eq_token = next!(self, iter);
-				let type_id = if let Token::Ident(ref id) = token.token {
-					Some(Node::new(id.clone(), token.clone()))
-				} else { unreachable!() };
 				match eq_token.token {
 					Token::Symbol('=') => (), // var: type = val
 					Token::Comma       => return Ok(Statement::Declaration(id, type_id, None)),
-					_ => return Err(Error::expected(eq_token, vec![Token::Symbol('=')])),
+					_ => {
+						let fix = starts_expr(&eq_token.token);
+						let e = Error::expected(eq_token.clone(), vec![Token::Symbol('=')]);
+						return Err(if fix { e.fix(eq_token.before(), "= ") } else { e });
+					},
 				}
 				type_id
 			},
-			_ => return Err(Error::expected(token, vec![Token::Symbol('='),
-			                                            Token::Ident("".to_string())])),
+			_ => {
+				// `x: 1` is missing the `=` of `:=`.
+				let colon_end = self.end.clone();
+				let token = next!(self, iter);
+				let fix = starts_expr(&token.token);
+				let e = Error::expected(token, vec![Token::Symbol('='), Token::Ident("".to_string())]);
+				return Err(if fix { e.fix(colon_end, "=") } else { e });
+			},
 		};
 		self.trim_commas(iter);
 		let mut expr = Vec::new();
@@ -174,10 +572,10 @@ impl Constructor {
 		if op != Op::Not {
 			// If it is an operator assignment (like +=) then the expression has the variable
 			// appended to the front and the operator appended to the back.
-			let (l, c) = (self.line, self.column);
-			expr.push(Node::newn((ExprToken::Id(id.val.clone()), Type::Unknown), l, c));
+			let pos = self.pos.clone();
+			expr.push(Node::new((ExprToken::Id(id.val.clone()), Type::Unknown), pos.clone()));
 			try!(self.do_expr(iter, '}', &mut expr));
-			expr.push(Node::newn((ExprToken::Op(op,), op.return_type()), l, c));
+			expr.push(Node::new((ExprToken::Op(op,), op.return_type()), pos));
 		} else {
 			try!(self.do_expr(iter, '}', &mut expr));
 		}
@@ -235,11 +633,11 @@ impl Constructor {
 			try!(self.do_expr(iter, '{', &mut expr));
 			self.trim_commas(iter);
 			//if iter.peek().unwrap().token == Token::Symbol('{') { iter.next(); }
-			expr.push(Node::newn(ExprToken::Op(Op::Not), self.line, self.column));
+			expr.push(Node::new(ExprToken::Op(Op::Not), self.pos.clone()));
 
 			let token = iter.next().unwrap();
 			let mut block = Vec::new();
-			let break_block = Node::newn(vec![Statement::Break], self.line, self.column);
+			let break_block = Node::new(vec![Statement::Break], self.pos.clone());
 			block.push(Statement::If(vec![expr], vec![break_block], None));
 			self.do_block(iter, &mut block);
 
@@ -261,19 +659,20 @@ impl Constructor {
 		}
 	}
 
-	fn do_return(&mut self, iter: &mut Iter) -> Result<Statement> {
+	fn do_return(&mut self, iter: &mut Iter, token: FullToken) -> Result<Statement> {
 		{
-			let token = peek!(self, iter);
-			if token.token == Token::Comma || token.token == Token::Symbol('}') {
-				return Ok(Statement::Return(None));
+			let next = peek!(self, iter);
+			if next.token == Token::Comma || next.token == Token::Symbol('}') {
+				return Ok(Statement::Return(Node::new(None, token)));
 			}
 		}
 		let mut expr = Vec::new();
 		try!(self.do_expr(iter, '}', &mut expr));
-		Ok(Statement::Return(Some(expr)))
+		Ok(Statement::Return(Node::new(Some(expr), token)))
 	}
 
-	fn do_special(&mut self, command: &str, iter: &mut Iter) -> Result<Statement> {
+	fn do_special(&mut self, command: &str, iter: &mut Iter,
+	              special: FullToken) -> Result<Statement> {
 		// TODO: make command lowercase (its unstable!)
 		match command {
 			"print" => {
@@ -283,25 +682,83 @@ impl Constructor {
 					_ => Err(Error::expected(token, vec![Token::Ident("".to_string())]))
 				}
 			},
-			_ => Err(Error::invalid_special(FullToken::new(Token::Special(command.to_string()),
-			                                               self.line,
-			                                               self.column))),
+			"static_assert" | "assert" => {
+				let (expr, message) = try!(self.do_parenthesized(iter, |slf, iter| {
+					let mut expr = Vec::new();
+					try!(slf.do_expr(iter, ')', &mut expr));
+					if command != "static_assert" {
+						return Ok((expr, None));
+					}
+					let token = next!(slf, iter);
+					if token.token != Token::Comma {
+						return Err(Error::expected(token, vec![Token::Comma]));
+					}
+					let token = next!(slf, iter);
+					match token.token {
+						Token::String(message) => Ok((expr, Some(message))),
+						_ => Err(Error::expected(token, vec![Token::String("".to_string())])),
+					}
+				}));
+				let expr = Node::new(expr, special);
+				Ok(match message {
+					Some(message) => Statement::StaticAssert(expr, message),
+					None          => Statement::Assert(expr),
+				})
+			},
+			_ => Err(Error::invalid_special(special)),
 		}
 	}
 
-	// shunting yard
 	fn do_expr(&mut self, iter: &mut Iter, term: char, output: &mut Expr) -> Result<()> {
+		self.do_expr_from(iter, term, output, None)
+	}
+
+	// shunting yard
+	// `first` is a token of the expression which has already been taken from the iterator.
+	fn do_expr_from(&mut self, iter: &mut Iter, term: char, output: &mut Expr,
+	                mut first: Option<FullToken>) -> Result<()> {
 		let mut ops: Vec<(Op, FullToken)> = Vec::new();
+		// The calls currently open: the function's token, the index of its parenthesis in `ops`,
+		// the number of arguments so far and the length of the output when the argument began.
+		let mut calls: Vec<(FullToken, usize, u32, usize)> = Vec::new();
 		let mut prev_was_op = true;
 		loop {
-			{
+			if first.is_none() {
+				let in_call = match calls.last() {
+					Some(&(_, paren, _, _)) => {
+						ops.iter().rposition(|&(op, _)| op == Op::TempParen) == Some(paren)
+					},
+					None => false,
+				};
+				if in_call && peek!(self, iter).token == Token::Comma {
+					// The argument has terminated.
+					iter.next();
+					while ops[ops.len() - 1].0 != Op::TempParen {
+						let (op, ftoken) = ops.pop().unwrap();
+						output.push(Node::new((ExprToken::Op(op), op.return_type()), ftoken));
+					}
+					let call = calls.last_mut().unwrap();
+					if output.len() > call.3 {
+						call.2 += 1;
+						call.3 = output.len();
+					}
+					prev_was_op = true;
+					continue;
+				}
 				let token = peek!(self, iter);
-				if token.token == Token::Comma || token.token == Token::Symbol(term) {
+				// A terminating ')' may instead close a parenthesis of the expression.
+				let closes_paren = term == ')' && ops.iter().any(|&(op, _)| op == Op::TempParen);
+				// The end of the file ends the last item too.
+				if token.token == Token::Comma || token.token == Token::None ||
+				   (token.token == Token::Symbol(term) && !closes_paren) {
 					// The expression has terminated.
 					while !ops.is_empty() {
 						let (op, ftoken) = ops.pop().unwrap();
 						if op == Op::TempParen {
-							return Err(Error::expected((*token).clone(), vec![Token::Symbol(')')]));
+							let e = Error::expected((*token).clone(), vec![Token::Symbol(')')]);
+							// The parenthesis is closed after the last operand.
+							let fix = !prev_was_op && ends_line(iter);
+							return Err(if fix { e.fix(self.end.clone(), ")") } else { e });
 						}
 						output.push(Node::new((ExprToken::Op(op), op.return_type()), ftoken));
 					}
@@ -310,7 +767,13 @@ impl Constructor {
 			}
 			let pwo = prev_was_op;
 			prev_was_op = false;
-			let token = next!(self, iter);
+			let mut token = match first.take() {
+				Some(token) => token,
+				None        => next!(self, iter),
+			};
+			if let Token::Ident(_) = token.token {
+				token = try!(self.do_path(iter, token));
+			}
 			match token.token {
 				Token::Int(val, ty) => {
 					output.push(Node::new((ExprToken::IntLit(val), Type::Num(ty)), token));
@@ -319,28 +782,95 @@ impl Constructor {
 					let ty = Type::Num(NumType::Float(ty));
 					output.push(Node::new((ExprToken::FloatLit(val), ty), token));
 				},
-				Token::String(_) => unimplemented!(),
-					/*let val = if let Token::String(ref val) = token.token {
-						val.clone()
-					} else { unreachable!() };
-					output.push(Node::new(ExprToken::StringLit(val), token));*/
+				Token::Char(val) => {
+					output.push(Node::new((ExprToken::CharLit(val), Type::Char), token));
+				},
+				Token::String(ref val) => {
+					output.push(Node::new((ExprToken::StringLit(val.clone()), Type::Unknown), token.clone()));
+				},
+				Token::Ident(_) if peek!(self, iter).token == Token::Symbol('(') => {
+					iter.next();
+					ops.push((Op::TempParen, FullToken::none(0, 0)));
+					calls.push((token, ops.len() - 1, 0, output.len()));
+					prev_was_op = true;
+				},
 				Token::Ident(_)   => {
 					let id = if let Token::Ident(ref id) = token.token {
 						id.clone()
 					} else { unreachable!() };
 					output.push(Node::new((ExprToken::Id(id), Type::Unknown), token));
 				},
+				Token::Special(ref name) if Op::from_special(name).is_some() ||
+				                            LANE_SPECIALS.contains(&&name[..]) => {
+					let paren = next!(self, iter);
+					if paren.token != Token::Symbol('(') {
+						return Err(Error::expected(paren, sym!['(']));
+					}
+					ops.push((Op::TempParen, FullToken::none(0, 0)));
+					calls.push((token.clone(), ops.len() - 1, 0, output.len()));
+					prev_was_op = true;
+				},
+				Token::Special(ref name) if TypeQuery::from_str(name).is_some() => {
+					let ty = try!(self.do_parenthesized(iter, |slf, iter| slf.do_type(iter)));
+					let query = ExprToken::TypeQuery(TypeQuery::from_str(name).unwrap(), ty);
+					output.push(Node::new((query, Type::Unknown), token.clone()));
+				},
+				Token::Special(ref name) if name == "cast" => {
+					let ty = try!(self.do_parenthesized(iter, |slf, iter| {
+						let ty = try!(slf.do_type(iter));
+						let comma = next!(slf, iter);
+						if comma.token != Token::Comma {
+							return Err(Error::expected(comma, vec![Token::Comma]));
+						}
+						try!(slf.do_expr(iter, ')', output));
+						Ok(ty)
+					}));
+					output.push(Node::new((ExprToken::Cast(ty), Type::Unknown), token.clone()));
+				},
+				Token::KwFn => {
+					let def = try!(self.do_closure(iter, token.clone()));
+					output.push(Node::new((ExprToken::Closure(Box::new(def)), Type::Unknown), token));
+				},
 				Token::KwTrue  => {
 					output.push(Node::new((ExprToken::BoolLit(true),  Type::Bool), token));
 				},
 				Token::KwFalse => {
 					output.push(Node::new((ExprToken::BoolLit(false), Type::Bool), token));
 				},
-				Token::Symbol('(') => ops.push((Op::TempParen, FullToken::none(0, 0))),
+				Token::Symbol('(') => {
+					ops.push((Op::TempParen, FullToken::none(0, 0)));
+					prev_was_op = true;
+				},
 				Token::Symbol(')') => loop {
 					match ops.pop() {
 						None => return Err(Error::mismatched_paren(token)),
-						Some((Op::TempParen, _)) => break,
+						Some((Op::TempParen, _)) => {
+							if calls.last().map_or(false, |&(_, paren, _, _)| paren == ops.len()) {
+								let (call, _, mut args, start) = calls.pop().unwrap();
+								if output.len() > start { args += 1; }
+								// Specials such as `@wrapping_add` are operators.
+								let val = match call.token {
+									Token::Ident(ref id) => (ExprToken::Call(id.clone(), args), Type::Unknown),
+									Token::Special(ref name) if name == "vec" => (ExprToken::Vector(args), Type::Unknown),
+									Token::Special(ref name) if name == "extract" => match args {
+										2 => (ExprToken::Extract, Type::Unknown),
+										_ => return Err(Error::wrong_arg_count(call.clone(), 2)),
+									},
+									Token::Special(ref name) if name == "insert" => match args {
+										3 => (ExprToken::Insert, Type::Unknown),
+										_ => return Err(Error::wrong_arg_count(call.clone(), 3)),
+									},
+									Token::Special(ref name) if args == 2 => {
+										let op = Op::from_special(name).unwrap();
+										(ExprToken::Op(op), op.return_type())
+									},
+									Token::Special(_) => return Err(Error::wrong_arg_count(call.clone(), 2)),
+									_ => unreachable!(),
+								};
+								output.push(Node::new(val, call));
+							}
+							break;
+						},
 						Some((op, t)) => {
 							output.push(Node::new((ExprToken::Op(op), op.return_type()), t));
 						},
@@ -405,13 +935,48 @@ impl Constructor {
 	fn trim_commas(&mut self, iter: &mut Iter) {
 		loop {
 			let _ = match iter.peek() {
-				Some(&FullToken { token: Token::Comma, line: _, column: _ }) => iter.next(),
+				Some(&FullToken { token: Token::Comma, .. }) => iter.next(),
 				_ => break,
 			};
 		}
 	}
 }
 
+/// Whether a token can start an expression, so that inserting what is missing before it
+/// surely fixes the error.
+fn starts_expr(token: &Token) -> bool {
+	match *token {
+		Token::Int(..) | Token::Float(..) | Token::Char(_) | Token::Ident(_) | Token::Special(_) |
+		Token::KwTrue | Token::KwFalse | Token::KwFn |
+		Token::Symbol('(') | Token::Symbol('-') | Token::Symbol('!') => true,
+		_ => false,
+	}
+}
+
+/// Whether the next token ends a line, the file or a block, so that what comes before it is
+/// complete once what is missing is inserted.
+fn ends_line(iter: &Iter) -> bool {
+	let mut ahead = iter.clone();
+	let token = match ahead.next() {
+		Some(token) => token,
+		None        => return true,
+	};
+	match token.token {
+		Token::None | Token::Symbol('{') | Token::Symbol('}') => true,
+		// Unlike the end of a line, a `,` is followed by more on the same line.
+		Token::Comma => ahead.peek().map_or(true, |next| next.line > token.line),
+		_ => false,
+	}
+}
+
+/// Whether a token can start a type.
+fn starts_type(token: &Token) -> bool {
+	match *token {
+		Token::Ident(_) | Token::KwFn => true,
+		_ => false,
+	}
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -449,4 +1014,109 @@ mod test {
 			}"#));
 		assert_eq!(e, Vec::new());
 	}
+
+	#[test]
+	fn program_test() {
+		let (p, e) = construct_program(tokenize(r#"
+			const STEMS := 4
+			@export fn f(a: I32, b: F64) -> I32 {
+				return a
+			}
+			fn g() {}"#));
+		assert_eq!(e, Vec::new());
+		assert_eq!(p.len(), 3);
+		match p[1] {
+			Item::Function(ref def) => {
+				assert!(def.export);
+				assert_eq!(def.params.len(), 2);
+				assert_eq!(def.ret.as_ref().unwrap().val, "I32");
+			},
+			_ => panic!(),
+		}
+
+		let (p, e) = construct_program(tokenize("fn f( { }\nfn g() {}"));
+		assert_eq!(e.len(), 1);
+		assert_eq!(p.len(), 1);
+
+		let (_, e) = construct_program(tokenize("@export const X := 1"));
+		assert_eq!(e.len(), 1);
+	}
+
+	#[test]
+	fn recovery_test() {
+		let (p, e) = construct_program(tokenize(r#"fn f() {
+			x := (1 + 2
+			y := 3 = 4
+			if x { z: 1 }
+			loop { return ) }
+			w := 4
+		}
+		fn g() { a := ) }"#));
+		let lines: Vec<u32> = e.iter().map(|e| e.token.line).collect();
+		assert_eq!(lines, vec![2, 3, 4, 5, 8]);
+		let block = match p[0] {
+			Item::Function(ref def) => &def.block.val,
+			_ => panic!(),
+		};
+		assert_eq!(block.len(), 5);
+		match block[0] {
+			Statement::Error(ref start) => assert_eq!(start.val, Some("x".to_string())),
+			_ => panic!(),
+		}
+		match block[4] {
+			Statement::Declaration(ref id, _, _) => assert_eq!(id.val, "w"),
+			_ => panic!(),
+		}
+		assert_eq!(p.len(), 2);
+
+		let (_, e) = construct_program(tokenize("fn f() {
+	x := 1
+"));
+		assert_eq!(e.len(), 1);
+		assert_eq!(e[0].kind, ErrorKind::UnclosedBlock);
+		let (_, e) = construct_program(tokenize("const X := 1"));
+		assert_eq!(e, Vec::new());
+	}
+
+	#[test]
+	fn assert_test() {
+		let (p, e) = construct_program(tokenize(r#"fn f() {
+			@static_assert((1 + 2) * 3 == 9, "math")
+			@assert(g(1, 2))
+		}"#));
+		assert_eq!(e, Vec::new());
+		let block = match p[0] {
+			Item::Function(ref def) => &def.block.val,
+			_ => panic!(),
+		};
+		match block[0] {
+			Statement::StaticAssert(ref expr, ref message) => {
+				assert_eq!(expr.val.len(), 7);
+				assert_eq!(message, "math");
+			},
+			_ => panic!(),
+		}
+		match block[1] {
+			Statement::Assert(ref expr) => assert_eq!(expr.token.token, Token::Special("assert".to_string())),
+			_ => panic!(),
+		}
+
+		let (_, e) = construct_program(tokenize("fn f() { @static_assert(true) }"));
+		assert_eq!(e[0].kind, ErrorKind::Expected(vec![Token::Comma]));
+	}
+
+	#[test]
+	fn wrapping_test() {
+		let (p, e) = construct_program(tokenize("const X := @wrapping_sub(1, 2) * 3\n"));
+		assert_eq!(e, Vec::new());
+		let ops: Vec<_> = match p[0] {
+			Item::Const(_, _, ref expr) => expr.iter().map(|node| node.val.0.clone()).collect(),
+			_ => panic!(),
+		};
+		assert_eq!(ops, vec![ExprToken::IntLit(1), ExprToken::IntLit(2), ExprToken::Op(Op::WrapSub),
+		                     ExprToken::IntLit(3), ExprToken::Op(Op::Mul)]);
+
+		let (_, e) = construct_program(tokenize("const X := @wrapping_add(1)\n"));
+		assert_eq!(e[0].kind, ErrorKind::WrongArgCount(2));
+	}
 }
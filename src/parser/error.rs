@@ -1,53 +1,183 @@
 use std::{fmt, result};
+use std::path::PathBuf;
 
-use parser::{FullToken, Token};
+use diagnostic::{self, Diagnostic, Fix};
+use parser::constructor::SPECIALS;
+use parser::{FullToken, Token, Invalid};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Error {
 	pub token: FullToken,
 	pub kind: ErrorKind,
+	/// Edits of the source which fix the error, when they surely do.
+	pub fixes: Vec<Fix>,
 }
 #[derive(Clone, Debug, PartialEq)]
 pub enum ErrorKind {
 	Done,
-	InvalidToken,
+	InvalidToken(Invalid),
 	Expected(Vec<Token>),
 	InvalidOp,
 	InvalidSpecial,
 	UnclosedBlock,
 	MismatchedParen,
+	ImportNotFound(PathBuf),
+	ImportCycle(PathBuf),
+	UnknownModule,
+	WrongArgCount(u32),   // expected
+	DuplicateImport(String, FullToken), // the name of the module, the first import of it
 }
 impl Error {
+	fn new(token: FullToken, kind: ErrorKind) -> Error {
+		Error { token: token, kind: kind, fixes: Vec::new() }
+	}
+	/// Adds an edit to the fix of the error.
+	pub fn fix(mut self, token: FullToken, text: &str) -> Error {
+		self.fixes.push(Fix { token: token, text: text.to_string() });
+		self
+	}
 	pub fn done() -> Error {
-		Error { token: FullToken::none(0, 0), kind: ErrorKind::Done }
+		Error::new(FullToken::none(0, 0), ErrorKind::Done)
 	}
+	/// The error of a token the tokenizer could not read.
 	pub fn invalid_token(token: FullToken) -> Error {
-		Error { token: token, kind: ErrorKind::InvalidToken }
+		let invalid = match token.token {
+			Token::Invalid(ref invalid) => invalid.clone(),
+			_ => unreachable!(),
+		};
+		Error::new(token, ErrorKind::InvalidToken(invalid))
 	}
 	pub fn expected(token: FullToken, possible_choices: Vec<Token>) -> Error {
-		Error { token: token, kind: ErrorKind::Expected(possible_choices) }
+		Error::new(token, ErrorKind::Expected(possible_choices))
 	}
 	pub fn invalid_op(token: FullToken) -> Error {
-		Error { token: token, kind: ErrorKind::InvalidOp }
+		Error::new(token, ErrorKind::InvalidOp)
 	}
 	pub fn invalid_special(token: FullToken) -> Error {
-		Error { token: token, kind: ErrorKind::InvalidSpecial }
+		Error::new(token, ErrorKind::InvalidSpecial)
 	}
 	pub fn unclosed_block(token: FullToken) -> Error {
-		Error { token: token, kind: ErrorKind::UnclosedBlock }
+		Error::new(token, ErrorKind::UnclosedBlock)
 	}
 	pub fn mismatched_paren(token: FullToken) -> Error {
-		Error { token: token, kind: ErrorKind::MismatchedParen }
+		Error::new(token, ErrorKind::MismatchedParen)
+	}
+	pub fn import_not_found(token: FullToken, path: PathBuf) -> Error {
+		Error::new(token, ErrorKind::ImportNotFound(path))
+	}
+	pub fn import_cycle(token: FullToken, path: PathBuf) -> Error {
+		Error::new(token, ErrorKind::ImportCycle(path))
+	}
+	pub fn unknown_module(token: FullToken) -> Error {
+		Error::new(token, ErrorKind::UnknownModule)
+	}
+	pub fn wrong_arg_count(token: FullToken, expected: u32) -> Error {
+		Error::new(token, ErrorKind::WrongArgCount(expected))
+	}
+	pub fn duplicate_import(token: FullToken, name: String, first: FullToken) -> Error {
+		Error::new(token, ErrorKind::DuplicateImport(name, first))
+	}
+}
+impl Error {
+	pub fn diagnostic(&self) -> Diagnostic {
+		let code = match self.kind {
+			ErrorKind::Done            => unreachable!(),
+			ErrorKind::InvalidToken(Invalid::Number(_)) => "E0101",
+			ErrorKind::InvalidToken(Invalid::Suffix(_)) => "E0111",
+			ErrorKind::InvalidToken(Invalid::CharLit(_)) => "E0112",
+			ErrorKind::InvalidToken(Invalid::UnterminatedString) => "E0113",
+			ErrorKind::InvalidToken(Invalid::UnterminatedChar) => "E0114",
+			ErrorKind::InvalidToken(Invalid::UnterminatedComment) => "E0115",
+			ErrorKind::InvalidToken(Invalid::Char(_)) => "E0116",
+			ErrorKind::Expected(_)     => "E0102",
+			ErrorKind::InvalidOp       => "E0103",
+			ErrorKind::InvalidSpecial  => "E0104",
+			ErrorKind::UnclosedBlock   => "E0105",
+			ErrorKind::MismatchedParen => "E0106",
+			ErrorKind::ImportNotFound(_) => "E0107",
+			ErrorKind::ImportCycle(_)  => "E0108",
+			ErrorKind::UnknownModule   => "E0109",
+			ErrorKind::WrongArgCount(_) => "E0110",
+			ErrorKind::DuplicateImport(..) => "E0117",
+		};
+		let mut diagnostic = Diagnostic::error(code, &self.token, Message(self).to_string());
+		diagnostic.fixes = self.fixes.clone();
+		if let Some(fix) = self.fixes.first() {
+			diagnostic.help = Some(if fix.token.start == fix.token.end {
+				format!("insert `{}`", fix.text.trim())
+			} else {
+				format!("write `{}` in place of `{}`", fix.text, fix.token.token)
+			});
+		}
+		match self.kind {
+			ErrorKind::ImportNotFound(_) => {
+				diagnostic.note("Imports are found relative to the directory of the file importing them.")
+			},
+			ErrorKind::ImportCycle(_) => {
+				diagnostic.note("Files cannot import each other, directly or through other files.")
+			},
+			ErrorKind::UnknownModule => {
+				diagnostic.help("import the file of the module first, as in `import lib.module`")
+			},
+			ErrorKind::DuplicateImport(_, ref first) => {
+				diagnostic.label(first, "first imported here")
+					.note("Modules are named by the last part of their path, so two of the same name \
+					       cannot be imported into one file.")
+			},
+			ErrorKind::InvalidToken(Invalid::Suffix(_)) => {
+				diagnostic.note("The suffixes are the names of number types in lowercase, such as \
+				                 `u8` or `f32`, or `i`, `u` and `f` alone.")
+			},
+			ErrorKind::InvalidToken(Invalid::CharLit(_)) => {
+				diagnostic.note("The escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\'`, `\\\"` and \
+				                 `\\u{...}`.")
+			},
+			ErrorKind::InvalidSpecial => match self.token.token {
+				Token::Special(ref name) => match diagnostic::suggest(name, SPECIALS.iter().cloned()) {
+					Some(special) => {
+						diagnostic.help(&diagnostic::did_you_mean(&format!("@{}", name), &format!("@{}", special)))
+					},
+					None          => diagnostic,
+				},
+				_ => diagnostic,
+			},
+			ErrorKind::InvalidToken(Invalid::UnterminatedString) |
+			ErrorKind::InvalidToken(Invalid::UnterminatedComment) => {
+				diagnostic.note("It starts here and goes on to the end of the file.")
+			},
+			_ => diagnostic,
+		}
 	}
 }
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
-		try!(f.write_fmt(format_args!("At {}:{} -- ", self.token.line, self.token.column)));
-		let my_token = &self.token.token;
-		match self.kind {
+		fmt::Display::fmt(&self.diagnostic(), f)
+	}
+}
+
+/// The description of an error, without its position.
+struct Message<'a>(&'a Error);
+impl<'a> fmt::Display for Message<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+		let my_token = &self.0.token.token;
+		match self.0.kind {
 			ErrorKind::Done => unreachable!(),
-			ErrorKind::InvalidToken => {
-				try!(f.write_str("Invalid token."));
+			ErrorKind::InvalidToken(ref invalid) => match *invalid {
+				Invalid::Number(ref number) => {
+					try!(f.write_fmt(format_args!("'{}' is not a valid number.", number)));
+				},
+				Invalid::Suffix(ref suffix) => {
+					try!(f.write_fmt(format_args!("Unknown number suffix '{}'.", suffix)));
+				},
+				Invalid::CharLit(ref body) => {
+					try!(f.write_fmt(format_args!("'{}' is not a single character or escape.", body)));
+				},
+				Invalid::UnterminatedString => try!(f.write_str("Unterminated string.")),
+				Invalid::UnterminatedChar => try!(f.write_str("Unterminated character literal.")),
+				Invalid::UnterminatedComment => try!(f.write_str("Unterminated block comment.")),
+				Invalid::Char(c) => {
+					try!(f.write_fmt(format_args!("Unexpected character '{}'.", c.escape_default())));
+				},
 			},
 			ErrorKind::Expected(ref tokens) => {
 				if tokens.len() == 1 {
@@ -73,6 +203,21 @@ impl fmt::Display for Error {
 			ErrorKind::MismatchedParen => {
 				try!(f.write_str("Mismatched parenthesis."));
 			},
+			ErrorKind::ImportNotFound(ref path) => {
+				try!(f.write_fmt(format_args!("Could not open '{}'.", path.display())));
+			},
+			ErrorKind::ImportCycle(ref path) => {
+				try!(f.write_fmt(format_args!("'{}' imports itself.", path.display())));
+			},
+			ErrorKind::UnknownModule => {
+				try!(f.write_fmt(format_args!("'{}' is not an imported module.", my_token)));
+			},
+			ErrorKind::WrongArgCount(expected) => {
+				try!(f.write_fmt(format_args!("'{}' takes {} arguments.", my_token, expected)));
+			},
+			ErrorKind::DuplicateImport(ref name, _) => {
+				try!(f.write_fmt(format_args!("A module named '{}' is already imported.", name)));
+			},
 		}
 		Ok(())
 	}
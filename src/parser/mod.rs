@@ -1,25 +1,38 @@
 pub mod ast;
 
 pub mod tokenizer;
-pub use self::tokenizer::{tokenize, Token,  FullToken};
+pub use self::tokenizer::{tokenize, Token,  FullToken, Invalid};
 
 pub mod constructor;
-pub use self::constructor::construct;
+pub use self::constructor::{construct, construct_program};
 
 pub mod error;
 pub use self::error::{Error, ErrorKind};
 
-pub fn parse(string: &str) -> (ast::Block, Vec<Error>) {
-	let tokens = tokenize(string);
+pub mod module;
+pub use self::module::load;
+
+pub fn parse(string: &str) -> (ast::Program, Vec<Error>) {
+	parse_file(string, 0)
+}
+
+/// Parses a program, marking its tokens as being from the given file.
+pub fn parse_file(string: &str, file: u32) -> (ast::Program, Vec<Error>) {
+	let mut tokens = tokenize(string);
 	let mut errors = Vec::new();
-	for token in tokens {
+	for token in &mut tokens {
+		token.file = file;
 		match token.token {
-			Token::Invalid(_, _) => errors.push(Error::invalid_token(token)),
+			Token::Invalid(_) => errors.push(Error::invalid_token(token.clone())),
 			_ => (),
 		}
 	}
 	if errors.is_empty() {
-		construct(tokenize(string))
+		let (program, mut errors) = construct_program(tokens);
+		for error in &mut errors {
+			error.token.file = file;
+		}
+		(program, errors)
 	} else {
 		(Vec::new(), errors)
 	}
@@ -0,0 +1,337 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use parser::ast::*;
+use parser::{parse_file, Error, FullToken};
+
+/// Parses the program in the given file along with every file it imports.
+/// Items of imported files are renamed to `module.name`, where the module is the path of
+/// the file relative to the main file with '.' between the directories,
+/// and are placed before the items of the file importing them.
+/// Also returns the paths of the files, which the `file` of each token indexes.
+pub fn load(path: &Path, code: &str) -> (Program, Vec<PathBuf>, Vec<Error>) {
+	let mut loader = Loader {
+		items: Vec::new(),
+		files: Vec::new(),
+		errors: Vec::new(),
+		loaded: HashMap::new(),
+		stack: Vec::new(),
+	};
+	let canonical = path.canonicalize().unwrap_or(path.to_path_buf());
+	loader.load_file(path, canonical, code, Vec::new(), &[]);
+	(loader.items, loader.files, loader.errors)
+}
+
+struct Loader {
+	items: Program,
+	files: Vec<PathBuf>,
+	errors: Vec<Error>,
+	/// The module name of each file which has been loaded.
+	loaded: HashMap<PathBuf, String>,
+	/// The files currently being loaded, to find cycles.
+	stack: Vec<PathBuf>,
+}
+impl Loader {
+	fn load_file(&mut self, path: &Path, canonical: PathBuf, code: &str,
+	             module: Vec<Id>, dir: &[Id]) {
+		let file = self.files.len() as u32;
+		self.files.push(path.to_path_buf());
+		self.stack.push(canonical);
+
+		let (mut items, errors) = parse_file(code, file);
+		self.errors.extend(errors);
+
+		// Imported modules are named by the last segment of their path.
+		let mut imports = HashMap::new();
+		let mut import_tokens: HashMap<&Id, &FullToken> = HashMap::new();
+		for item in &items {
+			if let Item::Import(ref import) = *item {
+				let short_name = &import.val[import.val.len() - 1];
+				if let Some(&first) = import_tokens.get(short_name) {
+					self.errors.push(Error::duplicate_import(import.token.clone(), short_name.clone(),
+					                                         first.clone()));
+					continue;
+				}
+				import_tokens.insert(short_name, &import.token);
+				match self.import(path.parent().unwrap_or(Path::new("")), dir, import) {
+					Ok(name) => { imports.insert(short_name.clone(), name); },
+					Err(e)   => self.errors.push(e),
+				}
+			}
+		}
+		items.retain(|item| match *item {
+			Item::Import(_) => false,
+			_               => true,
+		});
+
+		let mut names = HashSet::new();
+		let mut type_names = HashSet::new();
+		for item in &items {
+			match *item {
+				Item::Const(ref id, _, _) | Item::Static(ref id, _, _) => {
+					names.insert(id.val.clone());
+				},
+				Item::Function(ref def)   => { names.insert(def.name.val.clone()); },
+				Item::TypeAlias(ref id, _) => { type_names.insert(id.val.clone()); },
+				Item::Import(_)           => (),
+			}
+		}
+		let mut renamer = Renamer {
+			prefix: if module.is_empty() { String::new() } else { module.join(".") + "." },
+			names: names,
+			type_names: type_names,
+			imports: imports,
+			locals: Vec::new(),
+			errors: &mut self.errors,
+		};
+		for item in &mut items {
+			renamer.item(item);
+		}
+
+		self.items.extend(items);
+		self.stack.pop();
+	}
+
+	/// Loads an imported file if it has not been yet. Returns its module name.
+	fn import(&mut self, dir_path: &Path, dir: &[Id], import: &Node<Vec<Id>>) -> Result<String, Error> {
+		let mut path = dir_path.to_path_buf();
+		for segment in &import.val {
+			path.push(segment);
+		}
+		path.set_extension("eb");
+
+		let not_found = Error::import_not_found(import.token.clone(), path.clone());
+		let canonical = try!(path.canonicalize().map_err(|_| not_found.clone()));
+		if self.stack.contains(&canonical) {
+			return Err(Error::import_cycle(import.token.clone(), path));
+		}
+		if let Some(name) = self.loaded.get(&canonical) {
+			return Ok(name.clone());
+		}
+
+		let mut code = String::new();
+		let mut file = try!(File::open(&path).map_err(|_| not_found.clone()));
+		try!(file.read_to_string(&mut code).map_err(|_| not_found));
+
+		let mut module = dir.to_vec();
+		module.extend(import.val.iter().cloned());
+		let name = module.join(".");
+		let sub_dir = module[..module.len() - 1].to_vec();
+		self.load_file(&path, canonical.clone(), &code, module, &sub_dir);
+		self.loaded.insert(canonical, name.clone());
+		Ok(name)
+	}
+}
+
+/// Qualifies the names of a file's items, and the uses of imported items, with their module.
+struct Renamer<'a> {
+	prefix: String,
+	names: HashSet<Id>,
+	/// The names of the file's type aliases, which are in a namespace of their own.
+	type_names: HashSet<Id>,
+	/// The module name of each import.
+	imports: HashMap<Id, String>,
+	/// The variables declared in each enclosing block, which hide items.
+	locals: Vec<Vec<Id>>,
+	errors: &'a mut Vec<Error>,
+}
+impl<'a> Renamer<'a> {
+	fn item(&mut self, item: &mut Item) {
+		match *item {
+			Item::Const( ref mut id, ref mut type_name, ref mut expr) |
+			Item::Static(ref mut id, ref mut type_name, ref mut expr) => {
+				match *type_name {
+					Some(TypeName::TypeOf(ref mut expr)) => self.expr(&mut expr.val),
+					Some(TypeName::Name(ref mut name))   => self.type_name(name),
+					None => (),
+				}
+				self.expr(expr);
+				id.val = format!("{}{}", self.prefix, id.val);
+			},
+			Item::Function(ref mut def) => {
+				def.name.val = format!("{}{}", self.prefix, def.name.val);
+				self.fn_def(def);
+			},
+			Item::TypeAlias(ref mut id, ref mut type_name) => {
+				self.type_name(type_name);
+				id.val = format!("{}{}", self.prefix, id.val);
+			},
+			Item::Import(_) => (),
+		}
+	}
+	fn fn_def(&mut self, def: &mut FnDef) {
+		for &mut (_, ref mut type_name) in &mut def.params {
+			self.type_name(type_name);
+		}
+		if let Some(ref mut type_name) = def.ret {
+			self.type_name(type_name);
+		}
+		self.locals.push(def.params.iter().map(|&(ref id, _)| id.val.clone()).collect());
+		self.block(&mut def.block.val);
+		self.locals.pop();
+	}
+
+	fn block(&mut self, block: &mut Block) {
+		self.locals.push(Vec::new());
+		for statement in block {
+			match *statement {
+				Statement::Declaration(ref id, ref mut type_name, ref mut expr) => {
+					match *type_name {
+						Some(TypeName::TypeOf(ref mut expr)) => self.expr(&mut expr.val),
+						Some(TypeName::Name(ref mut name))   => self.type_name(name),
+						None => (),
+					}
+					if let Some(ref mut expr) = *expr {
+						self.expr(expr);
+					}
+					self.locals.last_mut().unwrap().push(id.val.clone());
+				},
+				Statement::Assignment(ref mut id, ref mut expr) => {
+					self.expr(expr);
+					let token = id.token.clone();
+					self.name(&mut id.val, &token);
+				},
+				Statement::If(ref mut conditions, ref mut blocks, ref mut else_block) => {
+					for condition in conditions {
+						self.expr(condition);
+					}
+					for block in blocks {
+						self.block(&mut block.val);
+					}
+					if let Some(ref mut block) = *else_block {
+						self.block(&mut block.val);
+					}
+				},
+				Statement::Loop(ref mut block) | Statement::Block(ref mut block) => {
+					self.block(&mut block.val);
+				},
+				Statement::Return(Node { val: Some(ref mut expr), .. }) |
+				Statement::Expr(ref mut expr) => self.expr(expr),
+				Statement::Return(_) => (),
+				Statement::StaticAssert(ref mut expr, _) | Statement::Assert(ref mut expr) => {
+					self.expr(&mut expr.val);
+				},
+				Statement::Print(ref mut id) => {
+					let token = FullToken::none(0, 0);
+					self.name(id, &token);
+				},
+				// The variable may be declared by it, so it is not renamed.
+				Statement::Error(Node { val: Some(ref id), .. }) => {
+					self.locals.last_mut().unwrap().push(id.clone());
+				},
+				Statement::Error(_) => (),
+			}
+		}
+		self.locals.pop();
+	}
+
+	fn expr(&mut self, expr: &mut Expr) {
+		for &mut Node { ref token, val: (ref mut e, _) } in expr {
+			match *e {
+				ExprToken::Id(ref mut id) | ExprToken::Call(ref mut id, _) => self.name(id, token),
+				ExprToken::Closure(ref mut def) => self.fn_def(def),
+				ExprToken::Cast(ref mut type_name) | ExprToken::TypeQuery(_, ref mut type_name) => {
+					self.type_name(type_name);
+				},
+				_ => (),
+			}
+		}
+	}
+
+	/// Qualifies the aliases a type name uses, which may be within other types as in
+	/// `fn(Meters) -> Meters`.
+	fn type_name(&mut self, type_name: &mut Node<Id>) {
+		let mut out = String::new();
+		let mut word = String::new();
+		for c in type_name.val.chars().chain(Some('\0')) {
+			if c.is_alphanumeric() || c == '_' || c == '.' {
+				word.push(c);
+				continue;
+			}
+			if let Some(dot) = word.find('.') {
+				match self.imports.get(&word[..dot]) {
+					Some(module) => word = format!("{}{}", module, &word[dot..]),
+					None         => self.errors.push(Error::unknown_module(type_name.token.clone())),
+				}
+			} else if self.type_names.contains(&word) {
+				word = format!("{}{}", self.prefix, word);
+			}
+			out.push_str(&word);
+			word.clear();
+			if c != '\0' { out.push(c); }
+		}
+		type_name.val = out;
+	}
+	fn name(&mut self, id: &mut Id, token: &FullToken) {
+		if let Some(dot) = id.find('.') {
+			match self.imports.get(&id[..dot]) {
+				Some(module) => *id = format!("{}{}", module, &id[dot..]),
+				None         => self.errors.push(Error::unknown_module(token.clone())),
+			}
+		} else if self.names.contains(id) && !self.locals.iter().any(|vars| vars.contains(id)) {
+			*id = format!("{}{}", self.prefix, id);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::env;
+	use std::fs::{self, File};
+	use std::io::Write;
+	use parser::ErrorKind;
+
+	fn write(path: &Path, code: &str) {
+		File::create(path).unwrap().write_all(code.as_bytes()).unwrap();
+	}
+
+	#[test]
+	fn load_test() {
+		let dir = env::temp_dir().join("thang_load_test");
+		fs::create_dir_all(dir.join("lib")).unwrap();
+		write(&dir.join("lib").join("util.eb"), "import helper\ntype Num = I32\nconst ONE: Num = 1\nfn one() -> Num { return helper.id(ONE) }");
+		write(&dir.join("lib").join("helper.eb"), "fn id(x: I32) -> I32 { return x }");
+		write(&dir.join("cycle.eb"), "import main");
+
+		let main = "import lib.util\nfn main() { one: util.Num = 2\nx := util.one() + one }";
+		write(&dir.join("main.eb"), main);
+		let (items, files, e) = load(&dir.join("main.eb"), main);
+		assert_eq!(e, Vec::new());
+		assert_eq!(files.len(), 3);
+		let names: Vec<&str> = items.iter().map(|item| match *item {
+			Item::Const(ref id, _, _) | Item::Static(ref id, _, _) => &id.val[..],
+			Item::Function(ref def)   => &def.name.val[..],
+			Item::TypeAlias(ref id, _) => &id.val[..],
+			Item::Import(_)           => panic!(),
+		}).collect();
+		assert_eq!(names, vec!["lib.helper.id", "lib.util.Num", "lib.util.ONE", "lib.util.one", "main"]);
+		match items[2] {
+			Item::Const(_, Some(TypeName::Name(ref name)), _) => assert_eq!(name.val, "lib.util.Num"),
+			_ => panic!(),
+		}
+
+		let (_, _, e) = load(&dir.join("main.eb"), "import cycle");
+		assert_eq!(e.len(), 1);
+		match e[0].kind {
+			ErrorKind::ImportCycle(_) => assert_eq!(e[0].token.file, 1),
+			_ => panic!(),
+		}
+		let (_, _, e) = load(&dir.join("main.eb"), "fn main() { x := math.one() }");
+		assert_eq!(e[0].kind, ErrorKind::UnknownModule);
+
+		// Modules of the same name in different directories cannot both be imported.
+		fs::create_dir_all(dir.join("other")).unwrap();
+		write(&dir.join("other").join("util.eb"), "fn two() -> I32 { return 2 }");
+		let (_, _, e) = load(&dir.join("main.eb"), "import lib.util\nimport other.util\nfn main() {}");
+		assert_eq!(e.len(), 1);
+		match e[0].kind {
+			ErrorKind::DuplicateImport(ref name, ref first) => {
+				assert_eq!((&name[..], first.line, e[0].token.line), ("util", 1, 2));
+			},
+			_ => panic!(),
+		}
+	}
+}
@@ -1,7 +1,6 @@
-use std::iter::Peekable;
-use std::str::{Chars, FromStr, from_utf8};
+use std::str::{FromStr, from_utf8};
 use std::ascii::AsciiExt;
-use std::{f64, result, fmt};
+use std::{char, f64, result, fmt};
 
 use types::{NumType, FloatType, SignedType, UnsignedType};
 
@@ -11,16 +10,41 @@ pub fn tokenize(string: &str) -> Vec<FullToken> {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FullToken {
+	/// Line and column of the first character, counted from 1.
 	pub line: u32,
 	pub column: u32,
+	/// Column just after the last character, which is the column for an empty span.
+	pub end_column: u32,
+	/// Byte offsets of the start and end of the token in its file.
+	/// Tokens made up by the parser have an empty span.
+	pub start: usize,
+	pub end: usize,
+	/// Index of the source file the token is from. The main file is 0.
+	pub file: u32,
 	pub token: Token,
 }
 impl FullToken {
 	pub fn new(token: Token, line: u32, column: u32) -> FullToken {
-		FullToken { token: token, line: line, column: column }
+		FullToken { token: token, line: line, column: column, end_column: column, start: 0, end: 0, file: 0 }
 	}
 	pub fn none(line: u32, column: u32) -> FullToken {
-		FullToken { token: Token::None, line: line, column: column }
+		FullToken::new(Token::None, line, column)
+	}
+	/// An empty token at the start of this one.
+	pub fn before(&self) -> FullToken {
+		let mut token = FullToken::none(self.line, self.column);
+		token.start = self.start;
+		token.end   = self.start;
+		token.file  = self.file;
+		token
+	}
+	/// An empty token just after this one, on the same line.
+	pub fn after(&self) -> FullToken {
+		let mut token = FullToken::none(self.line, self.end_column);
+		token.start = self.end;
+		token.end   = self.end;
+		token.file  = self.file;
+		token
 	}
 }
 
@@ -28,11 +52,18 @@ impl FullToken {
 pub enum Token {
 	None,
 	Float(f64, FloatType),
-	Int(  u64, NumType),
+	Int( u128, NumType),
+	/// A code point, which may not be a valid character. The checker rejects those.
+	Char(u32),
 	String(String),
 	Ident(String),
 	Symbol(char),
 	Comma,
+	KwFn,
+	KwConst,
+	KwStatic,
+	KwImport,
+	KwType,
 	KwIf,
 	KwElse,
 	KwLoop,
@@ -40,7 +71,7 @@ pub enum Token {
 	KwTrue,
 	KwFalse,
 	Special(String),
-	Invalid(TokenType, String),
+	Invalid(Invalid),
 }
 impl fmt::Display for Token {
 	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
@@ -48,10 +79,19 @@ impl fmt::Display for Token {
 			Token::None               => try!(f.write_str("UnknownToken")),
 			Token::Float( ref val, _) => try!(f.write_fmt(format_args!("{}", val))),
 			Token::Int(   ref val, _) => try!(f.write_fmt(format_args!("{}", val))),
+			Token::Char(val) => match char::from_u32(val) {
+				Some(c) => try!(f.write_fmt(format_args!("'{}'", c.escape_default()))),
+				None    => try!(f.write_fmt(format_args!("'\\u{{{:x}}}'", val))),
+			},
 			Token::String(ref val)    => try!(f.write_fmt(format_args!("\"{}\"", val))),
 			Token::Ident( ref val)    => try!(f.write_fmt(format_args!("{}", val))),
 			Token::Symbol(ref val)    => try!(f.write_fmt(format_args!("{}", val))),
 			Token::Comma              => try!(f.write_str(",")),
+			Token::KwFn               => try!(f.write_str("fn")),
+			Token::KwConst            => try!(f.write_str("const")),
+			Token::KwStatic           => try!(f.write_str("static")),
+			Token::KwImport           => try!(f.write_str("import")),
+			Token::KwType             => try!(f.write_str("type")),
 			Token::KwIf               => try!(f.write_str("if")),
 			Token::KwElse             => try!(f.write_str("else")),
 			Token::KwLoop             => try!(f.write_str("loop")),
@@ -59,91 +99,134 @@ impl fmt::Display for Token {
 			Token::KwTrue             => try!(f.write_str("true")),
 			Token::KwFalse            => try!(f.write_str("false")),
 			Token::Special(ref val)   => try!(f.write_fmt(format_args!("@{}", val))),
-			Token::Invalid(_, _)      => try!(f.write_str("invalid"))
+			Token::Invalid(_)         => try!(f.write_str("invalid"))
 		}
 		Ok(())
 	}
 }
 
+/// What is wrong with a token which could not be read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Invalid {
+	/// A number which is malformed apart from its suffix, such as `0b12`.
+	Number(String),
+	/// A number with a suffix which is not a type, such as the `u7` of `1u7`.
+	Suffix(String),
+	/// The body of a character literal which is not a single character or escape.
+	CharLit(String),
+	UnterminatedString,
+	UnterminatedChar,
+	UnterminatedComment,
+	/// A character which does not start any token.
+	Char(char),
+}
+
 #[derive(Clone, Debug, PartialEq)]
 enum TokenType {
 	Comma,
 	Number,
 	Word,
 	String,
+	Char,
 	Symbol,
+	Invalid(Invalid),
 }
 
+/// The symbols which are tokens on their own.
+const SYMBOLS: &'static str = "(){}:=!+-*/%^&|<>.@";
+
+/// The characters of the source along with the byte offset of the next one.
+struct Chars<'a> {
+	string: &'a str,
+	pos: usize,
+	next: Option<char>,
+}
+impl<'a> Chars<'a> {
+	fn new(string: &'a str) -> Chars<'a> {
+		Chars { string: string, pos: 0, next: string.chars().next() }
+	}
+	fn peek(&self) -> Option<&char> {
+		self.next.as_ref()
+	}
+	fn next(&mut self) -> Option<char> {
+		let c = self.next;
+		if let Some(c) = c {
+			self.pos += c.len_utf8();
+			self.next = self.string[self.pos..].chars().next();
+		}
+		c
+	}
+}
+
+/// Raw tokens are their text in the source along with the byte offset it starts at.
 struct Tokenizer {
-	raw_tokens: Vec<(TokenType, String, u32, u32)>,
-	line: u32,
-	column: u32,
+	raw_tokens: Vec<(TokenType, String, usize)>,
 }
 impl Tokenizer {
-	fn add_comma(&mut self) {
+	fn add_comma(&mut self, start: usize) {
 		if self.raw_tokens.is_empty() { return; }
 		match self.raw_tokens[self.raw_tokens.len() - 1] {
-			(TokenType::Comma, _, _, _) => (),
-			(_, _, _, _) => {
-				self.raw_tokens.push((TokenType::Comma, ",".to_string(), self.line, self.column))
+			(TokenType::Comma, _, _) => (),
+			(_, _, _) => {
+				self.raw_tokens.push((TokenType::Comma, ",".to_string(), start))
 			},
 		}
 	}
-	fn add_number(&mut self, number: String) {
-		self.raw_tokens.push((TokenType::Number, number, self.line, self.column));
+	fn add_number(&mut self, number: String, start: usize) {
+		self.raw_tokens.push((TokenType::Number, number, start));
+	}
+	fn add_word(&mut self, word: String, start: usize) {
+		self.raw_tokens.push((TokenType::Word, word, start));
 	}
-	fn add_word(&mut self, word: String) {
-		self.raw_tokens.push((TokenType::Word, word, self.line, self.column));
+	fn add_string(&mut self, string: String, start: usize) {
+		self.raw_tokens.push((TokenType::String, string, start));
 	}
-	fn add_string(&mut self, string: String) {
-		self.raw_tokens.push((TokenType::String, string, self.line, self.column));
+	fn add_char(&mut self, body: String, start: usize) {
+		self.raw_tokens.push((TokenType::Char, body, start));
 	}
-	fn add_symbol(&mut self, sym: char) {
+	/// Adds a token which could not be read, whose text is only what it starts with.
+	fn add_invalid(&mut self, invalid: Invalid, text: &str, start: usize) {
+		self.raw_tokens.push((TokenType::Invalid(invalid), text.to_string(), start));
+	}
+	fn add_symbol(&mut self, sym: char, start: usize) {
 		let mut token = String::new();
 		token.push(sym);
-		self.raw_tokens.push((TokenType::Symbol, token, self.line, self.column));
+		self.raw_tokens.push((TokenType::Symbol, token, start));
 	}
 
 	fn tokenize(string: &str) -> Vec<FullToken> {
 		let mut tokenizer = Tokenizer {
 			raw_tokens: Vec::new(),
-			line: 1,
-			column: 1,
 		};
 		tokenizer.tokenize_raw(string);
-		tokenizer.interpret_raw()
+		tokenizer.interpret_raw(string)
 	}
 	fn tokenize_raw(&mut self, string: &str) {
-		self.do_whitespace(string.chars().peekable())
+		self.do_whitespace(Chars::new(string))
 	}
 
-	fn do_whitespace(&mut self, mut iter: Peekable<Chars>) {
+	fn do_whitespace(&mut self, mut iter: Chars) {
 		loop {
 			match *iter.peek().unwrap_or(&'\0') {
-				'\n' => {
-					self.add_comma();
-					self.line += 1;
-					self.column = 0;
-				},
+				'\n' => self.add_comma(iter.pos),
 				c if c.is_whitespace()             => (),
 				c if c.is_alphabetic() || c == '_' => return self.do_word(iter),
 				c if c.is_digit(10)    || c == '.' => return self.do_number(iter),
 				_                                  => return self.do_symbol(iter),
 			}
 			iter.next();
-			self.column += 1;
 		}
 	}
-	fn do_word(&mut self, mut iter: Peekable<Chars>) {
+	fn do_word(&mut self, mut iter: Chars) {
+		let start = iter.pos;
 		let mut word = String::new();
 		word.push(iter.next().unwrap());
-		self.column += 1;
 		loop {
 			let c = *iter.peek().unwrap_or(&'\0');
 			if c.is_alphanumeric() || c == '_' {
 				word.push(c);
 			} else {
-				self.add_word(word);
+				self.add_word(word, start);
 				if c.is_whitespace() {
 					return self.do_whitespace(iter);
 				} else {
@@ -151,16 +234,15 @@ impl Tokenizer {
 				}
 			}
 			iter.next();
-			self.column += 1;
 		}
 	}
 
-	fn do_number(&mut self, mut iter: Peekable<Chars>) {
+	fn do_number(&mut self, mut iter: Chars) {
+		let start = iter.pos;
 		let mut number = String::new();
 		number.push(iter.next().unwrap());
 		let mut prev_e   = false;
-		let mut prev_dot = false;
-		self.column += 1;
+		let mut prev_dot = number == "."; // so that `module.name` is not a number
 		loop {
 			let c = *iter.peek().unwrap_or(&'\0');
 			if c.is_numeric() || ((c.is_alphabetic() || c == '_') && !prev_dot) ||
@@ -169,7 +251,7 @@ impl Tokenizer {
 				prev_dot = c == '.';
 				number.push(c);
 			} else {
-				self.add_number(number);
+				self.add_number(number, start);
 				if c.is_whitespace() {
 					return self.do_whitespace(iter);
 				} else {
@@ -177,122 +259,184 @@ impl Tokenizer {
 				}
 			}
 			iter.next();
-			self.column += 1;
 		}
 	}
-	fn do_symbol(&mut self, mut iter: Peekable<Chars>) {
+	fn do_symbol(&mut self, mut iter: Chars) {
+		let start = iter.pos;
 		match *iter.peek().unwrap_or(&'\0') {
 			'\0' => return,
 			'"' => {
 				// Strings!
 				let mut token = String::new();
 				token.push(iter.next().unwrap());
-				self.column += 1;
 				loop {
-					self.column += 1;
-					match iter.next().unwrap_or('\0') {
-						'\0' => return, // TODO: error (unterminated quote)
-						'\\' => {
+					match iter.next() {
+						None => return self.add_invalid(Invalid::UnterminatedString, "\"", start),
+						Some('\\') => {
 							token.push('\\');
-							token.push(iter.next().unwrap_or('\0'));
-							self.column += 1;
+							match iter.next() {
+								Some(c) => token.push(c),
+								None    => return self.add_invalid(Invalid::UnterminatedString, "\"", start),
+							}
 						},
-						'"' => {
+						Some('"') => {
 							token.push('"');
-							self.add_string(token);
+							self.add_string(token, start);
+							return self.do_symbol(iter);
+						},
+						Some(c) => token.push(c),
+					}
+				}
+			},
+			'\'' => {
+				// Characters! Only the body is kept. They end at the end of the line, so that the
+				// rest of the file is still read if the quote is missing.
+				let mut body = String::new();
+				iter.next();
+				loop {
+					match iter.peek().cloned() {
+						None | Some('\n') => {
+							self.add_invalid(Invalid::UnterminatedChar, "'", start);
 							return self.do_symbol(iter);
 						},
-						c => token.push(c),
+						Some('\\') => {
+							body.push('\\');
+							iter.next();
+							if let Some(c) = iter.peek().cloned() {
+								if c != '\n' {
+									body.push(c);
+									iter.next();
+								}
+							}
+						},
+						Some('\'') => {
+							iter.next();
+							self.add_char(body, start);
+							return self.do_symbol(iter);
+						},
+						Some(c) => {
+							body.push(c);
+							iter.next();
+						},
 					}
 				}
 			},
 			'/' => {
 				// Comments!
 				iter.next().unwrap();
-				self.column += 1;
 				match *iter.peek().unwrap_or(&'\0') {
-					'\0' => return self.add_symbol('/'),
+					'\0' => return self.add_symbol('/', start),
 					'*' => {
+						iter.next();
+						let mut prev = '\0';
 						loop {
-							self.column += 2;
-							iter.next();
-							match iter.next().unwrap_or('\0') {
-								'\0' => return, // TODO: error (unterminated comment)
-								'*'  => if iter.next().unwrap_or('\0') == '/' {
-									return self.do_symbol(iter);
-								},
-								_ => (),
+							match iter.next() {
+								None => return self.add_invalid(Invalid::UnterminatedComment, "/*", start),
+								Some('/') if prev == '*' => return self.do_symbol(iter),
+								Some(c) => prev = c,
 							}
 						}
 					},
 					'/' => {
-						self.column = 1;
+						// The newline is left to end the statement.
 						loop {
-							match iter.next().unwrap_or('\0') {
-								'\n' => return self.do_symbol(iter),
+							match *iter.peek().unwrap_or(&'\0') {
+								'\n' => return self.do_whitespace(iter),
 								'\0' => return,
-								_    => (),
+								_    => { iter.next(); },
 							}
 						}
 					},
-					_ => (),
+					_ => self.add_symbol('/', start),
 				}
 			},
 			',' => {
 				iter.next();
-				self.column += 1;
-				self.add_comma();
+				self.add_comma(start);
 			},
 			c if c.is_whitespace()             => return self.do_whitespace(iter),
 			c if c.is_alphabetic() || c == '_' => return self.do_word(iter),
 			c if c.is_digit(10)    || c == '.' => return self.do_number(iter),
 			_ => {
-				self.column += 1;
-				self.add_symbol(iter.next().unwrap())
+				let c = iter.next().unwrap();
+				self.add_symbol(c, start)
 			},
 		}
 		self.do_symbol(iter);
 	}
 
-	fn interpret_raw(self) -> Vec<FullToken> {
+	fn interpret_raw(self, source: &str) -> Vec<FullToken> {
+		let line_starts: Vec<usize> = Some(0).into_iter()
+			.chain(source.match_indices('\n').map(|(i, _)| i + 1))
+			.collect();
 		let mut tokens = Vec::new();
-		let mut prev_special = false;
-		for (token_type, string, ln, mut clm) in self.raw_tokens.into_iter() {
-			if clm > 0 { clm -= 1; }
-			match token_type {
-				TokenType::Comma => tokens.push(FullToken::new(Token::Comma, ln, clm)),
-				TokenType::Word  => if prev_special {
-					tokens.push(FullToken::new(Token::Special(string), ln, clm));
+		// The start of the `@` of a special.
+		let mut special_start = None;
+		for (token_type, string, start) in self.raw_tokens.into_iter() {
+			let end = start + match token_type {
+				TokenType::Comma => 1,
+				TokenType::Char  => string.len() + 2,
+				_                => string.len(),
+			};
+			let token = match token_type {
+				TokenType::Comma => Token::Comma,
+				TokenType::Word  => if special_start.is_some() {
+					Token::Special(string)
 				} else {
 					match string.as_ref() {
-						"if"     => tokens.push(FullToken::new(Token::KwIf,          ln, clm)),
-						"else"   => tokens.push(FullToken::new(Token::KwElse,        ln, clm)),
-						"loop"   => tokens.push(FullToken::new(Token::KwLoop,        ln, clm)),
-						"return" => tokens.push(FullToken::new(Token::KwReturn,      ln, clm)),
-						"true"   => tokens.push(FullToken::new(Token::KwTrue,        ln, clm)),
-						"false"  => tokens.push(FullToken::new(Token::KwFalse,       ln, clm)),
-						_        => tokens.push(FullToken::new(Token::Ident(string), ln, clm)),
+						"fn"     => Token::KwFn,
+						"const"  => Token::KwConst,
+						"static" => Token::KwStatic,
+						"import" => Token::KwImport,
+						"type"   => Token::KwType,
+						"if"     => Token::KwIf,
+						"else"   => Token::KwElse,
+						"loop"   => Token::KwLoop,
+						"return" => Token::KwReturn,
+						"true"   => Token::KwTrue,
+						"false"  => Token::KwFalse,
+						_        => Token::Ident(string),
 					}
 				},
-				TokenType::Number => tokens.push(FullToken::new(match parse_num(&string) {
+				TokenType::Number => match parse_num(&string) {
 					Some(token) => token,
-					None        => Token::Invalid(token_type, string),
-				}, ln, clm)),
+					None        => Token::Invalid(match unknown_suffix(&string) {
+						Some(suffix) => Invalid::Suffix(suffix),
+						None         => Invalid::Number(string),
+					}),
+				},
+				TokenType::Char => match parse_char(&string) {
+					Some(val) => Token::Char(val),
+					None      => Token::Invalid(Invalid::CharLit(string)),
+				},
+				TokenType::Invalid(invalid) => Token::Invalid(invalid),
 				TokenType::String => {
 					let s = unsafe { string.slice_unchecked(1, string.len() - 1) }; // slice_chars is unstable...
-					tokens.push(FullToken::new(Token::String(s.to_string()), ln, clm));
+					Token::String(s.to_string())
 				},
 				TokenType::Symbol => {
 					let c = string.chars().next().unwrap();
 					if c == '@' {
-						prev_special = true;
+						special_start = Some(start);
 						continue;
+					} else if SYMBOLS.contains(c) {
+						Token::Symbol(c)
 					} else {
-						tokens.push(FullToken::new(Token::Symbol(c), ln, clm));
+						Token::Invalid(Invalid::Char(c))
 					}
 				},
-			}
-			prev_special = false;
+			};
+			let start = special_start.take().unwrap_or(start);
+			let line = match line_starts.binary_search(&start) {
+				Ok(i)  => i + 1,
+				Err(i) => i,
+			};
+			let column = source[line_starts[line - 1]..start].chars().count() + 1;
+			let mut token = FullToken::new(token, line as u32, column as u32);
+			token.end_column = (column + source[start..end].chars().count()) as u32;
+			token.start = start;
+			token.end   = end;
+			tokens.push(token);
 		}
 		tokens
 	}
@@ -306,6 +450,9 @@ pub fn parse_num(s: &str) -> Option<Token> {
 	};
 	if string.len() >= 2 && b"bqoxd".contains(&string[1]) {
 		parse_int(string)
+	} else if string.ends_with(b"size") {
+		// The `e` of the suffix is not an exponent.
+		parse_int(string)
 	} else if string.contains(&b'e') | string.contains(&b'.') {
 		parse_float(string)
 	} else {
@@ -317,8 +464,10 @@ fn parse_float(string: Vec<u8>) -> Option<Token> {
 	let (ty, last_idx) = match rposition_elem(&string, b'f') {
 		Some(idx) => (match &string[idx..] {
 			b"f"   => FloatType::Unknown,
+			b"f16" => FloatType::F16,
 			b"f32" => FloatType::F32,
 			b"f64" => FloatType::F64,
+			b"f128"=> FloatType::F128,
 			_ => return None,
 		}, idx),
 		None => (FloatType::Unknown, string.len()),
@@ -351,38 +500,48 @@ fn parse_int(string: Vec<u8>) -> Option<Token> {
 		match f64::from_str(from_utf8(body).unwrap()) {
 			Ok(val) => return Some(Token::Float(val, match &string[idx..] {
 				b"f"   => FloatType::Unknown,
+				b"f16" => FloatType::F16,
 				b"f32" => FloatType::F32,
 				b"f64" => FloatType::F64,
+				b"f128"=> FloatType::F128,
 				_ => return None,
 			})),
 			Err(_)  => return None,
 		}
 	} }
 
-	let (ty, last_idx) = match rposition_elem(&string, b'i') {
-		Some(idx) => (match &string[idx..] {
-			b"i"   => NumType::Signed(SignedType::Unknown),
-			b"i8"  => NumType::Signed(SignedType::I8),
-			b"i16" => NumType::Signed(SignedType::I16),
-			b"i32" => NumType::Signed(SignedType::I32),
-			b"i64" => NumType::Signed(SignedType::I64),
-			_ => return None,
-		}, idx),
-		None => match rposition_elem(&string, b'u') {
+	// `usize` contains an `i`, so the pointer-sized suffixes are looked for first.
+	let size_idx = string.len().saturating_sub(5);
+	let (ty, last_idx) = match &string[size_idx..] {
+		b"isize" => (NumType::Signed(SignedType::ISize), size_idx),
+		b"usize" => (NumType::Unsigned(UnsignedType::USize), size_idx),
+		_ => match rposition_elem(&string, b'i') {
 			Some(idx) => (match &string[idx..] {
-				b"u"   => NumType::Unsigned(UnsignedType::Unknown),
-				b"u8"  => NumType::Unsigned(UnsignedType::U8),
-				b"u16" => NumType::Unsigned(UnsignedType::U16),
-				b"u32" => NumType::Unsigned(UnsignedType::U32),
-				b"u64" => NumType::Unsigned(UnsignedType::U64),
+				b"i"   => NumType::Signed(SignedType::Unknown),
+				b"i8"  => NumType::Signed(SignedType::I8),
+				b"i16" => NumType::Signed(SignedType::I16),
+				b"i32" => NumType::Signed(SignedType::I32),
+				b"i64" => NumType::Signed(SignedType::I64),
+				b"i128"=> NumType::Signed(SignedType::I128),
 				_ => return None,
 			}, idx),
-			None => (NumType::Unknown, string.len()),
+			None => match rposition_elem(&string, b'u') {
+				Some(idx) => (match &string[idx..] {
+					b"u"   => NumType::Unsigned(UnsignedType::Unknown),
+					b"u8"  => NumType::Unsigned(UnsignedType::U8),
+					b"u16" => NumType::Unsigned(UnsignedType::U16),
+					b"u32" => NumType::Unsigned(UnsignedType::U32),
+					b"u64" => NumType::Unsigned(UnsignedType::U64),
+					b"u128"=> NumType::Unsigned(UnsignedType::U128),
+					_ => return None,
+				}, idx),
+				None => (NumType::Unknown, string.len()),
+			},
 		},
 	};
 
 	let body  = &string[first_idx..last_idx];
-	let value = match u64::from_str_radix(from_utf8(body).unwrap(), base) {
+	let value = match u128::from_str_radix(from_utf8(body).unwrap(), base) {
 		Ok(val) => val,
 		Err(_)  => return None,
 	};
@@ -390,6 +549,65 @@ fn parse_int(string: Vec<u8>) -> Option<Token> {
 	Some(Token::Int(value, ty))
 }
 
+/// The code point of the body of a character literal: a character or an escape such as `\n`
+/// or `\u{1F600}`.
+fn parse_char(body: &str) -> Option<u32> {
+	let mut chars = body.chars();
+	let val = match chars.next() {
+		Some('\\') => match chars.next() {
+			Some('n')  => '\n' as u32,
+			Some('t')  => '\t' as u32,
+			Some('r')  => '\r' as u32,
+			Some('0')  => 0,
+			Some('\\') => '\\' as u32,
+			Some('\'') => '\'' as u32,
+			Some('"')  => '"' as u32,
+			Some('u')  => {
+				let rest = chars.as_str();
+				if !rest.starts_with('{') || !rest.ends_with('}') { return None; }
+				let hex = &rest[1..rest.len() - 1];
+				if hex.is_empty() || !hex.chars().all(|c| c.is_digit(16)) { return None; }
+				return u32::from_str_radix(hex, 16).ok();
+			},
+			_ => return None,
+		},
+		Some(c) => c as u32,
+		None    => return None,
+	};
+	if chars.next().is_some() { return None; }
+	Some(val)
+}
+
+/// The suffix of a number which could not be read, if it is not the name of a type.
+/// The suffix starts at the first letter which is not a digit or an exponent.
+fn unknown_suffix(string: &str) -> Option<String> {
+	let lower = string.to_lowercase();
+	let digits: Vec<(usize, char)> = lower.char_indices().filter(|&(_, c)| c != '_').collect();
+	let (base, first) = match (digits.get(0), digits.get(1)) {
+		(Some(&(_, '0')), Some(&(_, 'x'))) => (16, 2),
+		(Some(&(_, '0')), Some(&(_, 'b'))) | (Some(&(_, '0')), Some(&(_, 'q'))) |
+		(Some(&(_, '0')), Some(&(_, 'o'))) | (Some(&(_, '0')), Some(&(_, 'd'))) => (10, 2),
+		_ => (10, 0),
+	};
+	let start = (first..digits.len()).find(|&i| {
+		let c = digits[i].1;
+		let exponent = base == 10 && first == 0 && c == 'e' &&
+		               digits.get(i + 1).map_or(false, |&(_, c)| c == '-' || c.is_digit(10));
+		c.is_alphabetic() && !c.is_digit(base) && !exponent
+	});
+	let suffix = match start {
+		Some(i) => &lower[digits[i].0..],
+		None    => return None,
+	};
+	let suffix: String = suffix.chars().filter(|&c| c != '_').collect();
+	match &suffix[..] {
+		"i" | "i8" | "i16" | "i32" | "i64" | "i128" | "isize" |
+		"u" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" |
+		"f" | "f16" | "f32" | "f64" | "f128" => None,
+		_ => Some(suffix),
+	}
+}
+
 fn reduce_num(string: &str) -> Option<Vec<u8>> {
 	if !string.is_ascii() { return None; }
 	let mut out = Vec::new();
@@ -432,9 +650,21 @@ mod test {
 		assert_eq!(parse_num("0b01010111"),
 			Some(Token::Int(87, NumType::Unknown)));
 
+		assert_eq!(parse_num("340282366920938463463374607431768211455u128"),
+			Some(Token::Int(u128::max_value(), NumType::Unsigned(UnsignedType::U128))));
+
+		assert_eq!(parse_num("8usize"),
+			Some(Token::Int(8, NumType::Unsigned(UnsignedType::USize))));
+
 		assert_eq!(parse_num("51f32"),
 			Some(Token::Float(51., FloatType::F32)));
 
+		assert_eq!(parse_num("0.5f16"),
+			Some(Token::Float(0.5, FloatType::F16)));
+
+		assert_eq!(parse_num("2f128"),
+			Some(Token::Float(2., FloatType::F128)));
+
 		assert_eq!(parse_num("2."),
 			Some(Token::Float(2., FloatType::Unknown)));
 
@@ -444,4 +674,51 @@ mod test {
 		assert_eq!(parse_num("12e-2"),
 			Some(Token::Float(12e-2, FloatType::Unknown)));
 	}
+
+	#[test]
+	fn char_test() {
+		let tokens: Vec<Token> = tokenize(r"'a' '\n' '\'' '\u{1F600}' '\u{D800}' 'ab'").into_iter()
+			.map(|token| token.token).collect();
+		assert_eq!(tokens, vec![
+			Token::Char('a' as u32),
+			Token::Char('\n' as u32),
+			Token::Char('\'' as u32),
+			Token::Char(0x1F600),
+			Token::Char(0xD800),
+			Token::Invalid(Invalid::CharLit("ab".to_string())),
+		]);
+	}
+
+	#[test]
+	fn span_test() {
+		let code = "x := \"é\" // note\n\t@print x";
+		let tokens: Vec<(u32, u32, &str)> = tokenize(code).iter()
+			.map(|token| (token.line, token.column, &code[token.start..token.end])).collect();
+		assert_eq!(tokens, vec![
+			(1, 1, "x"), (1, 3, ":"), (1, 4, "="), (1, 6, "\"é\""), (1, 17, "\n"),
+			(2, 2, "@print"), (2, 9, "x"),
+		]);
+		// Columns count characters rather than bytes.
+		let string = &tokenize(code)[3];
+		assert_eq!((string.end_column, string.after().column, string.after().start), (9, 9, 9));
+	}
+
+	#[test]
+	fn invalid_test() {
+		let code = "x := 1u7 + 0b12 + 'ab' # 2\nc := 'a\nd := 0xffz /* a * / \"s\"";
+		let tokens: Vec<(u32, u32, Token)> = tokenize(code).into_iter()
+			.filter(|token| match token.token { Token::Invalid(_) => true, _ => false })
+			.map(|token| (token.line, token.column, token.token)).collect();
+		assert_eq!(tokens, vec![
+			(1, 6, Token::Invalid(Invalid::Suffix("u7".to_string()))),
+			(1, 12, Token::Invalid(Invalid::Number("0b12".to_string()))),
+			(1, 19, Token::Invalid(Invalid::CharLit("ab".to_string()))),
+			(1, 24, Token::Invalid(Invalid::Char('#'))),
+			(2, 6, Token::Invalid(Invalid::UnterminatedChar)),
+			(3, 6, Token::Invalid(Invalid::Suffix("z".to_string()))),
+			(3, 12, Token::Invalid(Invalid::UnterminatedComment)),
+		]);
+		let tokens: Vec<Token> = tokenize("/* a **/ x \"s").into_iter().map(|token| token.token).collect();
+		assert_eq!(tokens, vec![Token::Ident("x".to_string()), Token::Invalid(Invalid::UnterminatedString)]);
+	}
 }
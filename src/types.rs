@@ -1,4 +1,7 @@
-use std::{fmt, result};
+use std::{f32, f64, fmt, result};
+use std::cell::RefCell;
+
+use builder;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Type {
@@ -6,6 +9,11 @@ pub enum Type {
 	Unknown,
 	Num(NumType),
 	Bool,
+	/// A Unicode scalar value.
+	Char,
+	/// A fixed number of lanes of a number type, which are operated on element-wise.
+	Vector(NumType, u32),
+	Fn(FnType),
 }
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum NumType {
@@ -15,31 +23,168 @@ pub enum NumType {
 	Float(FloatType),
 }
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum SignedType   { Unknown, I8, I16, I32, I64 }
+pub enum SignedType   { Unknown, I8, I16, I32, I64, I128, ISize }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnsignedType { Unknown, U8, U16, U32, U64, U128, USize }
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum UnsignedType { Unknown, U8, U16, U32, U64 }
+pub enum FloatType    { Unknown,     F16, F32, F64, F128 }
+
+/// The names of the built-in types, other than those of vectors and functions.
+pub const TYPE_NAMES: [&'static str; 18] = [
+	"U8", "U16", "U32", "U64", "U128", "USize",
+	"I8", "I16", "I32", "I64", "I128", "ISize",
+	"F16", "F32", "F64", "F128", "Bool", "Char",
+];
+/// The names of the families of number types, which constrain type parameters.
+pub const FAMILY_NAMES: [&'static str; 4] = ["Number", "Signed", "Unsigned", "Float"];
+
+/// A function signature. Signatures are interned so that types stay `Copy`,
+/// and two function types are equal exactly when their signatures are.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum FloatType    { Unknown,          F32, F64 }
+pub struct FnType(usize);
+
+thread_local!(static FN_TYPES: RefCell<Vec<(Vec<Type>, Option<Type>)>> = RefCell::new(Vec::new()));
+
+impl FnType {
+	pub fn new(params: Vec<Type>, ret: Option<Type>) -> FnType {
+		FN_TYPES.with(|types| {
+			let mut types = types.borrow_mut();
+			let sig = (params, ret);
+			match types.iter().position(|other| *other == sig) {
+				Some(i) => FnType(i),
+				None    => {
+					types.push(sig);
+					FnType(types.len() - 1)
+				},
+			}
+		})
+	}
+	pub fn params(&self) -> Vec<Type> {
+		FN_TYPES.with(|types| types.borrow()[self.0].0.clone())
+	}
+	pub fn ret(&self) -> Option<Type> {
+		FN_TYPES.with(|types| types.borrow()[self.0].1)
+	}
+}
 impl Type {
 	pub fn from_str(string: &str) -> Type {
+		Type::from_str_with(string, &|_| None)
+	}
+
+	/// Parses a type name, looking up the names which are not built in with `alias`.
+	pub fn from_str_with(string: &str, alias: &Fn(&str) -> Option<Type>) -> Type {
 		// TODO: to upper case
 		match string {
 			"U8"          => Type::Num(NumType::Unsigned(UnsignedType::U8)),
 			"U16"         => Type::Num(NumType::Unsigned(UnsignedType::U16)),
 			"U32"         => Type::Num(NumType::Unsigned(UnsignedType::U32)),
 			"U64"         => Type::Num(NumType::Unsigned(UnsignedType::U64)),
+			"U128"        => Type::Num(NumType::Unsigned(UnsignedType::U128)),
+			"USize"       => Type::Num(NumType::Unsigned(UnsignedType::USize)),
 			"I8"          => Type::Num(NumType::Signed(    SignedType::I8)),
 			"I16"         => Type::Num(NumType::Signed(    SignedType::I16)),
 			"I32" | "Int" => Type::Num(NumType::Signed(    SignedType::I32)),
 			"I64"         => Type::Num(NumType::Signed(    SignedType::I64)),
+			"I128"        => Type::Num(NumType::Signed(    SignedType::I128)),
+			"ISize"       => Type::Num(NumType::Signed(    SignedType::ISize)),
+			"F16"         => Type::Num(NumType::Float(      FloatType::F16)),
 			"F32"         => Type::Num(NumType::Float(      FloatType::F32)),
 			"F64" |"Float"=> Type::Num(NumType::Float(      FloatType::F64)),
+			"F128"        => Type::Num(NumType::Float(      FloatType::F128)),
 			"Bool"        => Type::Bool,
-			_             => Type::Invalid,
+			"Char"        => Type::Char,
+			_ if string.starts_with("fn(") => Type::fn_from_str(&string[3..], alias),
+			_ if string.starts_with("Vec") && string.ends_with('>') => {
+				Type::vector_from_str(&string[3..], alias)
+			},
+			_             => alias(string).unwrap_or(Type::Invalid),
+		}
+	}
+
+	/// Parses the rest of a function type name, `[Type, ]...) [-> Type]`.
+	fn fn_from_str(string: &str, alias: &Fn(&str) -> Option<Type>) -> Type {
+		// Find the closing parenthesis, splitting the parameters at the commas outside of
+		// nested function types.
+		let mut params = Vec::new();
+		let mut depth = 0;
+		let mut start = 0;
+		let mut end = None;
+		for (i, c) in string.char_indices() {
+			match c {
+				'(' => depth += 1,
+				')' if depth > 0 => depth -= 1,
+				')' | ',' if depth == 0 => {
+					let param = string[start..i].trim();
+					if !param.is_empty() { params.push(Type::from_str_with(param, alias)); }
+					start = i + 1;
+					if c == ')' {
+						end = Some(i + 1);
+						break;
+					}
+				},
+				_ => (),
+			}
+		}
+		let rest = match end {
+			Some(end) => string[end..].trim(),
+			None      => return Type::Invalid,
+		};
+		let ret = if rest.is_empty() {
+			None
+		} else if rest.starts_with("->") {
+			Some(Type::from_str_with(rest[2..].trim(), alias))
+		} else {
+			return Type::Invalid;
+		};
+		if params.contains(&Type::Invalid) || ret == Some(Type::Invalid) {
+			return Type::Invalid;
+		}
+		Type::Fn(FnType::new(params, ret))
+	}
+
+	/// Parses the rest of a vector type name, `Lanes<Type>`.
+	fn vector_from_str(string: &str, alias: &Fn(&str) -> Option<Type>) -> Type {
+		let open = match string.find('<') {
+			Some(open) => open,
+			None       => return Type::Invalid,
+		};
+		let lanes = match string[..open].parse() {
+			Ok(lanes) if Type::is_lane_count(lanes) => lanes,
+			_ => return Type::Invalid,
+		};
+		match Type::from_str_with(&string[open + 1..string.len() - 1], alias) {
+			Type::Num(num) => Type::Vector(num, lanes),
+			_              => Type::Invalid,
+		}
+	}
+
+	/// Whether vectors can have the number of lanes: a power of two from 2 to 64.
+	pub fn is_lane_count(lanes: u32) -> bool {
+		lanes >= 2 && lanes <= 64 && lanes.is_power_of_two()
+	}
+
+	/// The type of the lanes of a vector. Other types are their own.
+	pub fn elem(self) -> Type {
+		match self {
+			Type::Vector(num, _) => Type::Num(num),
+			_                    => self,
+		}
+	}
+
+	/// Parses the name of a family of types, as used to constrain type parameters.
+	pub fn family_from_str(string: &str) -> Type {
+		match string {
+			"Number"   => Type::Num(NumType::Unknown),
+			"Signed"   => Type::Num(NumType::Signed(    SignedType::Unknown)),
+			"Unsigned" => Type::Num(NumType::Unsigned(UnsignedType::Unknown)),
+			"Float"    => Type::Num(NumType::Float(      FloatType::Unknown)),
+			_          => Type::Invalid,
 		}
 	}
 
 	/// Merges the types together.
+	/// A partially known type becomes the type it is merged with, while known types only merge
+	/// with themselves, so values of different widths need a `@cast`.
 	/// If they cannot be the same, this returns invalid.
 	pub fn merge(self, right: Type) -> Type {
 		match self.merge_left_to_right(right) {
@@ -56,18 +201,29 @@ impl Type {
 					Type::Num(_) => right,
 					_ => Type::Invalid,
 				},
-				NumType::Unsigned(_) => match right {
+				// Known types only merge with themselves, which was handled above.
+				NumType::Unsigned(UnsignedType::Unknown) => match right {
 					Type::Num(NumType::Unsigned(_)) => right,
 					_ => Type::Invalid,
 				},
-				NumType::Signed(_) => match right {
+				NumType::Signed(SignedType::Unknown) => match right {
 					Type::Num(NumType::Signed(_)) => right,
 					_ => Type::Invalid,
 				},
-				NumType::Float(_) => match right {
+				NumType::Float(FloatType::Unknown) => match right {
 					Type::Num(NumType::Float(_)) => right,
 					_ => Type::Invalid,
 				},
+				_ => Type::Invalid,
+			},
+			Type::Vector(num, lanes) => match right {
+				Type::Vector(right_num, right_lanes) if lanes == right_lanes => {
+					match Type::Num(num).merge_left_to_right(Type::Num(right_num)) {
+						Type::Num(merged) => Type::Vector(merged, lanes),
+						_ => Type::Invalid,
+					}
+				},
+				_ => Type::Invalid,
 			},
 			_ => Type::Invalid,
 		}
@@ -87,6 +243,10 @@ impl Type {
 				NumType::Float(      FloatType::Unknown)  => NumType::Float(      FloatType::F64),
 				_ => num,
 			}),
+			Type::Vector(num, lanes) => match Type::Num(num).complete() {
+				Type::Num(num) => Type::Vector(num, lanes),
+				_ => unreachable!(),
+			},
 			_ => self,
 		}
 	}
@@ -98,9 +258,61 @@ impl Type {
 			Type::Num(NumType::Unsigned(UnsignedType::Unknown)) => false,
 			Type::Num(NumType::Signed(    SignedType::Unknown)) => false,
 			Type::Num(NumType::Float(      FloatType::Unknown)) => false,
+			Type::Vector(num, _) => Type::Num(num).is_known(),
 			_ => true,
 		}
 	}
+
+	/// The width in bits of an integer type.
+	pub fn int_width(&self) -> Option<u32> {
+		match *self {
+			Type::Num(NumType::Signed(SignedType::I8 ))      |
+			Type::Num(NumType::Unsigned(UnsignedType::U8 ))  => Some(8),
+			Type::Num(NumType::Signed(SignedType::I16))      |
+			Type::Num(NumType::Unsigned(UnsignedType::U16))  => Some(16),
+			Type::Num(NumType::Signed(SignedType::I32))      |
+			Type::Num(NumType::Unsigned(UnsignedType::U32))  => Some(32),
+			Type::Num(NumType::Signed(SignedType::I64))      |
+			Type::Num(NumType::Unsigned(UnsignedType::U64))  => Some(64),
+			Type::Num(NumType::Signed(SignedType::I128))     |
+			Type::Num(NumType::Unsigned(UnsignedType::U128)) => Some(128),
+			Type::Num(NumType::Signed(SignedType::ISize))    |
+			Type::Num(NumType::Unsigned(UnsignedType::USize))=> Some(builder::pointer_width()),
+			_ => None,
+		}
+	}
+	/// The width in bits of a float type.
+	pub fn float_width(&self) -> Option<u32> {
+		match *self {
+			Type::Num(NumType::Float(FloatType::F16))  => Some(16),
+			Type::Num(NumType::Float(FloatType::F32))  => Some(32),
+			Type::Num(NumType::Float(FloatType::F64))  => Some(64),
+			Type::Num(NumType::Float(FloatType::F128)) => Some(128),
+			_ => None,
+		}
+	}
+	/// The smallest and largest values of an integer type.
+	/// The largest `U128` does not fit in an `i128`, so the largest value is a `u128`.
+	pub fn int_bounds(&self) -> Option<(i128, u128)> {
+		let width = match self.int_width() {
+			Some(width) => width,
+			None        => return None,
+		};
+		match *self {
+			Type::Num(NumType::Signed(_)) => Some((-1 << (width - 1), (1 << (width - 1)) - 1)),
+			_                             => Some((0, !0 >> (128 - width))),
+		}
+	}
+	/// The largest finite value of a float type. The smallest is its negation.
+	/// That of `F128` does not fit in an `f64`, so it has none.
+	pub fn float_max(&self) -> Option<f64> {
+		match *self {
+			Type::Num(NumType::Float(FloatType::F16)) => Some(65504.0),
+			Type::Num(NumType::Float(FloatType::F32)) => Some(f32::MAX as f64),
+			Type::Num(NumType::Float(FloatType::F64)) => Some(f64::MAX),
+			_ => None,
+		}
+	}
 }
 impl fmt::Display for Type {
 	fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
@@ -108,20 +320,35 @@ impl fmt::Display for Type {
 			Type::Invalid                                      => f.write_str("Invalid"),
 			Type::Unknown                                      => f.write_str("Unknown"),
 			Type::Bool                                         => f.write_str("Bool"),
+			Type::Char                                         => f.write_str("Char"),
 			Type::Num(NumType::Unknown)                        => f.write_str("Number"),
 			Type::Num(NumType::Signed(    SignedType::Unknown))=> f.write_str("Signed"),
 			Type::Num(NumType::Signed(    SignedType::I8 ))    => f.write_str("I8"),
 			Type::Num(NumType::Signed(    SignedType::I16))    => f.write_str("I16"),
 			Type::Num(NumType::Signed(    SignedType::I32))    => f.write_str("I32"),
 			Type::Num(NumType::Signed(    SignedType::I64))    => f.write_str("I64"),
+			Type::Num(NumType::Signed(    SignedType::I128))   => f.write_str("I128"),
+			Type::Num(NumType::Signed(    SignedType::ISize))  => f.write_str("ISize"),
 			Type::Num(NumType::Unsigned(UnsignedType::Unknown))=> f.write_str("Unsigned"),
 			Type::Num(NumType::Unsigned(UnsignedType::U8 ))    => f.write_str("U8"),
 			Type::Num(NumType::Unsigned(UnsignedType::U16))    => f.write_str("U16"),
 			Type::Num(NumType::Unsigned(UnsignedType::U32))    => f.write_str("U32"),
 			Type::Num(NumType::Unsigned(UnsignedType::U64))    => f.write_str("U64"),
+			Type::Num(NumType::Unsigned(UnsignedType::U128))   => f.write_str("U128"),
+			Type::Num(NumType::Unsigned(UnsignedType::USize))  => f.write_str("USize"),
 			Type::Num(NumType::Float(      FloatType::Unknown))=> f.write_str("Float"),
+			Type::Num(NumType::Float(      FloatType::F16))    => f.write_str("F16"),
 			Type::Num(NumType::Float(      FloatType::F32))    => f.write_str("F32"),
 			Type::Num(NumType::Float(      FloatType::F64))    => f.write_str("F64"),
+			Type::Num(NumType::Float(      FloatType::F128))   => f.write_str("F128"),
+			Type::Vector(num, lanes) => f.write_fmt(format_args!("Vec{}<{}>", lanes, Type::Num(num))),
+			Type::Fn(ref fn_type) => {
+				let params: Vec<String> = fn_type.params().iter().map(|ty| ty.to_string()).collect();
+				match fn_type.ret() {
+					Some(ret) => f.write_fmt(format_args!("fn({}) -> {}", params.join(", "), ret)),
+					None      => f.write_fmt(format_args!("fn({})", params.join(", "))),
+				}
+			},
 		}.unwrap();
 		Ok(())
 	}